@@ -0,0 +1,398 @@
+// Per cell memory of the last few generations, for shading cells by how old
+// they are or how busy they have been. Neither is free: ages cost 2 bytes a
+// cell and activity 5, plus a pass over the universe every generation. Snapshots
+// share them through an Arc rather than each getting a copy, which would be
+// another 2 or 5 bytes a cell to copy every generation on top of that pass.
+// AliveBefore only remembers the one generation, for picking out births and deaths
+use super::b_matrix_vector::*;
+
+// top bit of an age says the cell is alive, the rest counts generations
+const ALIVE_BIT: u16 = 1 << 15;
+/// Ages stop counting here
+pub const MAX_AGE: u32 = (ALIVE_BIT - 1) as u32;
+/// Most generations Activity can look back over, one bit each
+pub const MAX_ACTIVITY_WINDOW: u32 = 32;
+
+/// How many generations each cell has been alive for, or dead for. Anything
+/// not DEAD counts as alive, so dying states are still alive here
+pub struct CellAges {
+    width: i32,
+    ages: Vec<u16>,
+}
+
+impl CellAges {
+    pub fn new() -> Self {
+        CellAges { width: 0, ages: Vec::new() }
+    }
+    /// Starts counting from vec. Nobody knows when those cells were born, so
+    /// they are newborns, and the dead ones have been dead forever
+    pub fn reset(&mut self, vec: &BMatrixVector) {
+        self.width = vec.width();
+        self.ages.clear();
+        self.ages
+            .extend(vec.iter().map(|&cell| if cell == DEAD { MAX_AGE as u16 } else { ALIVE_BIT }));
+    }
+    /// reset, but only for the cells at these indices, e.g. the ones an edit changed
+    pub fn reset_cells(&mut self, vec: &BMatrixVector, cells: impl Iterator<Item = usize>) {
+        if self.ages.len() != vec.len() {
+            self.reset(vec);
+            return;
+        }
+        for index in cells {
+            self.ages[index] = if vec[index] == DEAD { MAX_AGE as u16 } else { ALIVE_BIT };
+        }
+    }
+    /// Generations have to come in one at a time, each one is a year older
+    pub fn observe(&mut self, vec: &BMatrixVector) {
+        if self.ages.len() != vec.len() {
+            self.reset(vec);
+            return;
+        }
+        for (age, &cell) in self.ages.iter_mut().zip(vec.iter()) {
+            *age = older(*age, cell);
+        }
+    }
+    /// previous observing vec, but left in here, so previous can stay where
+    /// it is without being copied first
+    pub fn observe_from(&mut self, previous: &CellAges, vec: &BMatrixVector) {
+        if previous.ages.len() != vec.len() {
+            self.reset(vec);
+            return;
+        }
+        self.width = previous.width;
+        self.ages.clear();
+        self.ages
+            .extend(previous.ages.iter().zip(vec.iter()).map(|(&age, &cell)| older(age, cell)));
+    }
+    fn age(&self, i: i32, j: i32) -> Option<u16> {
+        if i < 0 || j < 0 || i >= self.width {
+            return None;
+        }
+        self.ages.get((j * self.width + i) as usize).copied()
+    }
+    /// Generations since (i, j) was born, 0 the generation it is born in.
+    /// None if it is dead
+    pub fn since_birth(&self, i: i32, j: i32) -> Option<u32> {
+        self.age(i, j)
+            .filter(|age| age & ALIVE_BIT != 0)
+            .map(|age| (age & !ALIVE_BIT) as u32)
+    }
+    /// Generations since (i, j) was last alive, 0 the generation it dies in.
+    /// None if it is alive, or has not been in MAX_AGE generations
+    pub fn since_alive(&self, i: i32, j: i32) -> Option<u32> {
+        self.age(i, j)
+            .filter(|&age| age & ALIVE_BIT == 0 && (age as u32) < MAX_AGE)
+            .map(|age| age as u32)
+    }
+}
+
+// a generation on from age, with the cell now in state cell
+fn older(age: u16, cell: CellState) -> u16 {
+    let was_alive = age & ALIVE_BIT != 0;
+    match (was_alive, cell != DEAD) {
+        (true, true) => ALIVE_BIT | (age & !ALIVE_BIT).saturating_add(1).min(MAX_AGE as u16),
+        (false, false) => age.saturating_add(1).min(MAX_AGE as u16),
+        (false, true) => ALIVE_BIT,
+        (true, false) => 0,
+    }
+}
+
+impl Default for CellAges {
+    fn default() -> Self {
+        CellAges::new()
+    }
+}
+
+// by hand so handing a copy over every generation reuses the allocation
+impl Clone for CellAges {
+    fn clone(&self) -> Self {
+        CellAges { width: self.width, ages: self.ages.clone() }
+    }
+    fn clone_from(&mut self, source: &Self) {
+        self.width = source.width;
+        self.ages.clone_from(&source.ages);
+    }
+}
+
+/// How many of the last `window` generations each cell changed state in
+pub struct Activity {
+    window: u32,
+    width: i32,
+    // bit k is whether the cell changed k generations ago
+    changes: Vec<u32>,
+    last: Vec<CellState>,
+}
+
+impl Activity {
+    /// window is capped at MAX_ACTIVITY_WINDOW
+    pub fn new(window: u32) -> Self {
+        Activity {
+            window: window.min(MAX_ACTIVITY_WINDOW),
+            width: 0,
+            changes: Vec::new(),
+            last: Vec::new(),
+        }
+    }
+    pub fn window(&self) -> u32 {
+        self.window
+    }
+    /// Starts watching from vec, with nothing having changed yet
+    pub fn reset(&mut self, vec: &BMatrixVector) {
+        self.width = vec.width();
+        self.changes.clear();
+        self.changes.resize(vec.len(), 0);
+        self.last.clear();
+        self.last.extend_from_slice(vec);
+    }
+    /// reset, but only for the cells at these indices
+    pub fn reset_cells(&mut self, vec: &BMatrixVector, cells: impl Iterator<Item = usize>) {
+        if self.last.len() != vec.len() {
+            self.reset(vec);
+            return;
+        }
+        for index in cells {
+            self.changes[index] = 0;
+            self.last[index] = vec[index];
+        }
+    }
+    /// Generations have to come in one at a time
+    pub fn observe(&mut self, vec: &BMatrixVector) {
+        if self.last.len() != vec.len() {
+            self.reset(vec);
+            return;
+        }
+        let kept = self.kept();
+        for ((changes, last), &cell) in self.changes.iter_mut().zip(self.last.iter_mut()).zip(vec.iter()) {
+            *changes = (*changes << 1 | (*last != cell) as u32) & kept;
+            *last = cell;
+        }
+    }
+    /// previous observing vec, but left in here, the same as CellAges::observe_from
+    pub fn observe_from(&mut self, previous: &Activity, vec: &BMatrixVector) {
+        self.window = previous.window;
+        if previous.last.len() != vec.len() {
+            self.reset(vec);
+            return;
+        }
+        self.width = previous.width;
+        let kept = self.kept();
+        self.changes.clear();
+        self.changes.extend(
+            previous.changes.iter().zip(previous.last.iter()).zip(vec.iter())
+                .map(|((&changes, &last), &cell)| (changes << 1 | (last != cell) as u32) & kept),
+        );
+        self.last.clear();
+        self.last.extend_from_slice(vec);
+    }
+    fn kept(&self) -> u32 {
+        if self.window == MAX_ACTIVITY_WINDOW { u32::MAX } else { (1 << self.window) - 1 }
+    }
+    /// 0 for anything off the grid
+    pub fn changes(&self, i: i32, j: i32) -> u32 {
+        if i < 0 || j < 0 || i >= self.width {
+            return 0;
+        }
+        self.changes
+            .get((j * self.width + i) as usize)
+            .map_or(0, |changes| changes.count_ones())
+    }
+}
+
+impl Clone for Activity {
+    fn clone(&self) -> Self {
+        Activity {
+            window: self.window,
+            width: self.width,
+            changes: self.changes.clone(),
+            last: self.last.clone(),
+        }
+    }
+    fn clone_from(&mut self, source: &Self) {
+        self.window = source.window;
+        self.width = source.width;
+        self.changes.clone_from(&source.changes);
+        self.last.clone_from(&source.last);
+    }
+}
+
+/// Which cells were alive the generation before a snapshot's, one bit each,
+/// so a renderer can tell what was born or died on the way to it
+#[derive(Clone, Default)]
+pub struct AliveBefore {
+    width: i32,
+    bits: Vec<u64>,
+}
+
+impl AliveBefore {
+    /// Remembers vec, the generation before the next one to be published
+    pub fn record(&mut self, vec: &BMatrixVector) {
+        self.width = vec.width();
+        self.bits.clear();
+        self.bits.resize(vec.len().div_ceil(64), 0);
+        for (index, &cell) in vec.iter().enumerate() {
+            if cell != DEAD {
+                self.bits[index / 64] |= 1 << (index % 64);
+            }
+        }
+    }
+    /// false for anything off the grid
+    pub fn was_alive(&self, i: i32, j: i32) -> bool {
+        if i < 0 || j < 0 || i >= self.width {
+            return false;
+        }
+        let index = (j * self.width + i) as usize;
+        self.bits.get(index / 64).is_some_and(|bits| bits & 1 << (index % 64) != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::b_matrix::{create_engine, Backend};
+    use crate::patterns::PatternBuilder;
+
+    fn run(vec: &BMatrixVector, generations: u32, mut observe: impl FnMut(&BMatrixVector)) -> BMatrixVector {
+        let mut engine = create_engine(Backend::Single);
+        let mut old_vec = vec.clone();
+        let mut new_vec = vec.clone();
+        for _ in 0..generations {
+            engine.next_b_matrix(&old_vec, &mut new_vec);
+            observe(&new_vec);
+            std::mem::swap(&mut old_vec, &mut new_vec);
+        }
+        old_vec
+    }
+
+    #[test]
+    fn test_CellAges_blinker() {
+        // vertical blinker down (5,4) (5,5) (5,6)
+        let seed = PatternBuilder::with_size(12, 12).make_blinker(5, 4).build();
+        let mut ages = CellAges::new();
+        ages.reset(&seed);
+        assert_eq!(ages.since_birth(5, 5), Some(0));
+        assert_eq!(ages.since_alive(4, 5), None);
+
+        run(&seed, 3, |vec| ages.observe(vec));
+        // horizontal after 3, its ends born in the last generation
+        assert_eq!(ages.since_birth(5, 5), Some(3));
+        assert_eq!(ages.since_birth(4, 5), Some(0));
+        assert_eq!(ages.since_birth(5, 4), None);
+        assert_eq!(ages.since_alive(5, 4), Some(0));
+        assert_eq!(ages.since_alive(5, 5), None);
+        // never alive
+        assert_eq!(ages.since_alive(0, 0), None);
+        assert_eq!(ages.since_birth(-1, 0), None);
+        assert_eq!(ages.since_birth(12, 0), None);
+    }
+
+    #[test]
+    fn test_CellAges_stop_at_max_age() {
+        let seed = PatternBuilder::with_size(6, 6).make_square(1, 1).build();
+        let mut ages = CellAges::new();
+        ages.reset(&seed);
+        for _ in 0..MAX_AGE + 5 {
+            ages.observe(&seed);
+        }
+        assert_eq!(ages.since_birth(1, 1), Some(MAX_AGE));
+        // dies, and is still remembered until MAX_AGE is up
+        let empty = BMatrixVector::new(6, 6);
+        ages.observe(&empty);
+        assert_eq!(ages.since_alive(1, 1), Some(0));
+        for _ in 0..MAX_AGE - 1 {
+            ages.observe(&empty);
+        }
+        assert_eq!(ages.since_alive(1, 1), Some(MAX_AGE - 1));
+        ages.observe(&empty);
+        assert_eq!(ages.since_alive(1, 1), None);
+    }
+
+    #[test]
+    fn test_Activity_blinker_and_block() {
+        let seed = PatternBuilder::with_size(16, 16)
+            .make_blinker(5, 4)
+            .make_square(11, 11)
+            .build();
+        let mut activity = Activity::new(8);
+        activity.reset(&seed);
+        run(&seed, 5, |vec| activity.observe(vec));
+        // the ends change every generation, the middle and the block never do
+        assert_eq!(activity.changes(4, 5), 5);
+        assert_eq!(activity.changes(5, 4), 5);
+        assert_eq!(activity.changes(5, 5), 0);
+        assert_eq!(activity.changes(11, 11), 0);
+        assert_eq!(activity.changes(-1, 5), 0);
+
+        // only the last 8 count
+        run(&seed, 20, |vec| activity.observe(vec));
+        assert_eq!(activity.changes(4, 5), 8);
+        // and once it stops, they drop out. It stopped vertical, so the
+        // end at (5,4) changed once more on the way out
+        let still = PatternBuilder::with_size(16, 16).make_square(11, 11).build();
+        for _ in 0..4 {
+            activity.observe(&still);
+        }
+        assert_eq!(activity.changes(4, 5), 4);
+        assert_eq!(activity.changes(5, 4), 5);
+    }
+
+    #[test]
+    fn test_Activity_window_is_capped() {
+        let mut activity = Activity::new(100);
+        assert_eq!(activity.window(), MAX_ACTIVITY_WINDOW);
+        let (on, off) = (PatternBuilder::with_size(4, 4).make_square(1, 1).build(), BMatrixVector::new(4, 4));
+        activity.reset(&off);
+        for generation in 0..40 {
+            activity.observe(if generation % 2 == 0 { &on } else { &off });
+        }
+        assert_eq!(activity.changes(1, 1), MAX_ACTIVITY_WINDOW);
+    }
+
+    #[test]
+    fn test_observe_from_leaves_previous_alone() {
+        let seed = PatternBuilder::with_size(16, 16)
+            .make_blinker(5, 4)
+            .make_square(11, 11)
+            .build();
+        let (mut ages, mut activity) = (CellAges::new(), Activity::new(4));
+        ages.reset(&seed);
+        activity.reset(&seed);
+        let (mut next_ages, mut next_activity) = (CellAges::new(), Activity::new(1));
+        let next = run(&seed, 1, |vec| {
+            next_ages.observe_from(&ages, vec);
+            next_activity.observe_from(&activity, vec);
+        });
+        assert_eq!(ages.since_birth(4, 5), None);
+        assert_eq!(next_ages.since_birth(4, 5), Some(0));
+        assert_eq!(next_ages.since_birth(5, 5), Some(1));
+        assert_eq!(next_ages.since_alive(5, 4), Some(0));
+        assert_eq!(activity.changes(4, 5), 0);
+        assert_eq!(next_activity.window(), 4);
+        assert_eq!(next_activity.changes(4, 5), 1);
+        assert_eq!(next_activity.changes(5, 5), 0);
+
+        // and carries on from there like observe would have
+        next_ages.observe(&seed);
+        next_activity.observe(&seed);
+        ages.observe(&next);
+        ages.observe(&seed);
+        activity.observe(&next);
+        activity.observe(&seed);
+        for (i, j) in [(4, 5), (5, 4), (5, 5), (11, 11), (0, 0)] {
+            assert_eq!(next_ages.since_birth(i, j), ages.since_birth(i, j));
+            assert_eq!(next_ages.since_alive(i, j), ages.since_alive(i, j));
+            assert_eq!(next_activity.changes(i, j), activity.changes(i, j));
+        }
+    }
+
+    #[test]
+    fn test_AliveBefore_remembers_a_generation() {
+        // wide enough that the cells run across more than one u64
+        let seed = PatternBuilder::with_size(100, 3).make_blinker(70, 0).build();
+        let mut before = AliveBefore::default();
+        assert!(!before.was_alive(70, 1));
+        before.record(&seed);
+        assert!(before.was_alive(70, 0) && before.was_alive(70, 1) && before.was_alive(70, 2));
+        assert!(!before.was_alive(69, 1) && !before.was_alive(0, 0));
+        assert!(!before.was_alive(-1, 0) && !before.was_alive(100, 0) && !before.was_alive(70, 3));
+    }
+}
@@ -1,71 +1,291 @@
 // for globals
 use super::*;
+use rayon::prelude::*;
 use std::ops::{Deref, DerefMut};
 
 #[cfg(test)]
 use mocktopus::macros::*;
 
-// Since we need to copy the init_seed twice into vec & new_vec
-#[derive(Clone)]
+/// 0 is dead and 1 is alive for every rule. What the rest mean is up to the
+/// rule, e.g. the dying states of a Generations rule
+pub type CellState = u8;
+pub const DEAD: CellState = 0;
+pub const ALIVE: CellState = 1;
+
+// Since the triple buffer starts out with a copy in each of its buffers
+#[derive(Clone, PartialEq)]
 // has to be on heap otherwise stack overflow
-pub struct BMatrixVector(pub Vec<bool>);
+pub struct BMatrixVector {
+    cells: Vec<CellState>,
+    // the viewer always runs on a GRID_SIZE by GRID_SIZE universe, but engines
+    // only look at these so they can be exercised on small universes too
+    width: i32,
+    height: i32,
+}
+
+/// Smallest rectangle holding every cell that isn't dead
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BoundingBox {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl BoundingBox {
+    /// Box with corners a and b, in any order, both included
+    pub fn spanning(a: (i32, i32), b: (i32, i32)) -> BoundingBox {
+        BoundingBox {
+            x: a.0.min(b.0),
+            y: a.1.min(b.1),
+            width: (a.0 - b.0).abs() + 1,
+            height: (a.1 - b.1).abs() + 1,
+        }
+    }
+}
 
 // NOTE: For array indexing
 impl Deref for BMatrixVector {
-    type Target = Vec<bool>;
+    type Target = Vec<CellState>;
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.cells
     }
 }
 impl DerefMut for BMatrixVector {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.cells
     }
 }
 
 impl Default for BMatrixVector {
     fn default() -> Self {
-        BMatrixVector(vec![false; (GRID_SIZE * GRID_SIZE) as usize])
+        BMatrixVector::new(GRID_SIZE, GRID_SIZE)
     }
 }
 
 //#[mockable]
 impl BMatrixVector {
-    pub fn new_for_test(vec: Vec<bool>) -> Self {
-        BMatrixVector(vec)
+    pub fn new(width: i32, height: i32) -> Self {
+        BMatrixVector {
+            cells: vec![DEAD; (width * height) as usize],
+            width,
+            height,
+        }
+    }
+    // treats the vector as a single row, which is all the RegionPoolIterMut tests need
+    pub fn new_for_test(vec: Vec<CellState>) -> Self {
+        let width = vec.len() as i32;
+        BMatrixVector {
+            cells: vec,
+            width,
+            height: 1,
+        }
+    }
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+    pub fn in_bounds(&self, i: i32, j: i32) -> bool {
+        i >= 0 && j >= 0 && i < self.width && j < self.height
+    }
+    // For the neighbor counts, where falling off the edge is expected and
+    // just means dead. Cheaper than going through at's error.
+    // Only fully alive cells count, dying ones are not
+    #[inline]
+    pub fn is_alive(&self, i: i32, j: i32) -> bool {
+        self.state(i, j) == ALIVE
+    }
+    /// Dead off the edge, like is_alive
+    #[inline]
+    pub fn state(&self, i: i32, j: i32) -> CellState {
+        if self.in_bounds(i, j) {
+            self.cells[(j * self.width + i) as usize]
+        } else {
+            DEAD
+        }
+    }
+    /// Cells that aren't dead, dying ones included, the way Golly counts them
+    pub fn population(&self) -> usize {
+        self.cells.iter().filter(|&&cell| cell != DEAD).count()
+    }
+    /// Kills every cell in a state of states or more, for a rule with fewer states
+    pub fn drop_states_from(&mut self, states: usize) {
+        for cell in self.cells.iter_mut() {
+            if *cell as usize >= states {
+                *cell = DEAD;
+            }
+        }
+    }
+    // row slice of the whole matrix, so the bounding box can work a row at a time
+    pub fn row(&self, j: i32) -> &[CellState] {
+        let start = (j * self.width) as usize;
+        &self.cells[start..start + self.width as usize]
+    }
+    /// None if everything is dead
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        let occupied = |row: &[CellState]| row.iter().any(|&cell| cell != DEAD);
+        let mut rows = (0..self.height).filter(|&j| occupied(self.row(j)));
+        let top = rows.next()?;
+        let bottom = rows.next_back().unwrap_or(top);
+
+        let (mut left, mut right) = (self.width, 0);
+        for j in top..=bottom {
+            let row = self.row(j);
+            if let Some(first) = row.iter().position(|&cell| cell != DEAD) {
+                left = left.min(first as i32);
+                right = right.max(row.iter().rposition(|&cell| cell != DEAD).unwrap() as i32);
+            }
+        }
+        Some(BoundingBox {
+            x: left,
+            y: top,
+            width: right - left + 1,
+            height: bottom - top + 1,
+        })
+    }
+    /// Average position of the live cells, measured to cell centers. None if everything is dead
+    pub fn centroid(&self) -> Option<(f32, f32)> {
+        if self.width == 0 {
+            return None;
+        }
+        // one pass over a GRID_SIZE universe is a lot for one thread
+        let (count, sum_i, sum_j) = self
+            .cells
+            .par_chunks(self.width as usize)
+            .enumerate()
+            .map(|(j, row)| {
+                let (count, sum_i) = row
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &cell)| cell != DEAD)
+                    .fold((0u64, 0u64), |(count, sum_i), (i, _)| (count + 1, sum_i + i as u64));
+                (count, sum_i, count * j as u64)
+            })
+            .reduce(|| (0, 0, 0), |a, b| (a.0 + b.0, a.1 + b.1, a.2 + b.2));
+        if count == 0 {
+            return None;
+        }
+        Some((
+            (sum_i as f64 / count as f64) as f32 + 0.5,
+            (sum_j as f64 / count as f64) as f32 + 0.5,
+        ))
+    }
+    /// Live cell closest to (i, j), which can be off the grid. Looks a ring
+    /// at a time going out, so a live cell nearby is found quickly
+    pub fn nearest_alive(&self, i: i32, j: i32) -> Option<(i32, i32)> {
+        let distance = |(x, y): (i32, i32)| {
+            let (dx, dy) = ((x - i) as i64, (y - j) as i64);
+            dx * dx + dy * dy
+        };
+        // the ring the furthest corner of the grid is on
+        let last_ring = i.max(self.width - 1 - i).max(j).max(self.height - 1 - j);
+        let mut nearest: Option<(i32, i32)> = None;
+        for ring in 0..=last_ring {
+            // everything from here out is further away in a straight line
+            if nearest.is_some_and(|cell| (ring as i64) * (ring as i64) > distance(cell)) {
+                break;
+            }
+            let mut check = |x: i32, y: i32| {
+                if self.is_alive(x, y) && nearest.is_none_or(|cell| distance((x, y)) < distance(cell)) {
+                    nearest = Some((x, y));
+                }
+            };
+            let (left, right, top, bottom) = (i - ring, i + ring, j - ring, j + ring);
+            for x in left.max(0)..=right.min(self.width - 1) {
+                check(x, top);
+                check(x, bottom);
+            }
+            for y in (top + 1).max(0)..=(bottom - 1).min(self.height - 1) {
+                check(left, y);
+                check(right, y);
+            }
+        }
+        nearest
+    }
+    /// Cells inside region as their own width by height matrix. Whatever
+    /// hangs off the edge of the universe comes back dead
+    pub fn copy_region(&self, region: BoundingBox) -> BMatrixVector {
+        let mut copy = BMatrixVector::new(region.width, region.height);
+        for j in 0..region.height {
+            for i in 0..region.width {
+                copy.cells[(j * region.width + i) as usize] = self.state(region.x + i, region.y + j);
+            }
+        }
+        copy
+    }
+    /// Overwrites the cells under pattern, with its top left corner at (x,y).
+    /// Anything that hangs off the edge of the universe is dropped
+    pub fn paste(&mut self, pattern: &BMatrixVector, x: i32, y: i32) {
+        for j in 0..pattern.height {
+            for i in 0..pattern.width {
+                if self.in_bounds(x + i, y + j) {
+                    self.cells[((y + j) * self.width + x + i) as usize] =
+                        pattern.cells[(j * pattern.width + i) as usize];
+                }
+            }
+        }
+    }
+    pub fn clear_region(&mut self, region: BoundingBox) {
+        self.paste(&BMatrixVector::new(region.width, region.height), region.x, region.y);
+    }
+    /// Turned 90° clockwise, so width and height swap
+    pub fn rotated_clockwise(&self) -> BMatrixVector {
+        let mut rotated = BMatrixVector::new(self.height, self.width);
+        for j in 0..self.height {
+            for i in 0..self.width {
+                // (i,j) ends up in column height-1-j, row i
+                rotated.cells[(i * self.height + self.height - 1 - j) as usize] =
+                    self.cells[(j * self.width + i) as usize];
+            }
+        }
+        rotated
+    }
+    /// Mirrored left to right
+    pub fn flipped_horizontal(&self) -> BMatrixVector {
+        let mut flipped = self.clone();
+        for j in 0..self.height {
+            let start = (j * self.width) as usize;
+            flipped.cells[start..start + self.width as usize].reverse();
+        }
+        flipped
+    }
+    /// Mirrored top to bottom
+    pub fn flipped_vertical(&self) -> BMatrixVector {
+        let mut flipped = BMatrixVector::new(self.width, self.height);
+        for j in 0..self.height {
+            let (from, to) = ((j * self.width) as usize, ((self.height - 1 - j) * self.width) as usize);
+            flipped.cells[to..to + self.width as usize]
+                .copy_from_slice(&self.cells[from..from + self.width as usize]);
+        }
+        flipped
+    }
+    fn out_of_bounds(&self, i: i32, j: i32) -> LifeError {
+        LifeError::OutOfBounds {
+            i,
+            j,
+            width: self.width,
+            height: self.height,
+        }
     }
 }
 
 impl MatrixView for BMatrixVector {
-    type Item = bool;
-    fn at(&self, i: i32, j: i32) -> GameResult<Self::Item> {
-        if i >= 0 && j >= 0 && i < GRID_SIZE && j < GRID_SIZE {
-            //bool is copy type, so moving is fine
-            Ok(self.0[(j * GRID_SIZE + i) as usize])
-        } else if i >= GRID_SIZE || j >= GRID_SIZE {
-            //if i< GRID_SIZE && j<GRID_SIZE && i>=0 && j>=0{
-            Err(GameError::EventLoopError(format!(
-                "IndexError: b_matrix_vector's i must be less than {} and j must be less than {}",
-                GRID_SIZE, GRID_SIZE
-            )))
+    type Item = CellState;
+    fn at(&self, i: i32, j: i32) -> LifeResult<Self::Item> {
+        if self.in_bounds(i, j) {
+            //u8 is copy type, so moving is fine
+            Ok(self.cells[(j * self.width + i) as usize])
         } else {
-            Err(GameError::EventLoopError(
-                "IndexError(b_matrix.at): i and j must be nonnegative".to_string(),
-            ))
-        }
-    }
-    fn at_mut(&mut self, i: i32, j: i32) -> GameResult<&mut Self::Item> {
-        if i >= 0 && j >= 0 && i < GRID_SIZE && j < GRID_SIZE {
-            Ok(&mut self.0[(j * GRID_SIZE + i) as usize])
-        } else if i >= GRID_SIZE || j >= GRID_SIZE {
-            Err(GameError::EventLoopError(format!(
-                "IndexError: b_matrix_vector's i must be less than {} and j must be less than {}",
-                GRID_SIZE, GRID_SIZE
-            )))
+            Err(self.out_of_bounds(i, j))
+        }
+    }
+    fn at_mut(&mut self, i: i32, j: i32) -> LifeResult<&mut Self::Item> {
+        if self.in_bounds(i, j) {
+            Ok(&mut self.cells[(j * self.width + i) as usize])
         } else {
-            Err(GameError::EventLoopError(
-                "IndexError(b_matrix.at): i and j must be nonnegative".to_string(),
-            ))
+            Err(self.out_of_bounds(i, j))
         }
     }
 }
@@ -73,18 +293,43 @@ impl MatrixView for BMatrixVector {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fixtures::*;
     use crate::tests::*;
     #[test]
     fn test_BMatrixVector_index_on_subview() {
         let b_matrix_vector = BMatrixVector::default();
         // Check that a point close to origin
         let value = b_matrix_vector.at(1, 1).unwrap();
-        assert_eq!(value, false);
+        assert_eq!(value, DEAD);
         // Check last point:
         let value = b_matrix_vector
             .at((GRID_SIZE - 1) as i32, (GRID_SIZE - 1) as i32)
             .unwrap();
-        assert_eq!(value, false);
+        assert_eq!(value, DEAD);
+    }
+
+    #[test]
+    fn test_BMatrixVector_at_outOfBounds_has_coordinates() {
+        let b_matrix_vector = BMatrixVector::new(4, 3);
+        match b_matrix_vector.at(4, -1) {
+            Err(LifeError::OutOfBounds {
+                i,
+                j,
+                width,
+                height,
+            }) => assert_eq!((i, j, width, height), (4, -1, 4, 3)),
+            _ => panic!("expected an OutOfBounds error"),
+        }
+    }
+
+    #[test]
+    fn test_BMatrixVector_is_alive_off_edge_is_dead() {
+        let mut b_matrix_vector = BMatrixVector::new(4, 3);
+        *b_matrix_vector.at_mut(3, 2).unwrap() = ALIVE;
+        assert!(b_matrix_vector.is_alive(3, 2));
+        assert!(!b_matrix_vector.is_alive(4, 2));
+        assert!(!b_matrix_vector.is_alive(3, 3));
+        assert!(!b_matrix_vector.is_alive(-1, 0));
     }
 
     #[should_panic]
@@ -95,4 +340,88 @@ mod tests {
 
         let value = b_matrix_vector.at((2 * GRID_SIZE) as i32, 0).unwrap();
     }
+
+    #[test]
+    fn test_BMatrixVector_bounding_box() {
+        let mut b_matrix_vector = BMatrixVector::new(10, 8);
+        assert_eq!(b_matrix_vector.bounding_box(), None);
+        *b_matrix_vector.at_mut(3, 2).unwrap() = ALIVE;
+        assert_eq!(
+            b_matrix_vector.bounding_box(),
+            Some(BoundingBox {
+                x: 3,
+                y: 2,
+                width: 1,
+                height: 1
+            })
+        );
+        *b_matrix_vector.at_mut(7, 6).unwrap() = ALIVE;
+        *b_matrix_vector.at_mut(1, 4).unwrap() = ALIVE;
+        assert_eq!(
+            b_matrix_vector.bounding_box(),
+            Some(BoundingBox {
+                x: 1,
+                y: 2,
+                width: 7,
+                height: 5
+            })
+        );
+        assert_eq!(b_matrix_vector.population(), 3);
+    }
+
+    #[test]
+    fn test_BMatrixVector_centroid() {
+        let mut b_matrix_vector = BMatrixVector::new(10, 8);
+        assert_eq!(b_matrix_vector.centroid(), None);
+        *b_matrix_vector.at_mut(2, 1).unwrap() = ALIVE;
+        *b_matrix_vector.at_mut(6, 5).unwrap() = ALIVE;
+        assert_eq!(b_matrix_vector.centroid(), Some((4.5, 3.5)));
+        assert_eq!(BMatrixVector::new(0, 0).centroid(), None);
+    }
+
+    #[test]
+    fn test_BMatrixVector_nearest_alive() {
+        let mut b_matrix_vector = BMatrixVector::new(20, 12);
+        assert_eq!(b_matrix_vector.nearest_alive(5, 5), None);
+        *b_matrix_vector.at_mut(10, 9).unwrap() = ALIVE;
+        *b_matrix_vector.at_mut(1, 5).unwrap() = ALIVE;
+        *b_matrix_vector.at_mut(17, 11).unwrap() = ALIVE;
+        assert_eq!(b_matrix_vector.nearest_alive(1, 5), Some((1, 5)));
+        assert_eq!(b_matrix_vector.nearest_alive(11, 10), Some((10, 9)));
+        // (10,9) is on a nearer ring, but (1,5) is closer in a straight line
+        assert_eq!(b_matrix_vector.nearest_alive(6, 5), Some((1, 5)));
+        // from off the grid
+        assert_eq!(b_matrix_vector.nearest_alive(40, 30), Some((17, 11)));
+        assert_eq!(b_matrix_vector.nearest_alive(-10, -10), Some((1, 5)));
+    }
+
+    #[test]
+    fn test_BMatrixVector_copy_and_paste_region() {
+        let mut b_matrix_vector = from_rows(&["....", ".OO.", "..O.", "...."]);
+        let region = BoundingBox::spanning((2, 2), (1, 1));
+        assert_eq!(region, BoundingBox { x: 1, y: 1, width: 2, height: 2 });
+        let copy = b_matrix_vector.copy_region(region);
+        assert!(copy == from_rows(&["OO", ".O"]));
+        // hanging off the bottom right corner
+        b_matrix_vector.paste(&copy, 3, 3);
+        assert!(b_matrix_vector == from_rows(&["....", ".OO.", "..O.", "...O"]));
+        assert!(b_matrix_vector.copy_region(BoundingBox::spanning((3, 3), (4, 4))) == from_rows(&["O.", ".."]));
+        b_matrix_vector.clear_region(region);
+        assert!(b_matrix_vector == from_rows(&["....", "....", "....", "...O"]));
+    }
+
+    #[test]
+    fn test_BMatrixVector_rotate_and_flip() {
+        let l = from_rows(&["O..", "OOO"]);
+        assert!(l.rotated_clockwise() == from_rows(&["OO", "O.", "O."]));
+        assert!(l.rotated_clockwise().rotated_clockwise() == from_rows(&["OOO", "..O"]));
+        assert!(l.flipped_horizontal() == from_rows(&["..O", "OOO"]));
+        assert!(l.flipped_vertical() == from_rows(&["OOO", "O.."]));
+        // four turns go all the way round
+        let mut turned = l.clone();
+        for _ in 0..4 {
+            turned = turned.rotated_clockwise();
+        }
+        assert!(turned == l);
+    }
 }
@@ -0,0 +1,212 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use super::b_matrix_vector::*;
+
+/// The universe has been repeating itself every `period` generations,
+/// starting at generation `start_generation`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cycle {
+    pub period: u64,
+    pub start_generation: u64,
+}
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.period == 1 {
+            write!(f, "Stable since generation {}", self.start_generation)
+        } else {
+            write!(
+                f,
+                "Periodic with period {} since generation {}",
+                self.period, self.start_generation
+            )
+        }
+    }
+}
+
+// Only hashes the bounding box plus where it is, so a mostly empty
+// GRID_SIZE universe is cheap. Spaceships change position, so anything
+// with one flying around never counts as periodic
+pub fn hash_generation(vec: &BMatrixVector) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    let bounding_box = vec.bounding_box();
+    bounding_box.hash(&mut hasher);
+    if let Some(bounding_box) = bounding_box {
+        let (left, right) = (bounding_box.x as usize, (bounding_box.x + bounding_box.width) as usize);
+        for j in bounding_box.y..bounding_box.y + bounding_box.height {
+            // cells are bytes, and hashing them in one go is a lot faster
+            // than a write per cell
+            hasher.write(&vec.row(j)[left..right]);
+        }
+    }
+    hasher.finish()
+}
+
+/// Watches one generation at a time and reports once the universe repeats.
+/// Only periods up to the history length get caught, and since only hashes are
+/// kept a collision could in theory report a cycle that isn't there
+pub struct CycleDetector {
+    // (generation, hash), oldest first
+    history: VecDeque<(u64, u64)>,
+    history_len: usize,
+    cycle: Option<Cycle>,
+}
+
+impl CycleDetector {
+    pub fn new(history_len: usize) -> Self {
+        CycleDetector {
+            history: VecDeque::with_capacity(history_len + 1),
+            history_len,
+            cycle: None,
+        }
+    }
+    pub fn history_len(&self) -> usize {
+        self.history_len
+    }
+    pub fn cycle(&self) -> Option<Cycle> {
+        self.cycle
+    }
+    // for a new seed
+    pub fn reset(&mut self) {
+        self.history.clear();
+        self.cycle = None;
+    }
+    /// Generations have to come in one at a time, otherwise the period and
+    /// start could come out as multiples of the real ones
+    pub fn observe(&mut self, generation: u64, vec: &BMatrixVector) -> Option<Cycle> {
+        // a deterministic universe that repeated once repeats forever,
+        // so no need to keep hashing
+        if self.cycle.is_some() {
+            return self.cycle;
+        }
+        let hash = hash_generation(vec);
+        // newest match is the smallest period
+        if let Some(&(previous, _)) = self.history.iter().rev().find(|&&(_, h)| h == hash) {
+            let period = generation - previous;
+            // this is the first repeat we've seen, so the cycle started one period ago
+            self.cycle = Some(Cycle {
+                period,
+                start_generation: previous,
+            });
+            self.history.clear();
+            return self.cycle;
+        }
+        self.history.push_back((generation, hash));
+        if self.history.len() > self.history_len {
+            self.history.pop_front();
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::b_matrix::engine::*;
+    use crate::fixtures::*;
+    use crate::patterns::PatternBuilder;
+    use crate::MatrixView;
+
+    // runs the detector from generation 0 until it finds something
+    fn detect(seed: BMatrixVector, history_len: usize, max_generations: u64) -> Option<Cycle> {
+        let mut engine = create_engine(Backend::Single);
+        let mut detector = CycleDetector::new(history_len);
+        let mut old_vec = seed.clone();
+        let mut new_vec = seed;
+        detector.observe(0, &old_vec);
+        for generation in 1..=max_generations {
+            engine.next_b_matrix(&old_vec, &mut new_vec);
+            if let Some(cycle) = detector.observe(generation, &new_vec) {
+                return Some(cycle);
+            }
+            std::mem::swap(&mut old_vec, &mut new_vec);
+        }
+        None
+    }
+
+    #[test]
+    fn test_CycleDetector_still_life_from_start() {
+        let seed = PatternBuilder::with_size(16, 16).make_square(5, 5).build();
+        assert_eq!(
+            detect(seed, 8, 10),
+            Some(Cycle {
+                period: 1,
+                start_generation: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_CycleDetector_blinker_period_2() {
+        let seed = PatternBuilder::with_size(16, 16).make_blinker(5, 5).build();
+        assert_eq!(
+            detect(seed, 8, 10),
+            Some(Cycle {
+                period: 2,
+                start_generation: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_CycleDetector_finds_when_pattern_settles() {
+        // T-tetromino becomes a traffic light (4 blinkers) at generation 9
+        let seed = PatternBuilder::with_size(32, 32).make_t(14, 14).build();
+        assert_eq!(
+            detect(seed, 8, 40),
+            Some(Cycle {
+                period: 2,
+                start_generation: 9
+            })
+        );
+    }
+
+    #[test]
+    fn test_CycleDetector_everything_dies() {
+        // two cells die off in one generation, and the empty universe is a still life
+        let mut seed = PatternBuilder::with_size(16, 16).build();
+        *seed.at_mut(3, 3).unwrap() = ALIVE;
+        *seed.at_mut(4, 3).unwrap() = ALIVE;
+        assert_eq!(
+            detect(seed, 8, 10),
+            Some(Cycle {
+                period: 1,
+                start_generation: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_CycleDetector_glider_is_not_periodic() {
+        let seed = PatternBuilder::with_size(64, 64).make_glider(40, 40).build();
+        assert_eq!(detect(seed, 16, 60), None);
+    }
+
+    #[test]
+    fn test_CycleDetector_period_longer_than_history_is_missed() {
+        let mut seed = BMatrixVector::new(24, 24);
+        place(&mut seed, 5, 5, &PULSAR);
+        assert_eq!(detect(seed.clone(), 2, 20), None);
+        assert_eq!(
+            detect(seed, 3, 20),
+            Some(Cycle {
+                period: 3,
+                start_generation: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_CycleDetector_reset_forgets_cycle() {
+        let seed = PatternBuilder::with_size(16, 16).make_square(5, 5).build();
+        let mut detector = CycleDetector::new(4);
+        detector.observe(0, &seed);
+        assert!(detector.observe(1, &seed).is_some());
+        detector.reset();
+        assert_eq!(detector.cycle(), None);
+        assert_eq!(detector.observe(0, &seed), None);
+    }
+}
@@ -0,0 +1,120 @@
+use super::*;
+use crate::patterns::PatternBuilder;
+
+/// Change to part of the universe. Queued with BMatrix::queue_edit and applied
+/// by the worker in between generations, so it never lands on a half computed one
+#[derive(Clone)]
+pub enum Edit {
+    /// overwrites the cells under pattern, with its top left corner at (x,y)
+    Paste { pattern: BMatrixVector, x: i32, y: i32 },
+    Clear(BoundingBox),
+    FillRandom {
+        region: BoundingBox,
+        seed: u64,
+        density: f64,
+    },
+    /// 90° clockwise, keeping the top left corner where it is
+    Rotate(BoundingBox),
+    FlipHorizontal(BoundingBox),
+    FlipVertical(BoundingBox),
+}
+
+impl Edit {
+    pub fn apply(&self, vec: &mut BMatrixVector) {
+        match self {
+            Edit::Paste { pattern, x, y } => vec.paste(pattern, *x, *y),
+            Edit::Clear(region) => vec.clear_region(*region),
+            Edit::FillRandom {
+                region,
+                seed,
+                density,
+            } => {
+                let soup = PatternBuilder::with_size(region.width, region.height)
+                    .make_random_seeded((0, 0), region.width, region.height, *seed, *density)
+                    .build();
+                vec.paste(&soup, region.x, region.y);
+            }
+            Edit::Rotate(region) => {
+                let rotated = vec.copy_region(*region).rotated_clockwise();
+                vec.clear_region(*region);
+                vec.paste(&rotated, region.x, region.y);
+            }
+            Edit::FlipHorizontal(region) => {
+                let flipped = vec.copy_region(*region).flipped_horizontal();
+                vec.paste(&flipped, region.x, region.y);
+            }
+            Edit::FlipVertical(region) => {
+                let flipped = vec.copy_region(*region).flipped_vertical();
+                vec.paste(&flipped, region.x, region.y);
+            }
+        }
+    }
+    /// Where the edited cells end up, e.g. so a selection can follow a rotation
+    pub fn region(&self) -> BoundingBox {
+        match self {
+            Edit::Paste { pattern, x, y } => BoundingBox {
+                x: *x,
+                y: *y,
+                width: pattern.width(),
+                height: pattern.height(),
+            },
+            Edit::Rotate(region) => BoundingBox {
+                width: region.height,
+                height: region.width,
+                ..*region
+            },
+            Edit::Clear(region)
+            | Edit::FillRandom { region, .. }
+            | Edit::FlipHorizontal(region)
+            | Edit::FlipVertical(region) => *region,
+        }
+    }
+    /// Every cell the edit may change, where it took them from included
+    pub fn touched(&self) -> BoundingBox {
+        match self {
+            Edit::Rotate(region) => BoundingBox {
+                width: region.width.max(region.height),
+                height: region.width.max(region.height),
+                ..*region
+            },
+            _ => self.region(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MatrixView;
+
+    #[test]
+    fn test_Edit_rotate_keeps_top_left_corner() {
+        // a blinker standing up at (2,1)
+        let mut vec = PatternBuilder::with_size(6, 6).make_blinker(2, 1).build();
+        let region = vec.bounding_box().unwrap();
+        let edit = Edit::Rotate(region);
+        edit.apply(&mut vec);
+        assert_eq!(vec.bounding_box(), Some(edit.region()));
+        assert_eq!(edit.region().width, region.height);
+        assert_eq!(vec.population(), 3);
+    }
+
+    #[test]
+    fn test_Edit_fill_random_is_reproducible() {
+        let region = BoundingBox::spanning((1, 1), (8, 8));
+        let edit = Edit::FillRandom {
+            region,
+            seed: 77,
+            density: 0.5,
+        };
+        let mut a = BMatrixVector::new(10, 10);
+        let mut b = BMatrixVector::new(10, 10);
+        edit.apply(&mut a);
+        edit.apply(&mut b);
+        assert!(a == b);
+        assert!(a.population() > 0);
+        // nothing outside the region
+        assert_eq!(a.population(), a.copy_region(region).population());
+        assert!(a.at(0, 0).unwrap() == DEAD && a.at(9, 9).unwrap() == DEAD);
+    }
+}
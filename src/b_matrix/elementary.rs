@@ -0,0 +1,167 @@
+// Wolfram's elementary cellular automata: a single row where each cell looks
+// at itself and the cells either side of it. Only the bottom row of the grid
+// evolves, and everything above moves up a row every generation, so the grid
+// is a spacetime diagram with the newest generation at the bottom
+use super::*;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Elementary {
+    // bit k is the next state of a cell whose left, middle and right cells
+    // spell out k in binary
+    number: u8,
+}
+
+impl Elementary {
+    pub fn new(number: u8) -> Self {
+        Elementary { number }
+    }
+    #[inline]
+    fn next_in_row(&self, i: i32, j: i32, vec: &BMatrixVector) -> CellState {
+        let neighborhood =
+            (vec.is_alive(i - 1, j) as u8) << 2 | (vec.is_alive(i, j) as u8) << 1 | vec.is_alive(i + 1, j) as u8;
+        if self.number >> neighborhood & 1 == 1 {
+            ALIVE
+        } else {
+            DEAD
+        }
+    }
+}
+
+impl Rule for Elementary {
+    fn states(&self) -> usize {
+        2
+    }
+    fn dimensions(&self) -> usize {
+        1
+    }
+    #[inline]
+    fn next_state(&self, i: i32, j: i32, vec: &BMatrixVector) -> CellState {
+        if j + 1 < vec.height() {
+            vec.state(i, j + 1)
+        } else {
+            self.next_in_row(i, j, vec)
+        }
+    }
+    // everything but the bottom row is a copy of the row under it
+    fn next_cells(&self, start: usize, vec: &BMatrixVector, out: &mut [CellState]) {
+        // no rows means no bottom row either
+        if out.is_empty() {
+            return;
+        }
+        let width = vec.width() as usize;
+        let bottom_row = vec.len() - width;
+        let copied = out.len().min(bottom_row.saturating_sub(start));
+        if copied > 0 {
+            out[..copied].copy_from_slice(&vec[start + width..start + width + copied]);
+        }
+        let (mut i, j) = get_location_from_idx(start + copied, vec.width());
+        for cell in out[copied..].iter_mut() {
+            *cell = self.next_in_row(i, j, vec);
+            i += 1;
+        }
+    }
+}
+
+// W30, the way Golly writes them
+impl fmt::Display for Elementary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "W{}", self.number)
+    }
+}
+
+/// Takes W and a rule number from 0 to 255, e.g. W30 or W110
+impl FromStr for Elementary {
+    type Err = LifeError;
+    fn from_str(rulestring: &str) -> LifeResult<Self> {
+        let trimmed = rulestring.trim();
+        if !trimmed.starts_with(&['W', 'w'][..]) {
+            return Err(invalid_rule(rulestring, "expected something like W30"));
+        }
+        trimmed[1..]
+            .parse::<u8>()
+            .map(Elementary::new)
+            .map_err(|_| invalid_rule(rulestring, "the rule number has to be 0 to 255"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(rulestring: &str) -> Elementary {
+        rulestring.parse().unwrap()
+    }
+
+    // a single live cell in the middle of the bottom row, run for generations
+    fn run_from_one_cell(rule: &Elementary, width: i32, height: i32, generations: usize) -> BMatrixVector {
+        let mut vec = BMatrixVector::new(width, height);
+        *vec.at_mut(width / 2, height - 1).unwrap() = ALIVE;
+        for _ in 0..generations {
+            let mut next = vec.clone();
+            rule.next_cells(0, &vec, &mut next);
+            vec = next;
+        }
+        vec
+    }
+
+    fn row_string(vec: &BMatrixVector, j: i32) -> String {
+        vec.row(j).iter().map(|&cell| if cell == ALIVE { 'o' } else { '.' }).collect()
+    }
+
+    #[test]
+    fn test_Elementary_parse_and_display() {
+        assert_eq!(rule("W30"), Elementary::new(30));
+        assert_eq!(rule(" w110 ").to_string(), "W110");
+        assert_eq!(rule("W0"), Elementary::new(0));
+        assert_eq!(rule("W255"), Elementary::new(255));
+        for bad in ["W256", "W-1", "W", "30", "B3/S23", "Wx"].iter() {
+            assert!(bad.parse::<Elementary>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_Elementary_rule_30_fills_rows_from_the_bottom() {
+        let vec = run_from_one_cell(&rule("W30"), 9, 6, 3);
+        // oldest generation on top, the newest at the bottom
+        assert_eq!(row_string(&vec, 1), ".........");
+        assert_eq!(row_string(&vec, 2), "....o....");
+        assert_eq!(row_string(&vec, 3), "...ooo...");
+        assert_eq!(row_string(&vec, 4), "..oo..o..");
+        assert_eq!(row_string(&vec, 5), ".oo.oooo.");
+    }
+
+    #[test]
+    fn test_Elementary_rule_90_is_sierpinski() {
+        // Pascal's triangle mod 2, so generation 7 is all there and generation 8
+        // is just the two edges
+        let vec = run_from_one_cell(&rule("W90"), 21, 12, 8);
+        assert_eq!(row_string(&vec, 11), "..o...............o..");
+        assert_eq!(row_string(&vec, 10), "...o.o.o.o.o.o.o.o...");
+        assert_eq!(row_string(&vec, 9), "....o...o...o...o....");
+        assert_eq!(vec.row(0).iter().filter(|&&cell| cell == ALIVE).count(), 0);
+    }
+
+    #[test]
+    fn test_Elementary_next_cells_agrees_with_next_state() {
+        let rule = rule("W110");
+        let vec = crate::patterns::PatternBuilder::with_size(17, 5)
+            .make_random_seeded((0, 0), 17, 5, 3, 0.5)
+            .build();
+        let expected: Vec<CellState> = (0..vec.len())
+            .map(|idx| {
+                let (i, j) = get_location_from_idx(idx, vec.width());
+                rule.next_state(i, j, &vec)
+            })
+            .collect();
+        // runs that end before, straddle, and start in the bottom row
+        for &(start, end) in [(0, vec.len()), (5, 40), (60, 80), (70, vec.len())].iter() {
+            let mut out = vec![DEAD; end - start];
+            rule.next_cells(start, &vec, &mut out);
+            assert_eq!(&out[..], &expected[start..end], "from {}", start);
+        }
+        // nothing to do for an empty universe
+        rule.next_cells(0, &BMatrixVector::new(17, 0), &mut []);
+    }
+}
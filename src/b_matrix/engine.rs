@@ -8,6 +8,7 @@ use super::b_matrix_vector::*;
 // for globals
 use super::*;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Backend {
     Single,
     MultiThreaded(i32),
@@ -18,43 +19,66 @@ pub enum Backend {
 
 pub trait Engine{
     fn next_b_matrix(&mut self,old_vec:&BMatrixVector, new_vec: &mut BMatrixVector);
+
+    /// Advances n generations from old_vec and leaves the result in new_vec.
+    /// scratch only holds intermediate generations, so its contents are garbage afterwards
+    fn step_n(&mut self, n: u32, old_vec: &BMatrixVector, new_vec: &mut BMatrixVector, scratch: &mut BMatrixVector){
+        self.step_n_observed(n, old_vec, new_vec, scratch, &mut |_, _| ());
+    }
+
+    /// step_n, but observe sees every intermediate generation along with
+    /// how many generations past old_vec it is (1 to n)
+    fn step_n_observed(&mut self, n: u32, old_vec: &BMatrixVector, new_vec: &mut BMatrixVector, scratch: &mut BMatrixVector, observe: &mut dyn FnMut(u32, &BMatrixVector)){
+        if n == 0 {
+            new_vec.clone_from(old_vec);
+            return;
+        }
+        self.next_b_matrix(old_vec, new_vec);
+        observe(1, new_vec);
+        for step in 2..=n {
+            // ping pong between the two, swap is shallow
+            self.next_b_matrix(new_vec, scratch);
+            mem::swap(new_vec, scratch);
+            observe(step, new_vec);
+        }
+    }
 }
 
+/// Engine running Conway's Life
 pub fn create_engine(update_method: Backend) -> Box<dyn Engine>{
+    create_engine_with_rule(update_method, Arc::new(Generations::life()))
+}
+
+pub fn create_engine_with_rule(update_method: Backend, rule: Arc<dyn Rule>) -> Box<dyn Engine>{
     use Backend::*;
     match update_method{
-        Single => Box::new(SingleThreadEngine::new()),
-        MultiThreaded(worker_count) => Box::new(MultiThreadedEngine::new(worker_count)),
-        Rayon => Box::new(RayonEngine::new()),
+        Single => Box::new(SingleThreadEngine::new(rule)),
+        MultiThreaded(worker_count) => Box::new(MultiThreadedEngine::new(worker_count, rule)),
+        Rayon => Box::new(RayonEngine::new(rule)),
         Skip => Box::new(SkipEngine::new())
     }
 }
 
 // ************  Engine Implementations  ************   
-fn get_location_from_idx(idx: usize) -> (i32, i32) {
+pub(crate) fn get_location_from_idx(idx: usize, width: i32) -> (i32, i32) {
     let idx = idx as i32;
-    let i = idx % GRID_SIZE;
-    //let j = (idx - i) / GRID_SIZE;
-    let j = idx /GRID_SIZE;
+    let i = idx % width;
+    //let j = (idx - i) / width;
+    let j = idx / width;
     (i, j)
 }
 
-struct SingleThreadEngine;
+struct SingleThreadEngine{
+    rule: Arc<dyn Rule>
+}
 impl Engine for SingleThreadEngine{
     fn next_b_matrix(&mut self,old_vec:&BMatrixVector, new_vec: &mut BMatrixVector){
-        for j in 0..GRID_SIZE {
-            for i in 0..GRID_SIZE {
-                let count = life::get_count(i, j,old_vec);
-                let state = old_vec.at(i, j).unwrap();
-                let mut cell_ptr = new_vec.at_mut(i, j).unwrap();
-                *cell_ptr = life::new_cell_value(state, count, old_vec);
-            }
-        }
+        self.rule.next_cells(0, old_vec, &mut new_vec[..]);
     }
 }
 impl SingleThreadEngine{
-    fn new()->Self{
-        SingleThreadEngine{}
+    fn new(rule: Arc<dyn Rule>)->Self{
+        SingleThreadEngine{rule}
     }
 }
 
@@ -71,23 +95,28 @@ impl SkipEngine{
 }
 
 
-struct RayonEngine;
+struct RayonEngine{
+    rule: Arc<dyn Rule>
+}
 impl Engine for RayonEngine{
     fn next_b_matrix(&mut self,old_vec:&BMatrixVector, new_vec: &mut BMatrixVector){
+        // whole rows per job, a few jobs a thread. Rules like Larger than Life
+        // do some setup for every run of cells they get, so the runs are long
+        let width = old_vec.width() as usize;
+        if width == 0 {
+            return;
+        }
+        let rows = (old_vec.height() as usize / (4 * rayon::current_num_threads())).max(1);
+        let rule = &self.rule;
         new_vec
-            .par_iter_mut()
+            .par_chunks_mut(rows * width)
             .enumerate()
-            .for_each(|(idx, cell_ptr)| {
-                let (i, j) = get_location_from_idx(idx);
-                let count = life::get_count(i, j,old_vec);
-                let state = old_vec.at(i, j).unwrap();
-                *cell_ptr = life::new_cell_value(state, count,old_vec);
-            });
+            .for_each(|(chunk, cells)| rule.next_cells(chunk * rows * width, old_vec, cells));
     }
 }
 impl RayonEngine{
-    fn new()->Self{
-        RayonEngine{}
+    fn new(rule: Arc<dyn Rule>)->Self{
+        RayonEngine{rule}
     }
 }
 
@@ -98,7 +127,8 @@ fn get_num_elems_each_time(vector: &BMatrixVector, worker_count: i32) -> i32 {
 
 struct MultiThreadedEngine{
     threadpool: Pool,
-    worker_count: i32
+    worker_count: i32,
+    rule: Arc<dyn Rule>
 }
 impl Engine for MultiThreadedEngine{
     fn next_b_matrix(&mut self,old_vec:&BMatrixVector, new_vec: &mut BMatrixVector){
@@ -109,16 +139,15 @@ impl Engine for MultiThreadedEngine{
         // 3. join to wait
         //
         // need local variable since closures require unique acess to its borrows
+        let rule = self.rule.clone();
+        let rule = &*rule;
         let region_iterator = self.create_iter_mut(new_vec);
         self.threadpool.scoped(|scope| {
             for (slice, iter_offset) in region_iterator {
                 scope.execute(move || {
-                    for (rel_i, cell_ptr) in slice.iter_mut().enumerate() {
-                        let idx = rel_i + iter_offset as usize;
-                        let (i, j) = get_location_from_idx(idx);
-                        let count = life::get_count(i, j,old_vec);
-                        let state = old_vec.at(i, j).unwrap();
-                        *cell_ptr = life::new_cell_value(state, count,old_vec);
+                    // EC: more workers than cells leaves the first ones empty handed
+                    if !slice.is_empty() {
+                        rule.next_cells(iter_offset as usize, old_vec, slice);
                     }
                 });
             }
@@ -127,12 +156,13 @@ impl Engine for MultiThreadedEngine{
     }
 }
 impl MultiThreadedEngine {
-    fn new(worker_count: i32) -> Self {
+    fn new(worker_count: i32, rule: Arc<dyn Rule>) -> Self {
         //let threadpool = Arc::new(ThreadPool::new(worker_count as usize));
         let threadpool = Pool::new(worker_count as u32);
         MultiThreadedEngine {
             threadpool,
             worker_count,
+            rule,
         }
     }
     // EC: worker_count is 1 -> max_offset should be 0, so edge case is fine too
@@ -151,13 +181,13 @@ impl MultiThreadedEngine {
 
 
 struct RegionPoolIterMut<'a> {
-    ptr: &'a mut [bool],
+    ptr: &'a mut [CellState],
     offset: i32,
     num_elems_each_time: i32,
     max_offset: i32,
 }
 impl<'a> Iterator for RegionPoolIterMut<'a> {
-    type Item = (&'a mut [bool], i32);
+    type Item = (&'a mut [CellState], i32);
 
     // EC: at end when we need to take a bit more
     fn next(&mut self) -> Option<Self::Item> {
@@ -177,6 +207,9 @@ impl<'a> Iterator for RegionPoolIterMut<'a> {
                 // But that said, we can't explicitly take, since self.ptr doesn't own its values
                 let slice = mem::replace(&mut self.ptr, &mut []);
                 let l = slice;
+                // EC: more workers than cells -> num_elems_each_time is 0, so
+                // offset wouldn't move and we'd keep landing on this case
+                self.offset = self.max_offset + 1;
 
                 Some((l, old_offset))
             }
@@ -198,66 +231,6 @@ impl<'a> Iterator for RegionPoolIterMut<'a> {
     }
 }
 
-// ************  GAME OF LIFE RULES  ************   
-mod life {
-    use super::*;
-    pub fn convert_bool(i: i32, j: i32, b_matrix_vector: &BMatrixVector) -> u32 {
-        match b_matrix_vector.at(i, j) {
-            Ok(value) => {
-                if value {
-                    1
-                } else {
-                    0
-                }
-            }
-            //EC: off screen
-            Err(_) => 0,
-        }
-    }
-
-    // since we are using this to survey around, x and y can now be negative
-    // but "at" method covers this error handling
-    // TODO: time how fast w/o local variables + refactor into 3 by 3 permutation
-    pub fn get_count(i: i32, j: i32, b_matrix_vector: &BMatrixVector) -> u32 {
-        let mut total = 0;
-        total += convert_bool(i + 1, j, b_matrix_vector);
-        total += convert_bool(i + 1, j + 1, b_matrix_vector);
-        total += convert_bool(i, j + 1, b_matrix_vector);
-        total += convert_bool(i - 1, j + 1, b_matrix_vector);
-        total += convert_bool(i - 1, j, b_matrix_vector);
-        total += convert_bool(i - 1, j - 1, b_matrix_vector);
-        total += convert_bool(i, j - 1, b_matrix_vector);
-        total + convert_bool(i + 1, j - 1, b_matrix_vector)
-        //let total= 0;
-        //for delta_y in -1..2{
-        //for delta_x in -1..2{
-        //total += convert_bool(i+delta_x,j+delta_y);
-        //}
-        //}
-        //return total - convert_bool(i,j,b_matrix_vector)
-    }
-
-    pub fn new_cell_value(state: bool, count: u32, b_matrix_vector: &BMatrixVector) -> bool {
-        match state {
-            //dead transition
-            false => {
-                if count == 3 {
-                    true
-                } else {
-                    false
-                }
-            }
-            //alive transition
-            true => {
-                if count == 2 || count == 3 {
-                    true
-                } else {
-                    false
-                }
-            }
-        }
-    }
-}
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -272,11 +245,11 @@ mod tests {
     #[test]
     fn test_RegionPoolIterMut_next_edge_case() {
         let worker_count = 1;
-        let mut region_pool = MultiThreadedEngine::new(worker_count);
-        let bool_vec = vec![true, true, true, false, false, false, false];
+        let mut region_pool = MultiThreadedEngine::new(worker_count, Arc::new(Generations::life()));
+        let cells = vec![ALIVE, ALIVE, ALIVE, DEAD, DEAD, DEAD, DEAD];
 
-        let test_vec = bool_vec.clone();
-        let mut b_matrix_vector = BMatrixVector::new_for_test(bool_vec);
+        let test_vec = cells.clone();
+        let mut b_matrix_vector = BMatrixVector::new_for_test(cells);
         let mut region_iterator = region_pool.create_iter_mut(&mut b_matrix_vector);
 
         if let Some((whole_slice, offset)) = region_iterator.next() {
@@ -289,27 +262,27 @@ mod tests {
     #[test]
     fn test_RegionPoolIterMut_step_through_next() {
         let worker_count = 3;
-        let mut region_pool = MultiThreadedEngine::new(worker_count);
+        let mut region_pool = MultiThreadedEngine::new(worker_count, Arc::new(Generations::life()));
         let mut vec =
-            BMatrixVector::new_for_test(vec![true, true, true, false, false, false, false]);
+            BMatrixVector::new_for_test(vec![ALIVE, ALIVE, ALIVE, DEAD, DEAD, DEAD, DEAD]);
         let mut region_iterator = region_pool.create_iter_mut(&mut vec);
 
         if let Some((slice1, offset1)) = region_iterator.next() {
-            assert_eq!(slice1, vec![true, true]);
+            assert_eq!(slice1, vec![ALIVE, ALIVE]);
             assert_eq!(offset1, 0);
         } else {
             panic!("iterator should still have elements");
         }
 
         if let Some((slice2, offset2)) = region_iterator.next() {
-            assert_eq!(slice2, vec![true, false]);
+            assert_eq!(slice2, vec![ALIVE, DEAD]);
             assert_eq!(offset2, 2);
         } else {
             panic!("iterator should still have elements");
         }
 
         if let Some((slice3, offset3)) = region_iterator.next() {
-            assert_eq!(slice3, vec![false, false, false]);
+            assert_eq!(slice3, vec![DEAD, DEAD, DEAD]);
             assert_eq!(offset3, 4);
         } else {
             panic!("iterator should still have elements");
@@ -319,6 +292,23 @@ mod tests {
             panic!("iterator should be empty now");
         }
     }
+    #[test]
+    fn test_RegionPoolIterMut_more_workers_than_cells() {
+        let worker_count = 10;
+        let mut region_pool = MultiThreadedEngine::new(worker_count, Arc::new(Generations::life()));
+        let mut vec = BMatrixVector::new_for_test(vec![ALIVE, DEAD, ALIVE]);
+        let mut region_iterator = region_pool.create_iter_mut(&mut vec);
+
+        if let Some((whole_slice, offset)) = region_iterator.next() {
+            assert_eq!(whole_slice, vec![ALIVE, DEAD, ALIVE]);
+            assert_eq!(offset, 0);
+        } else {
+            panic!("iterator should still have elements");
+        }
+        if let Some(_) = region_iterator.next() {
+            panic!("iterator should be empty now");
+        }
+    }
 
 
     #[test]
@@ -329,12 +319,12 @@ mod tests {
         let i = i as i32;
         let j = j as i32;
 
-        *b_matrix_vector.at_mut(i, j).unwrap() = true;
-        assert_eq!(b_matrix_vector.at(i, j).unwrap(), true);
+        *b_matrix_vector.at_mut(i, j).unwrap() = ALIVE;
+        assert_eq!(b_matrix_vector.at(i, j).unwrap(), ALIVE);
         let mut next_b_matrix_vector = BMatrixVector::default();
-        SingleThreadEngine::new().next_b_matrix(&b_matrix_vector,&mut next_b_matrix_vector);
+        SingleThreadEngine::new(Arc::new(Generations::life())).next_b_matrix(&b_matrix_vector,&mut next_b_matrix_vector);
 
-        assert_eq!(next_b_matrix_vector.at(i, j).unwrap(), false);
+        assert_eq!(next_b_matrix_vector.at(i, j).unwrap(), DEAD);
     }
 
     #[test]
@@ -344,17 +334,17 @@ mod tests {
         let j = 40;
         let i = i as i32;
         let j = j as i32;
-        *b_matrix_vector.at_mut(i, j + 1).unwrap() = true;
-        *b_matrix_vector.at_mut(i - 1, j).unwrap() = true;
-        *b_matrix_vector.at_mut(i, j - 1).unwrap() = true;
+        *b_matrix_vector.at_mut(i, j + 1).unwrap() = ALIVE;
+        *b_matrix_vector.at_mut(i - 1, j).unwrap() = ALIVE;
+        *b_matrix_vector.at_mut(i, j - 1).unwrap() = ALIVE;
 
         let mut next_b_matrix_vector = BMatrixVector::default();
-        SingleThreadEngine::new().next_b_matrix(&b_matrix_vector,&mut next_b_matrix_vector);
+        SingleThreadEngine::new(Arc::new(Generations::life())).next_b_matrix(&b_matrix_vector,&mut next_b_matrix_vector);
 
-        assert_eq!(next_b_matrix_vector.at(i, j + 1).unwrap(), false);
-        assert_eq!(next_b_matrix_vector.at(i - 1, j).unwrap(), true);
-        assert_eq!(next_b_matrix_vector.at(i, j - 1).unwrap(), false);
-        assert_eq!(next_b_matrix_vector.at(i, j).unwrap(), true);
+        assert_eq!(next_b_matrix_vector.at(i, j + 1).unwrap(), DEAD);
+        assert_eq!(next_b_matrix_vector.at(i - 1, j).unwrap(), ALIVE);
+        assert_eq!(next_b_matrix_vector.at(i, j - 1).unwrap(), DEAD);
+        assert_eq!(next_b_matrix_vector.at(i, j).unwrap(), ALIVE);
     }
     #[test]
     fn test_update_b_matrix_corner_cell_stays_alive() {
@@ -362,16 +352,16 @@ mod tests {
         let i = GRID_SIZE - 1;
         let j = GRID_SIZE - 1;
 
-        *b_matrix_vector.at_mut(i, j - 1).unwrap() = true;
-        *b_matrix_vector.at_mut(i - 1, j).unwrap() = true;
-        *b_matrix_vector.at_mut(i, j).unwrap() = true;
+        *b_matrix_vector.at_mut(i, j - 1).unwrap() = ALIVE;
+        *b_matrix_vector.at_mut(i - 1, j).unwrap() = ALIVE;
+        *b_matrix_vector.at_mut(i, j).unwrap() = ALIVE;
 
         let mut next_b_matrix_vector = BMatrixVector::default();
-        SingleThreadEngine::new().next_b_matrix(&b_matrix_vector,&mut next_b_matrix_vector);
+        SingleThreadEngine::new(Arc::new(Generations::life())).next_b_matrix(&b_matrix_vector,&mut next_b_matrix_vector);
 
-        assert_eq!(next_b_matrix_vector.at(i, j).unwrap(), true);
-        assert_eq!(next_b_matrix_vector.at(i, j - 1).unwrap(), true);
-        assert_eq!(next_b_matrix_vector.at(i - 1, j).unwrap(), true);
+        assert_eq!(next_b_matrix_vector.at(i, j).unwrap(), ALIVE);
+        assert_eq!(next_b_matrix_vector.at(i, j - 1).unwrap(), ALIVE);
+        assert_eq!(next_b_matrix_vector.at(i - 1, j).unwrap(), ALIVE);
     }
 
     #[test]
@@ -379,7 +369,7 @@ mod tests {
         let i: i32 = 3;
         let j: i32 = 2;
         let idx = j * GRID_SIZE + i;
-        let (new_i, new_j) = get_location_from_idx(idx as usize);
+        let (new_i, new_j) = get_location_from_idx(idx as usize, GRID_SIZE);
         assert_eq!(i, new_i);
         assert_eq!(j, new_j);
     }
@@ -0,0 +1,316 @@
+// Every engine gets run against SingleThreadEngine, which is the reference
+// implementation since it is the most straightforward translation of the rules
+use super::*;
+use crate::fixtures::*;
+use crate::patterns::PatternBuilder;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::mem;
+
+// ************  SETUP  ************
+// Odd worker counts + non square grids so RegionPoolIterMut has to split
+// in the middle of a row
+const WORKER_COUNTS: [i32; 5] = [1, 2, 3, 7, 16];
+
+fn backends_under_test() -> Vec<Backend> {
+    let mut backends = vec![Backend::Single, Backend::Rayon];
+    for &worker_count in WORKER_COUNTS.iter() {
+        backends.push(Backend::MultiThreaded(worker_count));
+    }
+    // Compile time reminder: adding a Backend should also add it to the list above
+    for backend in backends.iter() {
+        match backend {
+            Backend::Single | Backend::MultiThreaded(_) | Backend::Rayon => (),
+            // does no work, so nothing to compare against
+            Backend::Skip => unreachable!(),
+        }
+    }
+    backends
+}
+
+fn backend_name(backend: &Backend) -> String {
+    match backend {
+        Backend::Single => "Single".to_string(),
+        Backend::MultiThreaded(worker_count) => format!("MultiThreaded({})", worker_count),
+        Backend::Rayon => "Rayon".to_string(),
+        Backend::Skip => "Skip".to_string(),
+    }
+}
+
+fn random_soup(width: i32, height: i32, seed: u64, density: f64) -> BMatrixVector {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut vec = BMatrixVector::new(width, height);
+    for cell in vec.iter_mut() {
+        *cell = if rng.gen_bool(density) { ALIVE } else { DEAD };
+    }
+    vec
+}
+
+fn first_difference(a: &BMatrixVector, b: &BMatrixVector) -> Option<(i32, i32)> {
+    a.iter()
+        .zip(b.iter())
+        .position(|(x, y)| x != y)
+        .map(|idx| (idx as i32 % a.width(), idx as i32 / a.width()))
+}
+
+fn step(engine: &mut Box<dyn Engine>, vec: &BMatrixVector, generations: u32) -> BMatrixVector {
+    let mut old_vec = vec.clone();
+    let mut new_vec = vec.clone();
+    for _ in 0..generations {
+        engine.next_b_matrix(&old_vec, &mut new_vec);
+        mem::swap(&mut old_vec, &mut new_vec);
+    }
+    old_vec
+}
+
+fn assert_engines_agree(seed_vec: &BMatrixVector, generations: u32) {
+    assert_engines_agree_under(Arc::new(Generations::life()), seed_vec, generations);
+}
+
+fn assert_engines_agree_under(rule: Arc<dyn Rule>, seed_vec: &BMatrixVector, generations: u32) {
+    let mut reference = create_engine_with_rule(Backend::Single, rule.clone());
+    let mut expected = vec![seed_vec.clone()];
+    for gen in 0..generations as usize {
+        let next = step(&mut reference, &expected[gen], 1);
+        expected.push(next);
+    }
+
+    for backend in backends_under_test() {
+        let name = backend_name(&backend);
+        let mut engine = create_engine_with_rule(backend, rule.clone());
+        let mut current = seed_vec.clone();
+        for (gen, expected_vec) in expected.iter().enumerate().skip(1) {
+            current = step(&mut engine, &current, 1);
+            if let Some((i, j)) = first_difference(&current, expected_vec) {
+                panic!(
+                    "{} diverged from Single at generation {} on cell ({},{})",
+                    name, gen, i, j
+                );
+            }
+        }
+    }
+}
+
+// Runs the pattern on every engine, expecting it to come back shifted by
+// (dx,dy) after `period` generations, for a few periods
+fn assert_returns_after(rows: &[&str], period: u32, dx: i32, dy: i32) {
+    let (width, height) = (48, 40);
+    let (x, y) = (20, 18);
+    let mut seed_vec = BMatrixVector::new(width, height);
+    place(&mut seed_vec, x, y, rows);
+
+    for backend in backends_under_test() {
+        let name = backend_name(&backend);
+        let mut engine = create_engine(backend);
+        let mut current = seed_vec.clone();
+        for cycle in 1..4 {
+            current = step(&mut engine, &current, period);
+            let mut expected = BMatrixVector::new(width, height);
+            place(&mut expected, x + cycle * dx, y + cycle * dy, rows);
+            if let Some((i, j)) = first_difference(&current, &expected) {
+                panic!(
+                    "{}: pattern wrong after {} generations at cell ({},{})",
+                    name,
+                    cycle as u32 * period,
+                    i,
+                    j
+                );
+            }
+        }
+        // and shouldn't be back any earlier, otherwise period is wrong
+        if dx == 0 && dy == 0 {
+            for gen in 1..period {
+                let current = step(&mut engine, &seed_vec, gen);
+                assert!(
+                    first_difference(&current, &seed_vec).is_some(),
+                    "{}: returned to phase after only {} generations",
+                    name,
+                    gen
+                );
+            }
+        }
+    }
+}
+
+// ************  RANDOM SEEDS  ************
+#[test]
+fn test_engines_agree_on_random_soups() {
+    for seed in 0..4 {
+        let seed_vec = random_soup(61, 37, seed, 0.35);
+        assert_engines_agree(&seed_vec, 40);
+    }
+}
+
+#[test]
+fn test_engines_agree_on_dense_soup() {
+    assert_engines_agree(&random_soup(33, 33, 42, 0.8), 20);
+}
+
+#[test]
+fn test_engines_agree_on_single_row_and_column() {
+    assert_engines_agree(&random_soup(97, 1, 7, 0.5), 10);
+    assert_engines_agree(&random_soup(1, 97, 8, 0.5), 10);
+}
+
+#[test]
+fn test_engines_agree_on_generations_rules() {
+    // Brian's Brain and Star Wars, where dying cells have to be left out of
+    // the neighbor counts on every engine
+    for (seed, &rulestring) in ["B2/S/C3", "345/2/4"].iter().enumerate() {
+        let rule = parse_rule(rulestring).unwrap();
+        assert_engines_agree_under(rule, &random_soup(61, 37, seed as u64, 0.3), 40);
+    }
+}
+
+#[test]
+fn test_engines_agree_on_isotropic_rules() {
+    for (seed, &rulestring) in ["B2-a/S12", "B3/S2-i34q", "B2ac3-ce/S2-c34q/C3"].iter().enumerate() {
+        let rule = parse_rule(rulestring).unwrap();
+        assert_engines_agree_under(rule, &random_soup(61, 37, 10 + seed as u64, 0.3), 40);
+    }
+}
+
+#[test]
+fn test_engines_agree_on_other_neighborhoods() {
+    // Larger than Life looks further than the row above and below, past the
+    // edges of whatever each thread is given
+    for (seed, &rulestring) in ["B2/S34H", "B1/S012/C4V", "R5,C0,M1,S34..58,B34..45,NM", "R3,C3,M0,S6..12,B7..9,NN"]
+        .iter()
+        .enumerate()
+    {
+        let rule = parse_rule(rulestring).unwrap();
+        assert_engines_agree_under(rule, &random_soup(61, 37, 20 + seed as u64, 0.4), 20);
+    }
+}
+
+#[test]
+fn test_engines_agree_on_elementary_rules() {
+    // every thread copies rows up from the next thread's cells
+    for (seed, &rulestring) in ["W30", "W110", "W90"].iter().enumerate() {
+        let rule = parse_rule(rulestring).unwrap();
+        assert_engines_agree_under(rule, &random_soup(61, 37, 30 + seed as u64, 0.5), 50);
+    }
+}
+
+#[test]
+fn test_engines_agree_on_wireworld() {
+    // wire everywhere, with electrons on some of it
+    let mut seed_vec = random_soup(61, 37, 5, 0.6);
+    let mut rng = StdRng::seed_from_u64(6);
+    for cell in seed_vec.iter_mut() {
+        if *cell == ALIVE {
+            *cell = rng.gen_range(1, 4);
+        }
+    }
+    assert_engines_agree_under(parse_rule("WireWorld").unwrap(), &seed_vec, 40);
+}
+
+#[test]
+fn test_engines_agree_with_more_workers_than_cells() {
+    // RegionPoolIterMut hands everything to a single region
+    let seed_vec = random_soup(5, 3, 99, 0.6);
+    let expected = step(&mut create_engine(Backend::Single), &seed_vec, 5);
+    let current = step(&mut create_engine(Backend::MultiThreaded(32)), &seed_vec, 5);
+    assert!(first_difference(&current, &expected).is_none());
+}
+
+#[test]
+fn test_engines_agree_on_soup_touching_edges() {
+    // full soup means the out of bounds neighbors get exercised every generation
+    assert_engines_agree(&random_soup(50, 50, 1234, 0.5), 60);
+}
+
+#[test]
+fn test_engines_agree_on_pattern_builder_patterns() {
+    let seed_vec = PatternBuilder::with_size(64, 48)
+        .make_square(2, 2)
+        .make_blinker(10, 4)
+        .make_t(20, 20)
+        .make_r_pentomino(40, 24)
+        .make_glider(30, 40)
+        .build();
+    assert_engines_agree(&seed_vec, 100);
+}
+
+#[test]
+fn test_step_n_matches_repeated_next_b_matrix() {
+    let seed_vec = random_soup(40, 30, 77, 0.4);
+    for &n in [0, 1, 2, 7].iter() {
+        let expected = step(&mut create_engine(Backend::Single), &seed_vec, n);
+        for backend in backends_under_test() {
+            let name = backend_name(&backend);
+            let mut new_vec = BMatrixVector::new(40, 30);
+            let mut scratch = BMatrixVector::new(40, 30);
+            create_engine(backend).step_n(n, &seed_vec, &mut new_vec, &mut scratch);
+            assert!(
+                first_difference(&new_vec, &expected).is_none(),
+                "{}: step_n({}) differs from stepping one at a time",
+                name,
+                n
+            );
+        }
+    }
+}
+
+// ************  GOLDEN PATTERNS  ************
+#[test]
+fn test_block_is_still_life() {
+    assert_returns_after(&["OO", "OO"], 1, 0, 0);
+}
+
+#[test]
+fn test_beehive_is_still_life() {
+    assert_returns_after(&[".OO.", "O..O", ".OO."], 1, 0, 0);
+}
+
+#[test]
+fn test_blinker_period_2() {
+    assert_returns_after(&["OOO"], 2, 0, 0);
+}
+
+#[test]
+fn test_toad_period_2() {
+    assert_returns_after(&[".OOO", "OOO."], 2, 0, 0);
+}
+
+#[test]
+fn test_beacon_period_2() {
+    assert_returns_after(&["OO..", "OO..", "..OO", "..OO"], 2, 0, 0);
+}
+
+#[test]
+fn test_pulsar_period_3() {
+    assert_returns_after(&PULSAR, 3, 0, 0);
+}
+
+#[test]
+fn test_pentadecathlon_period_15() {
+    assert_returns_after(&["..O....O..", "OO.OOOO.OO", "..O....O.."], 15, 0, 0);
+}
+
+#[test]
+fn test_glider_translates_diagonally() {
+    // same orientation PatternBuilder::make_glider draws, which heads up and to the left
+    assert_returns_after(&["OOO", "O..", ".O."], 4, -1, -1);
+    assert_returns_after(&[".O.", "..O", "OOO"], 4, 1, 1);
+}
+
+#[test]
+fn test_lwss_translates_orthogonally() {
+    assert_returns_after(&[".O..O", "O....", "O...O", "OOOO."], 4, -2, 0);
+}
+
+#[test]
+fn test_make_glider_translates_diagonally() {
+    let seed_vec = PatternBuilder::with_size(32, 32).make_glider(16, 16).build();
+    let expected = PatternBuilder::with_size(32, 32).make_glider(14, 14).build();
+    for backend in backends_under_test() {
+        let name = backend_name(&backend);
+        let current = step(&mut create_engine(backend), &seed_vec, 8);
+        assert!(
+            first_difference(&current, &expected).is_none(),
+            "{}: glider did not move by (-2,-2) after 8 generations",
+            name
+        );
+    }
+}
@@ -0,0 +1,474 @@
+use super::*;
+use rayon::prelude::*;
+use std::collections::VecDeque;
+
+// a keyframe every this many recorded generations, so a long way back does
+// not have to go through every diff in between
+const KEYFRAME_INTERVAL: usize = 64;
+// most changed cells GenerationHistory keeps, diffs and keyframes together.
+// 5 bytes each (index and mask), so 320MB. A fresh GRID_SIZE soup blows
+// through it in a couple of generations, a settled one fits hundreds
+const HISTORY_BUDGET: usize = 1 << 26;
+const MAX_UNDO: usize = 100;
+// cells per rayon job when diffing whole universes
+const DIFF_CHUNK: usize = 1 << 16;
+
+/// Sorted indices of cells, each with a mask to xor the cell's state with.
+/// Either the cells that changed between two generations, or for a keyframe
+/// the ones that are not dead, which is the same thing as changing them from
+/// an empty universe.
+/// xor-ing twice gets you back where you started, so one diff goes both ways
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CellDiff {
+    indices: Vec<u32>,
+    masks: Vec<CellState>,
+}
+
+impl CellDiff {
+    /// Cells that differ between old and new, which have to be the same size
+    pub fn between(old: &BMatrixVector, new: &BMatrixVector) -> CellDiff {
+        assert_eq!(old.len(), new.len(), "can only diff universes of the same size");
+        let chunks: Vec<CellDiff> = old
+            .par_chunks(DIFF_CHUNK)
+            .zip(new.par_chunks(DIFF_CHUNK))
+            .enumerate()
+            .map(|(chunk, (old, new))| {
+                let start = chunk * DIFF_CHUNK;
+                let mut diff = CellDiff::default();
+                for (index, (old, new)) in old.iter().zip(new.iter()).enumerate() {
+                    diff.push((start + index) as u32, old ^ new);
+                }
+                diff
+            })
+            .collect();
+        CellDiff {
+            indices: chunks.iter().flat_map(|diff| diff.indices.iter().cloned()).collect(),
+            masks: chunks.iter().flat_map(|diff| diff.masks.iter().cloned()).collect(),
+        }
+    }
+    /// Same as between, but only looking at region. before is what the region
+    /// looked like, as copy_region gave it
+    pub fn in_region(before: &BMatrixVector, after: &BMatrixVector, region: BoundingBox) -> CellDiff {
+        let mut diff = CellDiff::default();
+        for j in 0..region.height {
+            for i in 0..region.width {
+                let (x, y) = (region.x + i, region.y + j);
+                if after.in_bounds(x, y) {
+                    diff.push((y * after.width() + x) as u32, before.state(i, j) ^ after.state(x, y));
+                }
+            }
+        }
+        diff
+    }
+    pub fn live_cells(vec: &BMatrixVector) -> CellDiff {
+        let mut diff = CellDiff::default();
+        for (index, &cell) in vec.iter().enumerate() {
+            diff.push(index as u32, cell);
+        }
+        diff
+    }
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+    pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.indices.iter().map(|&index| index as usize)
+    }
+    pub fn apply(&self, vec: &mut BMatrixVector) {
+        for (&index, &mask) in self.indices.iter().zip(self.masks.iter()) {
+            vec[index as usize] ^= mask;
+        }
+    }
+    /// Doing self and then other in one go. Cells changed back by other drop out
+    pub fn then(&self, other: &CellDiff) -> CellDiff {
+        let mut a = self.indices.iter().zip(self.masks.iter()).peekable();
+        let mut b = other.indices.iter().zip(other.masks.iter()).peekable();
+        let mut merged = CellDiff::default();
+        loop {
+            match (a.peek(), b.peek()) {
+                (Some(&(&x, &mask_x)), Some(&(&y, &mask_y))) if x == y => {
+                    merged.push(x, mask_x ^ mask_y);
+                    a.next();
+                    b.next();
+                }
+                (Some(&(&x, &mask)), Some(&(&y, _))) if x < y => {
+                    merged.push(x, mask);
+                    a.next();
+                }
+                (_, Some(&(&y, &mask))) => {
+                    merged.push(y, mask);
+                    b.next();
+                }
+                (Some(&(&x, &mask)), None) => {
+                    merged.push(x, mask);
+                    a.next();
+                }
+                (None, None) => return merged,
+            }
+        }
+    }
+    // indices have to come in order, cells that stay the same are left out
+    fn push(&mut self, index: u32, mask: CellState) {
+        if mask != 0 {
+            self.indices.push(index);
+            self.masks.push(mask);
+        }
+    }
+}
+
+struct Entry {
+    generation: u64,
+    // from the entry before this one. Empty for the oldest, since that one is gone
+    diff: CellDiff,
+    keyframe: Option<CellDiff>,
+}
+
+impl Entry {
+    fn stored(&self) -> usize {
+        self.diff.len() + self.keyframe.as_ref().map_or(0, CellDiff::len)
+    }
+}
+
+/// Recent generations, kept as diffs from one to the next with a keyframe
+/// every so often. Memory is bounded both in generations and in cells.
+/// Only ever records what the renderer was handed, so while running to a
+/// generation it has one entry every RUN_TO_CHUNK generations
+pub struct GenerationHistory {
+    max_generations: usize,
+    entries: VecDeque<Entry>,
+    // changed cells across all entries
+    stored: usize,
+    since_keyframe: usize,
+}
+
+impl GenerationHistory {
+    pub fn new(max_generations: usize) -> Self {
+        GenerationHistory {
+            max_generations,
+            entries: VecDeque::new(),
+            stored: 0,
+            since_keyframe: 0,
+        }
+    }
+    pub fn max_generations(&self) -> usize {
+        self.max_generations
+    }
+    pub fn oldest(&self) -> Option<u64> {
+        self.entries.front().map(|entry| entry.generation)
+    }
+    pub fn latest(&self) -> Option<u64> {
+        self.entries.back().map(|entry| entry.generation)
+    }
+    /// Forgets everything, vec at generation is where it starts from again
+    pub fn reset(&mut self, generation: u64, vec: &BMatrixVector) {
+        self.entries.clear();
+        self.stored = 0;
+        self.push(Entry {
+            generation,
+            diff: CellDiff::default(),
+            keyframe: Some(CellDiff::live_cells(vec)),
+        });
+    }
+    /// old has to be the latest generation recorded, new is what came after it
+    pub fn record(&mut self, generation: u64, old: &BMatrixVector, new: &BMatrixVector) {
+        if self.entries.is_empty() {
+            self.reset(generation, new);
+            return;
+        }
+        let keyframe = if self.since_keyframe + 1 >= KEYFRAME_INTERVAL {
+            Some(CellDiff::live_cells(new))
+        } else {
+            None
+        };
+        self.push(Entry {
+            generation,
+            diff: CellDiff::between(old, new),
+            keyframe,
+        });
+    }
+    /// Latest generation got edited, diff is what the edit flipped
+    pub fn amend(&mut self, diff: &CellDiff) {
+        // the oldest one has no diff to speak of, its keyframe is all there is
+        let has_diff = self.entries.len() > 1;
+        if let Some(latest) = self.entries.back_mut() {
+            let before = latest.stored();
+            if has_diff {
+                latest.diff = latest.diff.then(diff);
+            }
+            if let Some(keyframe) = latest.keyframe.as_mut() {
+                *keyframe = keyframe.then(diff);
+            }
+            self.stored = self.stored + latest.stored() - before;
+        }
+    }
+    /// Turns vec, which has to be the latest generation recorded, into the
+    /// latest recorded generation at or before target and forgets everything
+    /// after it. None, with vec untouched, if target is older than the history
+    pub fn rewind(&mut self, target: u64, vec: &mut BMatrixVector) -> Option<u64> {
+        let to = self.entries.iter().rposition(|entry| entry.generation <= target)?;
+        let backwards: usize = self.entries.iter().skip(to + 1).map(|entry| entry.diff.len()).sum();
+        let from_keyframe = self
+            .entries
+            .iter()
+            .take(to + 1)
+            .rposition(|entry| entry.keyframe.is_some())
+            .map(|keyframe| {
+                let cost = self.entries[keyframe].keyframe.as_ref().unwrap().len()
+                    + self.entries.iter().take(to + 1).skip(keyframe + 1).map(|entry| entry.diff.len()).sum::<usize>()
+                    // clearing the universe is a pass over it, just a cheap one
+                    + vec.len() / 64;
+                (keyframe, cost)
+            });
+        match from_keyframe {
+            Some((keyframe, cost)) if cost < backwards => {
+                for cell in vec.iter_mut() {
+                    *cell = DEAD;
+                }
+                self.entries[keyframe].keyframe.as_ref().unwrap().apply(vec);
+                for entry in self.entries.iter().take(to + 1).skip(keyframe + 1) {
+                    entry.diff.apply(vec);
+                }
+            }
+            _ => {
+                for entry in self.entries.iter().skip(to + 1).rev() {
+                    entry.diff.apply(vec);
+                }
+            }
+        }
+        for entry in self.entries.drain(to + 1..) {
+            self.stored -= entry.stored();
+        }
+        self.since_keyframe = self
+            .entries
+            .iter()
+            .rev()
+            .position(|entry| entry.keyframe.is_some())
+            .unwrap_or(self.entries.len());
+        self.latest()
+    }
+    fn push(&mut self, entry: Entry) {
+        self.since_keyframe = if entry.keyframe.is_some() { 0 } else { self.since_keyframe + 1 };
+        self.stored += entry.stored();
+        self.entries.push_back(entry);
+        // always keeps the latest one, however big it is
+        while self.entries.len() > 1
+            && (self.entries.len() > self.max_generations || self.stored > HISTORY_BUDGET)
+        {
+            let oldest = self.entries.pop_front().unwrap();
+            self.stored -= oldest.stored();
+            // the new oldest one's diff is from a generation that is gone now
+            let front = self.entries.front_mut().unwrap();
+            self.stored -= front.diff.len();
+            front.diff = CellDiff::default();
+        }
+    }
+}
+
+/// An edit as the cells it flipped, at the generation it was made in
+pub struct EditRecord {
+    pub generation: u64,
+    pub diff: CellDiff,
+}
+
+/// Edits that can be undone, and undone ones that can be redone
+#[derive(Default)]
+pub struct UndoStack {
+    undo: Vec<EditRecord>,
+    redo: Vec<EditRecord>,
+}
+
+impl UndoStack {
+    pub fn new() -> Self {
+        UndoStack::default()
+    }
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+    /// A fresh edit, so whatever was undone before it cannot be redone anymore
+    pub fn push(&mut self, record: EditRecord) {
+        self.redo.clear();
+        self.push_undo(record);
+    }
+    pub fn pop_undo(&mut self) -> Option<EditRecord> {
+        self.undo.pop()
+    }
+    pub fn pop_redo(&mut self) -> Option<EditRecord> {
+        self.redo.pop()
+    }
+    pub fn push_undo(&mut self, record: EditRecord) {
+        if self.undo.len() == MAX_UNDO {
+            self.undo.remove(0);
+        }
+        self.undo.push(record);
+    }
+    pub fn push_redo(&mut self, record: EditRecord) {
+        self.redo.push(record);
+    }
+    /// After a rewind, edits made later than generation never happened
+    pub fn forget_after(&mut self, generation: u64) {
+        self.undo.retain(|record| record.generation <= generation);
+        self.redo.retain(|record| record.generation <= generation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::PatternBuilder;
+
+    // generations 0..=n of an r-pentomino, which keeps changing for a long time
+    fn r_pentomino_generations(n: usize) -> Vec<BMatrixVector> {
+        let mut engine = create_engine(Backend::Single);
+        let mut generations = vec![PatternBuilder::with_size(48, 48).make_r_pentomino(22, 22).build()];
+        for _ in 0..n {
+            let mut next = generations.last().unwrap().clone();
+            engine.next_b_matrix(generations.last().unwrap(), &mut next);
+            generations.push(next);
+        }
+        generations
+    }
+
+    #[test]
+    fn test_CellDiff_goes_both_ways() {
+        let generations = r_pentomino_generations(1);
+        let diff = CellDiff::between(&generations[0], &generations[1]);
+        assert!(!diff.is_empty());
+        let mut vec = generations[0].clone();
+        diff.apply(&mut vec);
+        assert!(vec == generations[1]);
+        diff.apply(&mut vec);
+        assert!(vec == generations[0]);
+        // doing it twice in one go does nothing
+        assert!(diff.then(&diff).is_empty());
+        assert_eq!(CellDiff::live_cells(&generations[0]).len(), 5);
+    }
+
+    #[test]
+    fn test_CellDiff_keeps_states() {
+        // dying cells of a Generations rule
+        let mut before = BMatrixVector::new(4, 1);
+        before.copy_from_slice(&[ALIVE, 2, DEAD, 3]);
+        let mut after = BMatrixVector::new(4, 1);
+        after.copy_from_slice(&[2, 3, DEAD, DEAD]);
+        let diff = CellDiff::between(&before, &after);
+        assert_eq!(diff.len(), 3);
+        let mut vec = before.clone();
+        diff.apply(&mut vec);
+        assert!(vec == after);
+        diff.apply(&mut vec);
+        assert!(vec == before);
+        let mut vec = BMatrixVector::new(4, 1);
+        CellDiff::live_cells(&before).apply(&mut vec);
+        assert!(vec == before);
+    }
+
+    #[test]
+    fn test_CellDiff_in_region_matches_between() {
+        let generations = r_pentomino_generations(3);
+        let region = generations[3].bounding_box().unwrap();
+        let mut after = generations[0].clone();
+        after.paste(&generations[3].copy_region(region), region.x, region.y);
+        let before = generations[0].copy_region(region);
+        assert_eq!(
+            CellDiff::in_region(&before, &after, region),
+            CellDiff::between(&generations[0], &after)
+        );
+    }
+
+    #[test]
+    fn test_GenerationHistory_rewinds_across_keyframes() {
+        let generations = r_pentomino_generations(150);
+        let mut history = GenerationHistory::new(1000);
+        history.reset(0, &generations[0]);
+        for (generation, pair) in generations.windows(2).enumerate() {
+            history.record(generation as u64 + 1, &pair[0], &pair[1]);
+        }
+        assert_eq!((history.oldest(), history.latest()), (Some(0), Some(150)));
+
+        let mut vec = generations[150].clone();
+        // a couple back goes through the diffs, a long way back from a keyframe
+        assert_eq!(history.rewind(148, &mut vec), Some(148));
+        assert!(vec == generations[148]);
+        assert_eq!(history.rewind(70, &mut vec), Some(70));
+        assert!(vec == generations[70]);
+        assert_eq!(history.rewind(3, &mut vec), Some(3));
+        assert!(vec == generations[3]);
+        assert_eq!(history.latest(), Some(3));
+
+        // and it can carry on recording from there
+        history.record(4, &generations[3], &generations[4]);
+        let mut vec = generations[4].clone();
+        assert_eq!(history.rewind(0, &mut vec), Some(0));
+        assert!(vec == generations[0]);
+    }
+
+    #[test]
+    fn test_GenerationHistory_is_bounded() {
+        let generations = r_pentomino_generations(40);
+        let mut history = GenerationHistory::new(10);
+        history.reset(0, &generations[0]);
+        for (generation, pair) in generations.windows(2).enumerate() {
+            history.record(generation as u64 + 1, &pair[0], &pair[1]);
+        }
+        assert_eq!((history.oldest(), history.latest()), (Some(31), Some(40)));
+        let mut vec = generations[40].clone();
+        assert_eq!(history.rewind(30, &mut vec), None);
+        assert!(vec == generations[40]);
+        // the oldest one has lost its diff, but can still be got back to
+        assert_eq!(history.rewind(31, &mut vec), Some(31));
+        assert!(vec == generations[31]);
+    }
+
+    #[test]
+    fn test_GenerationHistory_amend_keeps_edits_in_the_past() {
+        let generations = r_pentomino_generations(2);
+        let mut history = GenerationHistory::new(10);
+        history.reset(0, &generations[0]);
+        history.record(1, &generations[0], &generations[1]);
+
+        // a block dropped in at generation 1
+        let mut edited = generations[1].clone();
+        let region = BoundingBox::spanning((2, 2), (3, 3));
+        let before = edited.copy_region(region);
+        Edit::Paste {
+            pattern: PatternBuilder::with_size(2, 2).make_square(0, 0).build(),
+            x: 2,
+            y: 2,
+        }
+        .apply(&mut edited);
+        history.amend(&CellDiff::in_region(&before, &edited, region));
+
+        let mut next = edited.clone();
+        create_engine(Backend::Single).next_b_matrix(&edited, &mut next);
+        history.record(2, &edited, &next);
+        let mut vec = next.clone();
+        assert_eq!(history.rewind(1, &mut vec), Some(1));
+        assert!(vec == edited);
+        assert_eq!(history.rewind(0, &mut vec), Some(0));
+        assert!(vec == generations[0]);
+    }
+
+    #[test]
+    fn test_UndoStack_new_edit_clears_redo() {
+        let record = |generation| EditRecord {
+            generation,
+            diff: CellDiff::default(),
+        };
+        let mut stack = UndoStack::new();
+        stack.push(record(1));
+        stack.push(record(5));
+        let undone = stack.pop_undo().unwrap();
+        assert_eq!(undone.generation, 5);
+        stack.push_redo(undone);
+        stack.push(record(6));
+        assert!(stack.pop_redo().is_none());
+
+        stack.push_redo(record(9));
+        stack.forget_after(5);
+        assert!(stack.pop_redo().is_none());
+        assert_eq!(stack.pop_undo().unwrap().generation, 1);
+    }
+}
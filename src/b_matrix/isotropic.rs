@@ -0,0 +1,455 @@
+// Isotropic non-totalistic rules, written in Hensel notation like B2-a/S12.
+// Instead of only counting the live neighbors, these look at how they are
+// arranged around the cell, up to rotations and reflections
+use super::*;
+use std::fmt;
+use std::str::FromStr;
+
+/// Number of ways the 8 neighbors can be alive or dead
+pub const CONFIGURATIONS: usize = 256;
+
+// Letters for each neighbor count up to 4, along with a 3x3 bitmask of one of
+// the arrangements each stands for(bit 8 top left, bit 0 bottom right and bit
+// 4 the center), the same ones Golly uses. 5 to 7 neighbors take the letter of
+// the arrangement of the dead ones, and 0 and 8 have no letters at all
+const LETTERS: [(&str, &[u16]); 5] = [
+    ("", &[]),
+    ("ce", &[1, 2]),
+    ("ceaikn", &[5, 10, 3, 40, 33, 68]),
+    ("ceaiknjqry", &[69, 42, 11, 7, 98, 13, 14, 70, 41, 97]),
+    ("ceaiknjqrytwz", &[325, 170, 15, 45, 99, 71, 106, 102, 43, 101, 105, 78, 108]),
+];
+
+// neighbor offsets, from bit 7 of a configuration index down to bit 0
+const NEIGHBORS: [(i32, i32); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+/// Which of the 256 neighbor configurations surrounds (i, j): one bit per live
+/// neighbor, row by row from the top left one(bit 7) to the bottom right one(bit 0).
+/// Like life::get_count only fully alive cells count
+#[inline]
+pub fn get_configuration(i: i32, j: i32, vec: &BMatrixVector) -> usize {
+    let mut configuration = 0;
+    for &(di, dj) in NEIGHBORS.iter() {
+        configuration = configuration << 1 | vec.is_alive(i + di, j + dj) as usize;
+    }
+    configuration
+}
+
+// drops the center bit of a 3x3 bitmask
+fn from_grid(mask: u16) -> usize {
+    (mask as usize & 0xF) | (mask as usize >> 5) << 4
+}
+
+// configuration with every neighbor moved by to
+fn moved(configuration: usize, to: impl Fn(i32, i32) -> (i32, i32)) -> usize {
+    NEIGHBORS
+        .iter()
+        .enumerate()
+        .filter(|&(bit, _)| configuration >> (7 - bit) & 1 == 1)
+        .map(|(_, &(di, dj))| {
+            let target = to(di, dj);
+            1 << (7 - NEIGHBORS.iter().position(|&neighbor| neighbor == target).unwrap())
+        })
+        .sum()
+}
+
+// the configuration turned and flipped all 8 ways
+fn symmetries(configuration: usize) -> Vec<usize> {
+    let mut turned = configuration;
+    let mut all = Vec::with_capacity(8);
+    for _ in 0..4 {
+        all.push(turned);
+        all.push(moved(turned, |di, dj| (-di, dj)));
+        turned = moved(turned, |di, dj| (-dj, di));
+    }
+    all
+}
+
+/// The letters that go with a neighbor count, in the order Hensel gave them
+pub fn letters(count: u32) -> &'static str {
+    LETTERS[count.min(8 - count) as usize].0
+}
+
+/// The neighbor count and letter a configuration goes by, None for 0 and 8
+/// neighbors which have just the one arrangement
+pub fn hensel_letter(configuration: usize) -> (u32, Option<char>) {
+    let count = configuration.count_ones();
+    // more than 4 goes by where the dead neighbors are
+    let (arranged, fewest) = if count > 4 {
+        (configuration ^ (CONFIGURATIONS - 1), 8 - count)
+    } else {
+        (configuration, count)
+    };
+    let (letters, masks) = LETTERS[fewest as usize];
+    let letter = letters
+        .chars()
+        .zip(masks.iter())
+        .find(|&(_, &mask)| symmetries(from_grid(mask)).contains(&arranged))
+        .map(|(letter, _)| letter);
+    (count, letter)
+}
+
+/// Isotropic non-totalistic rules on the Moore neighborhood. Like Generations,
+/// more than 2 states have cells that stop surviving take `states - 2`
+/// generations to die
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Isotropic {
+    // indexed by the neighbor configuration
+    birth: Vec<bool>,
+    survival: Vec<bool>,
+    states: usize,
+}
+
+impl Isotropic {
+    /// Rule where each of birth and survival is a list of neighbor counts and
+    /// the letters it takes for that count, all of them if there are none
+    pub fn new(birth: &[(u32, &str)], survival: &[(u32, &str)], states: usize) -> Self {
+        assert!(
+            (2..=MAX_STATES).contains(&states),
+            "Isotropic rules need 2 to {} states, got {}",
+            MAX_STATES,
+            states
+        );
+        let configurations = |counts: &[(u32, &str)]| -> Vec<bool> {
+            (0..CONFIGURATIONS)
+                .map(|configuration| {
+                    let (count, letter) = hensel_letter(configuration);
+                    counts.iter().any(|&(with, letters)| {
+                        with == count
+                            && (letters.is_empty() || matches!(letter, Some(letter) if letters.contains(letter)))
+                    })
+                })
+                .collect()
+        };
+        Isotropic {
+            birth: configurations(birth),
+            survival: configurations(survival),
+            states,
+        }
+    }
+}
+
+impl Rule for Isotropic {
+    fn states(&self) -> usize {
+        self.states
+    }
+    #[inline]
+    fn next_state(&self, i: i32, j: i32, vec: &BMatrixVector) -> CellState {
+        match vec.state(i, j) {
+            DEAD => {
+                if self.birth[get_configuration(i, j, vec)] {
+                    ALIVE
+                } else {
+                    DEAD
+                }
+            }
+            ALIVE => {
+                if self.survival[get_configuration(i, j, vec)] {
+                    ALIVE
+                } else if self.states > 2 {
+                    ALIVE + 1
+                } else {
+                    DEAD
+                }
+            }
+            dying if (dying as usize) + 1 < self.states => dying + 1,
+            _ => DEAD,
+        }
+    }
+}
+
+// e.g. 2-a3ce, leaving out the letters when all of them are there, and
+// writing whichever of the letters in or out is shorter otherwise
+fn transitions(configurations: &[bool]) -> String {
+    let mut written = String::new();
+    for count in 0..=8 {
+        let mut inside = String::new();
+        let mut outside = String::new();
+        let mut any = false;
+        for letter in letters(count).chars().map(Some).chain(std::iter::once(None)) {
+            let has = (0..CONFIGURATIONS)
+                .filter(|&configuration| hensel_letter(configuration) == (count, letter))
+                .any(|configuration| configurations[configuration]);
+            match (letter, has) {
+                (Some(letter), true) => inside.push(letter),
+                (Some(letter), false) => outside.push(letter),
+                (None, has) => any |= has,
+            }
+        }
+        if !any && inside.is_empty() {
+            continue;
+        }
+        written += &count.to_string();
+        if outside.is_empty() {
+            continue;
+        }
+        if inside.len() <= outside.len() {
+            written += &inside;
+        } else {
+            written += "-";
+            written += &outside;
+        }
+    }
+    written
+}
+
+// B2-a/S12, with /C for more than 2 states like Generations
+impl fmt::Display for Isotropic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B{}/S{}", transitions(&self.birth), transitions(&self.survival))?;
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        Ok(())
+    }
+}
+
+fn pairs(transitions: &[(u32, String)]) -> Vec<(u32, &str)> {
+    transitions.iter().map(|(count, letters)| (*count, letters.as_str())).collect()
+}
+
+// the (count, letters) pairs of e.g. 2-a3ce, with the letters to leave out
+// turned into the letters to keep
+fn parse_transitions(rulestring: &str, part: &str) -> LifeResult<Vec<(u32, String)>> {
+    let mut parsed: Vec<(u32, String)> = Vec::new();
+    let mut chars = part.chars().peekable();
+    while let Some(c) = chars.next() {
+        let count = match c.to_digit(10) {
+            Some(count) if count <= 8 => count,
+            _ => return Err(invalid_rule(rulestring, &format!("'{}' is not a neighbor count", c))),
+        };
+        let without = chars.peek() == Some(&'-');
+        if without {
+            chars.next();
+        }
+        let mut given = String::new();
+        while let Some(&letter) = chars.peek() {
+            if letter.is_ascii_digit() {
+                break;
+            }
+            if !letters(count).contains(letter) {
+                return Err(invalid_rule(
+                    rulestring,
+                    &format!("'{}' is not one of the letters for {} neighbors", letter, count),
+                ));
+            }
+            given.push(letter);
+            chars.next();
+        }
+        if without && given.is_empty() {
+            return Err(invalid_rule(rulestring, &format!("nothing to leave out after {}-", count)));
+        }
+        let kept = if without {
+            letters(count).chars().filter(|&letter| !given.contains(letter)).collect()
+        } else {
+            given
+        };
+        // an empty string would mean every letter
+        if !(without && kept.is_empty()) {
+            parsed.push((count, kept));
+        }
+    }
+    Ok(parsed)
+}
+
+/// Takes B2-a/S12 style rulestrings, optionally with /C for the number of
+/// states. Plain outer totalistic ones like B3/S23 work as well
+impl FromStr for Isotropic {
+    type Err = LifeError;
+    fn from_str(rulestring: &str) -> LifeResult<Self> {
+        let (mut birth, mut survival, mut states) = (None, None, None);
+        for part in rulestring.trim().split('/').map(str::trim) {
+            let letter = part.chars().next().map(|letter| letter.to_ascii_uppercase());
+            let rest = part.get(1..).unwrap_or("").to_ascii_lowercase();
+            match letter {
+                Some('B') if birth.is_none() => birth = Some(parse_transitions(rulestring, &rest)?),
+                Some('S') if survival.is_none() => survival = Some(parse_transitions(rulestring, &rest)?),
+                Some('C') | Some('G') if states.is_none() => states = Some(rest),
+                _ => return Err(invalid_rule(rulestring, "expected something like B2-a/S12")),
+            }
+        }
+        let states = match states {
+            None => 2,
+            Some(states) => match states.parse::<usize>() {
+                Ok(states) if (2..=MAX_STATES).contains(&states) => states,
+                _ => {
+                    return Err(invalid_rule(
+                        rulestring,
+                        &format!("the number of states has to be 2 to {}", MAX_STATES),
+                    ))
+                }
+            },
+        };
+        match (birth, survival) {
+            (Some(birth), Some(survival)) => Ok(Isotropic::new(&pairs(&birth), &pairs(&survival), states)),
+            _ => Err(invalid_rule(rulestring, "it needs both B and S")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::PatternBuilder;
+
+    // rule, cells, period, and how far it gets in a period
+    type Spaceship<'a> = (&'a str, &'a [(i32, i32)], usize, (i32, i32));
+
+    fn rule(rulestring: &str) -> Isotropic {
+        rulestring.parse().unwrap()
+    }
+
+    fn step(rule: &dyn Rule, vec: &BMatrixVector) -> BMatrixVector {
+        let mut next = vec.clone();
+        rule.next_cells(0, vec, &mut next);
+        next
+    }
+
+    fn pattern(width: i32, height: i32, cells: &[(i32, i32)]) -> BMatrixVector {
+        let mut vec = BMatrixVector::new(width, height);
+        for &(i, j) in cells.iter() {
+            *vec.at_mut(i, j).unwrap() = ALIVE;
+        }
+        vec
+    }
+
+    // live cells, moved to the top left
+    fn shape(vec: &BMatrixVector) -> Vec<(i32, i32)> {
+        let mut cells = Vec::new();
+        for j in 0..vec.height() {
+            for i in 0..vec.width() {
+                if vec.is_alive(i, j) {
+                    cells.push((i, j));
+                }
+            }
+        }
+        let left = cells.iter().map(|&(i, _)| i).min().unwrap_or(0);
+        let top = cells.iter().map(|&(_, j)| j).min().unwrap_or(0);
+        cells.iter().map(|&(i, j)| (i - left, j - top)).collect()
+    }
+
+    // runs a pattern for period generations, returning where its top left went
+    fn displacement(rule: &Isotropic, vec: &BMatrixVector, period: usize) -> Option<(i32, i32)> {
+        let top_left = |vec: &BMatrixVector| {
+            let mut found = (i32::MAX, i32::MAX);
+            for j in 0..vec.height() {
+                for i in 0..vec.width() {
+                    if vec.is_alive(i, j) {
+                        found = (found.0.min(i), found.1.min(j));
+                    }
+                }
+            }
+            found
+        };
+        let mut next = vec.clone();
+        for _ in 0..period {
+            next = step(rule, &next);
+        }
+        if shape(&next) != shape(vec) {
+            return None;
+        }
+        let ((from_i, from_j), (to_i, to_j)) = (top_left(vec), top_left(&next));
+        Some((to_i - from_i, to_j - from_j))
+    }
+
+    #[test]
+    fn test_hensel_letter_covers_every_configuration_once() {
+        let binomial = [1, 8, 28, 56, 70, 56, 28, 8, 1];
+        for count in 0..=8u32 {
+            let configurations: Vec<usize> = (0..CONFIGURATIONS)
+                .filter(|&configuration| configuration.count_ones() == count)
+                .collect();
+            assert_eq!(configurations.len(), binomial[count as usize]);
+            let mut seen = String::new();
+            for &configuration in configurations.iter() {
+                let (with, letter) = hensel_letter(configuration);
+                assert_eq!(with, count);
+                assert_eq!(letter.is_none(), count == 0 || count == 8, "{:08b}", configuration);
+                // the same letter for every way to turn it
+                for &turned in symmetries(configuration).iter() {
+                    assert_eq!(hensel_letter(turned), (with, letter));
+                }
+                if let Some(letter) = letter {
+                    if !seen.contains(letter) {
+                        seen.push(letter);
+                    }
+                }
+            }
+            let mut expected: Vec<char> = letters(count).chars().collect();
+            let mut seen: Vec<char> = seen.chars().collect();
+            expected.sort();
+            seen.sort();
+            assert_eq!(seen, expected, "{} neighbors", count);
+        }
+        // 2a is a corner with an edge next to it, 2i the edges across from each other
+        assert_eq!(hensel_letter(0b1100_0000), (2, Some('a')));
+        assert_eq!(hensel_letter(0b0001_1000), (2, Some('i')));
+        assert_eq!(hensel_letter(0b1000_0001), (2, Some('n')));
+        // 6 neighbors alive is 2 of them dead
+        assert_eq!(hensel_letter(0b0011_1111), (6, Some('a')));
+    }
+
+    #[test]
+    fn test_Isotropic_parse_and_display() {
+        assert_eq!(rule("B2-a/S12").to_string(), "B2-a/S12");
+        assert_eq!(rule("b2ceikn/s12"), rule("B2-a/S12"));
+        assert_eq!(rule("B3/S23").to_string(), "B3/S23");
+        assert_eq!(rule("B3ceaiknjqry/S2ceaikn3").to_string(), "B3/S23");
+        // letters come out in the order Hensel gave them
+        assert_eq!(rule("B2ac3-ce/S2-c34q/C3").to_string(), "B2ca3-ce/S2-c34q/C3");
+        // leaving out every letter is leaving out the count
+        assert_eq!(rule("B2-ceaikn3/S23"), rule("B3/S23"));
+        for bad in ["B2z/S23", "B0c/S", "B8e/S", "B9/S23", "B2-/S23", "B-a/S", "B3", "B3/S23/C1", "Life"].iter() {
+            assert!(bad.parse::<Isotropic>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_Isotropic_with_every_letter_is_outer_totalistic() {
+        let life = Generations::life();
+        let mut vec = PatternBuilder::with_size(24, 24).make_random_seeded((0, 0), 24, 24, 8, 0.4).build();
+        for _ in 0..20 {
+            let next = step(&rule("B3/S23"), &vec);
+            assert!(next == step(&life, &vec));
+            vec = next;
+        }
+    }
+
+    #[test]
+    fn test_Isotropic_letters_decide_births() {
+        // two neighbors side by side (2a) or across from each other (2i)
+        let beside = pattern(3, 3, &[(0, 0), (1, 0)]);
+        let across = pattern(3, 3, &[(1, 0), (1, 2)]);
+        let no_a = rule("B2-a/S");
+        assert!(!step(&no_a, &beside).is_alive(1, 1));
+        assert!(step(&no_a, &across).is_alive(1, 1));
+        let only_a = rule("B2a/S");
+        assert!(step(&only_a, &beside).is_alive(1, 1));
+        assert!(!step(&only_a, &across).is_alive(1, 1));
+    }
+
+    #[test]
+    fn test_Isotropic_spaceships() {
+        let spaceships: [Spaceship; 4] = [
+            // the Life glider, which does not care how it is written
+            ("B3ceaiknjqry/S23", &[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)], 4, (1, 1)),
+            // tlife's T tetromino, c/5 orthogonal
+            ("B3/S2-i34q", &[(0, 0), (1, 0), (2, 0), (1, 1)], 5, (0, 1)),
+            // a c/6 diagonal one from three cells
+            ("B2-a/S12", &[(1, 0), (2, 0), (0, 1)], 6, (-1, -1)),
+            // the glider again, which never runs into 2n or 3q
+            ("B2n3/S23-q", &[(0, 0), (1, 0), (0, 1), (2, 1), (0, 2)], 4, (-1, -1)),
+        ];
+        for &(rulestring, cells, period, moves) in spaceships.iter() {
+            let rule = rule(rulestring);
+            // a few periods, so it is not just something that moves once
+            let mut moved = pattern(40, 40, &cells.iter().map(|&(i, j)| (i + 18, j + 18)).collect::<Vec<_>>());
+            for _ in 0..3 {
+                assert_eq!(displacement(&rule, &moved, period), Some(moves), "{}", rulestring);
+                for _ in 0..period {
+                    moved = step(&rule, &moved);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,331 @@
+// Larger than Life: outer totalistic rules that count the live cells out to a
+// range of up to MAX_RANGE, with a birth and a survival interval rather than a
+// handful of counts. Golly writes them R5,C0,M1,S34..58,B34..45,NM (Bosco's rule)
+use super::*;
+use std::fmt;
+use std::str::FromStr;
+
+/// Furthest out a Larger than Life rule looks, the same as Golly
+pub const MAX_RANGE: i32 = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LargerThanLife {
+    range: i32,
+    states: usize,
+    // whether a cell counts itself
+    middle: bool,
+    // inclusive intervals of live cells in range
+    survival: (u32, u32),
+    birth: (u32, u32),
+    neighborhood: Neighborhood,
+}
+
+impl LargerThanLife {
+    pub fn new(
+        range: i32,
+        states: usize,
+        middle: bool,
+        survival: (u32, u32),
+        birth: (u32, u32),
+        neighborhood: Neighborhood,
+    ) -> Self {
+        assert!((1..=MAX_RANGE).contains(&range), "range has to be 1 to {}, got {}", MAX_RANGE, range);
+        assert!(
+            (2..=MAX_STATES).contains(&states),
+            "Larger than Life rules need 2 to {} states, got {}",
+            MAX_STATES,
+            states
+        );
+        LargerThanLife {
+            range,
+            states,
+            middle,
+            survival,
+            birth,
+            neighborhood,
+        }
+    }
+
+    // same as Generations from here on, once the live cells are counted
+    #[inline]
+    fn next_from_count(&self, state: CellState, count: u32) -> CellState {
+        let within = |(from, to): (u32, u32)| from <= count && count <= to;
+        match state {
+            DEAD => {
+                if within(self.birth) {
+                    ALIVE
+                } else {
+                    DEAD
+                }
+            }
+            ALIVE => {
+                if within(self.survival) {
+                    ALIVE
+                } else if self.states > 2 {
+                    ALIVE + 1
+                } else {
+                    DEAD
+                }
+            }
+            dying if (dying as usize) + 1 < self.states => dying + 1,
+            _ => DEAD,
+        }
+    }
+
+    // live cells in range of (i, j), itself included
+    #[inline]
+    fn count_within(&self, i: i32, j: i32, sums: &AliveSums) -> u32 {
+        match self.neighborhood {
+            Neighborhood::Moore => sums.count(i - self.range, j - self.range, i + self.range, j + self.range),
+            neighborhood => (-self.range..=self.range)
+                .filter_map(|dj| neighborhood.row_span(self.range, dj).map(|span| (dj, span)))
+                .map(|(dj, (from, to))| sums.count(i + from, j + dj, i + to, j + dj))
+                .sum(),
+        }
+    }
+}
+
+impl Rule for LargerThanLife {
+    fn states(&self) -> usize {
+        self.states
+    }
+
+    // cell by cell this is a lot of counting, next_cells is the quick way
+    fn next_state(&self, i: i32, j: i32, vec: &BMatrixVector) -> CellState {
+        let sums = AliveSums::new(vec, j - self.range, j + self.range);
+        let mut count = self.count_within(i, j, &sums);
+        if !self.middle && vec.is_alive(i, j) {
+            count -= 1;
+        }
+        self.next_from_count(vec.state(i, j), count)
+    }
+
+    fn next_cells(&self, start: usize, vec: &BMatrixVector, out: &mut [CellState]) {
+        if out.is_empty() {
+            return;
+        }
+        // the rows these cells are on, and as far up and down as they can see
+        let (_, top) = get_location_from_idx(start, vec.width());
+        let (_, bottom) = get_location_from_idx(start + out.len() - 1, vec.width());
+        let sums = AliveSums::new(vec, top - self.range, bottom + self.range);
+        let (mut i, mut j) = get_location_from_idx(start, vec.width());
+        for cell in out.iter_mut() {
+            let state = vec.state(i, j);
+            let mut count = self.count_within(i, j, &sums);
+            if !self.middle && state == ALIVE {
+                count -= 1;
+            }
+            *cell = self.next_from_count(state, count);
+            i += 1;
+            if i == vec.width() {
+                i = 0;
+                j += 1;
+            }
+        }
+    }
+}
+
+// Rr,Cc,Mm,Smin..max,Bmin..max,Nn, with C0 for 2 states like Golly
+impl fmt::Display for LargerThanLife {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let states = if self.states == 2 { 0 } else { self.states };
+        let neighborhood = match self.neighborhood {
+            Neighborhood::Moore => "M",
+            Neighborhood::VonNeumann => "N",
+            Neighborhood::Hexagonal => "H",
+        };
+        write!(
+            f,
+            "R{},C{},M{},S{}..{},B{}..{},N{}",
+            self.range,
+            states,
+            self.middle as u8,
+            self.survival.0,
+            self.survival.1,
+            self.birth.0,
+            self.birth.1,
+            neighborhood
+        )
+    }
+}
+
+/// Takes Golly's R5,C0,M1,S34..58,B34..45,NM. C, M and N can be left out for 2
+/// states, no middle and Moore, and a lone number is an interval of one
+impl FromStr for LargerThanLife {
+    type Err = LifeError;
+    fn from_str(rulestring: &str) -> LifeResult<Self> {
+        let (mut range, mut states, mut middle, mut survival, mut birth, mut neighborhood) =
+            (None, None, None, None, None, None);
+        let number = |value: &str| -> LifeResult<u32> {
+            value
+                .trim()
+                .parse::<u32>()
+                .map_err(|_| invalid_rule(rulestring, &format!("{} is not a number", value)))
+        };
+        let interval = |value: &str| -> LifeResult<(u32, u32)> {
+            let mut ends = value.splitn(2, "..");
+            let from = number(ends.next().unwrap_or(""))?;
+            let to = match ends.next() {
+                Some(to) => number(to)?,
+                None => from,
+            };
+            Ok((from, to))
+        };
+        for part in rulestring.trim().split(',').map(str::trim) {
+            let key = part.chars().next().map(|key| key.to_ascii_uppercase());
+            let value = part.get(1..).unwrap_or("");
+            match key {
+                Some('R') if range.is_none() => range = Some(number(value)?),
+                Some('C') if states.is_none() => states = Some(number(value)?),
+                Some('M') if middle.is_none() => middle = Some(number(value)?),
+                Some('S') if survival.is_none() => survival = Some(interval(value)?),
+                Some('B') if birth.is_none() => birth = Some(interval(value)?),
+                Some('N') if neighborhood.is_none() => {
+                    neighborhood = Some(match value.to_ascii_uppercase().as_str() {
+                        "M" => Neighborhood::Moore,
+                        "N" => Neighborhood::VonNeumann,
+                        "H" => Neighborhood::Hexagonal,
+                        _ => return Err(invalid_rule(rulestring, &format!("N{} is not a neighborhood", value))),
+                    })
+                }
+                _ => return Err(invalid_rule(rulestring, "expected something like R5,C0,M1,S34..58,B34..45,NM")),
+            }
+        }
+        let range = match range {
+            Some(range) if (1..=MAX_RANGE as u32).contains(&range) => range as i32,
+            Some(_) => return Err(invalid_rule(rulestring, &format!("the range has to be 1 to {}", MAX_RANGE))),
+            None => return Err(invalid_rule(rulestring, "it needs a range")),
+        };
+        // Golly takes C0 and C1 as 2 states as well
+        let states = match states.unwrap_or(0) {
+            0 | 1 => 2,
+            states if states as usize <= MAX_STATES => states as usize,
+            _ => {
+                return Err(invalid_rule(
+                    rulestring,
+                    &format!("the number of states has to be 2 to {}", MAX_STATES),
+                ))
+            }
+        };
+        let middle = match middle.unwrap_or(0) {
+            0 => false,
+            1 => true,
+            _ => return Err(invalid_rule(rulestring, "M has to be 0 or 1")),
+        };
+        let neighborhood = neighborhood.unwrap_or(Neighborhood::Moore);
+        let most = neighborhood.cells_within(range) - !middle as u32;
+        let (survival, birth) = match (survival, birth) {
+            (Some(survival), Some(birth)) => (survival, birth),
+            _ => return Err(invalid_rule(rulestring, "it needs both B and S")),
+        };
+        for &(from, to) in [survival, birth].iter() {
+            if from > to || to > most {
+                return Err(invalid_rule(
+                    rulestring,
+                    &format!("{}..{} is not an interval of 0 to {} cells", from, to, most),
+                ));
+            }
+        }
+        Ok(LargerThanLife::new(range, states, middle, survival, birth, neighborhood))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::PatternBuilder;
+
+    fn rule(rulestring: &str) -> LargerThanLife {
+        rulestring.parse().unwrap()
+    }
+
+    #[test]
+    fn test_LargerThanLife_parse_and_display() {
+        let bosco = rule("R5,C0,M1,S34..58,B34..45,NM");
+        assert_eq!(bosco, LargerThanLife::new(5, 2, true, (34, 58), (34, 45), Neighborhood::Moore));
+        assert_eq!(bosco.to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+        assert_eq!(rule("r5,m1,s34..58,b34..45"), bosco);
+        assert_eq!(rule("R2,C3,M0,S4,B5..6,NN").to_string(), "R2,C3,M0,S4..4,B5..6,NN");
+        for bad in [
+            "R0,C0,M1,S1..2,B1..2,NM",
+            "R501,C0,M1,S1..2,B1..2,NM",
+            "R1,C0,M2,S1..2,B1..2,NM",
+            "R1,C0,M1,S3..2,B1..2,NM",
+            "R1,C0,M0,S1..9,B1..2,NM",
+            "R1,C0,M1,S1..2,B1..2,NX",
+            "R1,C0,M1,S1..2",
+            "R1,R1,S1..2,B1..2",
+            "B3/S23",
+        ]
+        .iter()
+        {
+            assert!(bad.parse::<LargerThanLife>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_LargerThanLife_range_1_is_outer_totalistic() {
+        // S2..3/B3..3 without the middle is Life, and the same on the other
+        // neighborhoods is Generations with an H or V
+        let cases = [
+            ("R1,C0,M0,S2..3,B3..3,NM", "B3/S23"),
+            ("R1,C0,M0,S1..2,B2..2,NN", "B2/S12V"),
+            ("R1,C3,M0,S2..4,B2..3,NH", "B23/S234/C3H"),
+        ];
+        for &(larger, generations) in cases.iter() {
+            let larger = rule(larger);
+            let generations: Generations = generations.parse().unwrap();
+            let mut vec = PatternBuilder::with_size(30, 20).make_random_seeded((0, 0), 30, 20, 3, 0.4).build();
+            for _ in 0..10 {
+                let mut expected = vec.clone();
+                generations.next_cells(0, &vec, &mut expected);
+                let mut next = vec.clone();
+                larger.next_cells(0, &vec, &mut next);
+                assert!(next == expected, "{}", larger);
+                vec = next;
+            }
+        }
+    }
+
+    #[test]
+    fn test_LargerThanLife_next_cells_agrees_with_next_state() {
+        // runs that start and end part way through a row, for every neighborhood
+        let vec = PatternBuilder::with_size(23, 19).make_random_seeded((0, 0), 23, 19, 9, 0.5).build();
+        let rulestrings = ["R3,C0,M1,S10..30,B12..20,NM", "R4,C4,M0,S8..20,B9..15,NN", "R2,C0,M1,S6..12,B5..9,NH"];
+        for &rulestring in rulestrings.iter() {
+            let larger = rule(rulestring);
+            let expected: Vec<CellState> = (0..vec.len())
+                .map(|idx| {
+                    let (i, j) = get_location_from_idx(idx, vec.width());
+                    larger.next_state(i, j, &vec)
+                })
+                .collect();
+            for &(start, end) in [(0, vec.len()), (30, 31), (17, 120), (200, vec.len())].iter() {
+                let mut out = vec![DEAD; end - start];
+                larger.next_cells(start, &vec, &mut out);
+                assert_eq!(&out[..], &expected[start..end], "{} from {}", rulestring, start);
+            }
+        }
+    }
+
+    #[test]
+    fn test_LargerThanLife_bosco() {
+        // Bosco's rule: a solid block that is too small just dies off, and
+        // anything alive stays within range of where it was
+        let bosco = rule("R5,C0,M1,S34..58,B34..45,NM");
+        let mut vec = PatternBuilder::with_size(40, 40).make_random_seeded((17, 17), 5, 5, 0, 1.0).build();
+        let mut next = vec.clone();
+        bosco.next_cells(0, &vec, &mut next);
+        assert_eq!(next.population(), 0);
+
+        vec = PatternBuilder::with_size(60, 60).make_random_seeded((20, 20), 20, 20, 12, 0.5).build();
+        let mut next = vec.clone();
+        bosco.next_cells(0, &vec, &mut next);
+        for idx in 0..next.len() {
+            let (i, j) = get_location_from_idx(idx, next.width());
+            if next.is_alive(i, j) {
+                assert!((15..45).contains(&i) && (15..45).contains(&j), "({}, {})", i, j);
+            }
+        }
+    }
+}
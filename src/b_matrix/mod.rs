@@ -1,11 +1,13 @@
 use std::thread::JoinHandle;
 use std::thread;
-use std::mem;
-use std::time::{SystemTime, UNIX_EPOCH};
-use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
 
 // for globals
 use super::*;
+use crate::patterns::SoupInfo;
+use crate::tracker::{SpaceshipTracker, Track, TrackEvent};
 
 mod sync_utils;
 use sync_utils::*;
@@ -15,6 +17,39 @@ pub use b_matrix_vector::*;
 
 mod engine;
 pub use engine::*;
+
+mod rule;
+pub use rule::*;
+
+mod neighborhood;
+pub use neighborhood::*;
+
+mod larger_than_life;
+pub use larger_than_life::*;
+
+mod rule_table;
+pub use rule_table::*;
+
+mod isotropic;
+pub use isotropic::*;
+
+mod elementary;
+pub use elementary::*;
+
+mod cycle;
+pub use cycle::*;
+
+mod age;
+pub use age::*;
+
+mod edit;
+pub use edit::*;
+
+mod history;
+pub use history::*;
+
+#[cfg(test)]
+mod equivalence_tests;
 // ************  MAIN CODE  ************   
 pub struct MainWorkerHandle(JoinHandle<()>);
 impl MainWorkerHandle{
@@ -23,107 +58,698 @@ impl MainWorkerHandle{
     }
 }
 
-#[derive(Copy,Clone)]
-pub enum WorkFlag{
-    InProgress,
-    Done
+// Lets the worker decide on its own how far to go, so BMatrix never has to wait on it
+struct StepControl {
+    // latest generation the renderer picked up
+    displayed_generation: AtomicU64,
+    // how many generations the worker may get ahead of displayed_generation
+    run_ahead: AtomicU32,
+    // worker goes here as fast as it can, regardless of run_ahead
+    target_generation: AtomicU64,
+    // how many generations the cycle detector remembers, 0 turns it off
+    cycle_history: AtomicUsize,
+    track_spaceships: AtomicBool,
+    compute_centroid: AtomicBool,
+    // how many generations can be stepped back through, 0 turns it off
+    generation_history: AtomicUsize,
+    track_ages: AtomicBool,
+    remember_alive_before: AtomicBool,
+    // how many generations activity covers, 0 turns it off
+    activity_window: AtomicU32,
+}
+
+// Anything that changes the universe out of turn. Kept in one queue since
+// the order matters, e.g. an undo right after an edit
+enum Change {
+    Edit(Edit),
+    Undo,
+    Redo,
+    StepBack(u64),
+    SetRule(Arc<dyn Rule>),
+}
+
+// the renderer still gets a snapshot every RUN_TO_CHUNK generations during run_to_generation
+const RUN_TO_CHUNK: u64 = 16;
+
+/// What the worker hands over to the renderer after every generation
+#[derive(Clone, Default)]
+pub struct Snapshot {
+    pub vec: BMatrixVector,
+    pub generation: u64,
+    // random parts of the seed this generation came from
+    pub soups: Vec<SoupInfo>,
+    // None until the cycle detector finds one, or if it is off
+    pub cycle: Option<Cycle>,
+    // both stay empty unless spaceship tracking is on
+    pub spaceships: Vec<Track>,
+    pub spaceship_events: Vec<TrackEvent>,
+    // in cells, None if everything is dead or nobody asked for it
+    pub centroid: Option<(f32, f32)>,
+    // earliest generation step_back can get to, None if the history is off
+    pub oldest_in_history: Option<u64>,
+    // None unless age or activity tracking is on. Shared with the worker,
+    // which only ever works out the next ones into a spare
+    pub ages: Option<Arc<CellAges>>,
+    pub activity: Option<Arc<Activity>>,
+    // None unless asked for, and after a seed or edit, which nothing came before
+    pub alive_before: Option<AliveBefore>,
+}
+impl Snapshot {
+    /// 0 by 0 universe with nothing in it, mostly for filling in the rest with ..
+    pub fn empty() -> Self {
+        Snapshot {
+            vec: BMatrixVector::new(0, 0),
+            generation: 0,
+            soups: Vec::new(),
+            cycle: None,
+            spaceships: Vec::new(),
+            spaceship_events: Vec::new(),
+            centroid: None,
+            oldest_in_history: None,
+            ages: None,
+            activity: None,
+            alive_before: None,
+        }
+    }
 }
 
 pub struct BMatrix {
-    pub vec: MyArcRwLock<BMatrixVector>,
-    pub new_vec: MyArcMut<BMatrixVector>,
+    // renderer and worker never block on each other, see TripleBuffer
+    snapshots: TripleBufferReader<Snapshot>,
+    pending_seed: MyArcMut<Option<Snapshot>>,
+    pending_changes: MyArcMut<Vec<Change>>,
+    // whatever went wrong on the worker, until take_errors picks it up
+    errors: MyArcMut<Vec<LifeError>>,
     main_worker_thread: MainWorkerHandle,
-    status: MyArcMut<WorkFlag>,
+    control: Arc<StepControl>,
 }
 
 impl BMatrix {
     pub fn new(update_method: Backend) -> Self {
-        let vec = MyArcRwLock::new(BMatrixVector::default());
-        let vec2 = vec.clone();
-        let new_vec = MyArcMut::new(BMatrixVector::default());
-        let new_vec2 = new_vec.clone();
+        // empty until the first seed, rather than 3 GRID_SIZE universes nobody looks at
+        let (snapshot_writer, snapshots) = triple_buffer(Snapshot::empty());
+        let pending_seed = MyArcMut::new(None);
+        let pending_seed2 = pending_seed.clone();
+        let pending_changes = MyArcMut::new(Vec::new());
+        let pending_changes2 = pending_changes.clone();
+        let errors = MyArcMut::new(Vec::new());
+        let errors2 = errors.clone();
 
-        let status = MyArcMut::new(WorkFlag::Done);
-        //let status = Arc::new(Mutex::new(WorkFlag::Done));
-        let status2 = status.clone();
+        // one generation per frame unless told otherwise
+        let control = Arc::new(StepControl {
+            displayed_generation: AtomicU64::new(0),
+            run_ahead: AtomicU32::new(1),
+            target_generation: AtomicU64::new(0),
+            cycle_history: AtomicUsize::new(0),
+            track_spaceships: AtomicBool::new(false),
+            compute_centroid: AtomicBool::new(false),
+            generation_history: AtomicUsize::new(0),
+            track_ages: AtomicBool::new(false),
+            remember_alive_before: AtomicBool::new(false),
+            activity_window: AtomicU32::new(0),
+        });
+        let control2 = control.clone();
 
         // Spin up new thread and have it sleep until event loop starts and BMatrix calls signal
         let main_worker_thread = thread::spawn(
             move ||{
-                let mut main_worker = MainWorker::new(update_method,status2,new_vec2,vec2);
+                let mut main_worker = MainWorker::new(update_method,control2,snapshot_writer,pending_seed2,pending_changes2,errors2);
                 main_worker.sync_worker_do_work();
             });
         BMatrix {
-            vec,
-            new_vec,
+            snapshots,
+            pending_seed,
+            pending_changes,
+            errors,
             main_worker_thread: MainWorkerHandle(main_worker_thread),
-            status,
+            control,
         }
     }
 
-    pub fn sync_main_update_backend(&mut self){
-        if let WorkFlag::Done = self.status.get() {
-            // no need to lock since MainWorker can't modify
-            // until we call signal anyways
-            self.update_vector();
+    // set_seed_with_soups for a seed that isn't a soup
+    pub fn set_seed(&mut self, seed: BMatrixVector){
+        self.set_seed_with_soups(seed, Vec::new());
+    }
 
-            self.status.set(WorkFlag::InProgress);
-            
-            self.main_worker_thread.signal();
-        }
+    // Picked up by the worker in between generations, as generation 0. soups
+    // get passed along in every snapshot so the run can be reproduced
+    pub fn set_seed_with_soups(&mut self, seed: BMatrixVector, soups: Vec<SoupInfo>){
+        // a run_to_generation for the old seed would otherwise carry on in this one
+        self.control.target_generation.store(0, Ordering::Release);
+        self.pending_seed.set(Some(Snapshot {
+            vec: seed,
+            soups,
+            ..Snapshot::empty()
+        }));
+        self.main_worker_thread.signal();
+    }
+
+    /// Applied by the worker before it computes the next generation, in the
+    /// order they were queued
+    pub fn queue_edit(&mut self, edit: Edit){
+        self.queue_change(Change::Edit(edit));
+    }
+
+    /// Takes back the last edit. If the universe has moved on since, it goes
+    /// back to the generation the edit was made in, as long as that is still
+    /// in the generation history
+    pub fn undo(&mut self){
+        self.queue_change(Change::Undo);
+    }
+
+    pub fn redo(&mut self){
+        self.queue_change(Change::Redo);
+    }
+
+    /// Goes back `generations` generations, or as far as the generation
+    /// history goes. Edits made after it are forgotten
+    pub fn step_back(&mut self, generations: u64){
+        self.queue_change(Change::StepBack(generations));
+    }
+
+    /// Runs the universe by rule from the current generation on. Cells in
+    /// states the rule does not have die, and since the past was under a
+    /// different rule, the generation history and undo start over
+    pub fn set_rule(&mut self, rule: Arc<dyn Rule>){
+        self.queue_change(Change::SetRule(rule));
+    }
+
+    fn queue_change(&mut self, change: Change){
+        self.pending_changes.grab_lock().push(change);
+        self.main_worker_thread.signal();
+    }
+
+    /// Anything that went wrong on the worker since the last call, oldest first,
+    /// e.g. an undo that needed a generation the history no longer has
+    pub fn take_errors(&mut self) -> Vec<LifeError> {
+        std::mem::take(&mut *self.errors.grab_lock())
+    }
+
+    /// Most recent generation the worker has finished. Never blocks on the worker
+    pub fn latest(&mut self) -> &Snapshot {
+        self.snapshots.read()
+    }
+
+    /// How many generations the worker may compute past the one on screen.
+    /// 1 means one generation per frame
+    pub fn set_run_ahead(&mut self, generations: u32){
+        self.control.run_ahead.store(generations, Ordering::Release);
+        self.main_worker_thread.signal();
+    }
+
+    /// Worker steps straight to `generation` without waiting on the renderer.
+    /// Does nothing if we are already past it
+    pub fn run_to_generation(&mut self, generation: u64){
+        self.control.target_generation.store(generation, Ordering::Release);
+        self.main_worker_thread.signal();
+    }
+
+    /// Has the worker look for the universe repeating itself, catching periods
+    /// up to `history_len`. Shows up in Snapshot::cycle. 0 turns it off
+    pub fn set_cycle_detection(&mut self, history_len: usize){
+        self.control.cycle_history.store(history_len, Ordering::Release);
+        self.main_worker_thread.signal();
+    }
+
+    /// Has the worker keep the last `generations` generations around for
+    /// step_back and undo, see GenerationHistory. 0 turns it off
+    pub fn set_generation_history(&mut self, generations: usize){
+        self.control.generation_history.store(generations, Ordering::Release);
+        self.main_worker_thread.signal();
+    }
+
+    /// Has the worker follow spaceships every generation, see SpaceshipTracker.
+    /// Shows up in Snapshot::spaceships and Snapshot::spaceship_events
+    pub fn set_spaceship_tracking(&mut self, on: bool){
+        self.control.track_spaceships.store(on, Ordering::Release);
+        self.main_worker_thread.signal();
+    }
+
+    /// Has the worker fill in Snapshot::centroid, which costs a pass over the universe
+    pub fn set_centroid_tracking(&mut self, on: bool){
+        self.control.compute_centroid.store(on, Ordering::Release);
+        self.main_worker_thread.signal();
+    }
+
+    /// Has the worker count how long every cell has been alive or dead for.
+    /// Shows up in Snapshot::ages
+    pub fn set_age_tracking(&mut self, on: bool){
+        self.control.track_ages.store(on, Ordering::Release);
+        self.main_worker_thread.signal();
     }
-    fn update_vector(&mut self){
-        // utilizing low level nature of swap function to do shallow swap
-        let mut new_vec_lock = self.new_vec.grab_lock();
-        let new_vec_raw: &mut BMatrixVector = new_vec_lock.deref_mut();
 
-        let mut vec_lock = self.vec.grab_writer_lock();
-        let vec_raw: &mut BMatrixVector = vec_lock.deref_mut();
-        mem::swap(vec_raw,new_vec_raw);
+    /// Has the worker remember which cells were alive the generation before
+    /// each snapshot, 1 bit a cell. Shows up in Snapshot::alive_before
+    pub fn set_alive_before_tracking(&mut self, on: bool){
+        self.control.remember_alive_before.store(on, Ordering::Release);
+        self.main_worker_thread.signal();
+    }
+
+    /// Has the worker count how often every cell changed over the last
+    /// `window` generations, up to MAX_ACTIVITY_WINDOW. Shows up in
+    /// Snapshot::activity. 0 turns it off
+    pub fn set_activity_tracking(&mut self, window: u32){
+        self.control.activity_window.store(window, Ordering::Release);
+        self.main_worker_thread.signal();
+    }
+
+    pub fn sync_main_update_backend(&mut self){
+        let displayed_generation = self.latest().generation;
+        self.control
+            .displayed_generation
+            .store(displayed_generation, Ordering::Release);
+        self.main_worker_thread.signal();
     }
 }
 
 // ************  WORKER CODE  ************   
 struct MainWorker{
-    new_vec: MyArcMut<BMatrixVector>,
-    vec: MyArcRwLock<BMatrixVector>,
-    status: MyArcMut<WorkFlag>,
-    update_engine: Box<dyn Engine>
+    snapshots: TripleBufferWriter<Snapshot>,
+    pending_seed: MyArcMut<Option<Snapshot>>,
+    pending_changes: MyArcMut<Vec<Change>>,
+    errors: MyArcMut<Vec<LifeError>>,
+    control: Arc<StepControl>,
+    update_method: Backend,
+    update_engine: Box<dyn Engine>,
+    // only needed when stepping more than one generation at a time
+    scratch: BMatrixVector,
+    cycle_detector: Option<CycleDetector>,
+    spaceship_tracker: Option<SpaceshipTracker>,
+    history: Option<GenerationHistory>,
+    undo_stack: UndoStack,
+    // the same ones the latest snapshot has
+    ages: Option<Arc<CellAges>>,
+    activity: Option<Arc<Activity>>,
 }
 impl MainWorker{
-    fn new(update_method: Backend, status: MyArcMut<WorkFlag>, new_vec: MyArcMut<BMatrixVector>, vec: MyArcRwLock<BMatrixVector>)->Self{
+    fn new(update_method: Backend, control: Arc<StepControl>, snapshots: TripleBufferWriter<Snapshot>, pending_seed: MyArcMut<Option<Snapshot>>, pending_changes: MyArcMut<Vec<Change>>, errors: MyArcMut<Vec<LifeError>>)->Self{
         let update_engine = engine::create_engine(update_method);
         MainWorker{
-            new_vec,
-            vec,
-            status,
-            update_engine
+            snapshots,
+            pending_seed,
+            pending_changes,
+            errors,
+            control,
+            update_method,
+            update_engine,
+            scratch: BMatrixVector::new(0, 0),
+            cycle_detector: None,
+            spaceship_tracker: None,
+            history: None,
+            undo_stack: UndoStack::new(),
+            ages: None,
+            activity: None,
         }
     }
     fn wait(&self){
         thread::park();
     }
     fn sync_worker_do_work(&mut self){
-        loop{
+        // nothing to do until there is a seed, otherwise it would be stepping
+        // the empty default universe in the meantime
+        while self.pending_seed.grab_lock().is_none() {
             self.wait();
+        }
+        loop{
+            self.sync_cycle_detector();
+            self.sync_spaceship_tracker();
+            self.sync_history();
+            self.sync_ages();
+            let seed = self.pending_seed.grab_lock().take();
+            if let Some(seed) = seed {
+                self.publish_seed(seed);
+                continue;
+            }
+            let changes = std::mem::take(&mut *self.pending_changes.grab_lock());
+            if !changes.is_empty() {
+                self.publish_changes(changes);
+                continue;
+            }
 
-            let sys_time = SystemTime::now();
-            self.backendMethodDispatch();
-            print_time_lapse(sys_time);
+            match self.generations_to_run() {
+                0 => self.wait(),
+                generations => {
+                    let sys_time = SystemTime::now();
+                    self.backendMethodDispatch(generations);
+                    print_time_lapse(sys_time);
+                }
+            }
+        }
+    }
+    fn generations_to_run(&self) -> u32 {
+        let generation = self.snapshots.latest().generation;
 
-            self.status.set(WorkFlag::Done);
+        let target_generation = self.control.target_generation.load(Ordering::Acquire);
+        if target_generation > generation {
+            return (target_generation - generation).min(RUN_TO_CHUNK) as u32;
+        }
+
+        // EC: renderer may not have seen the latest seed yet, in which case
+        // it is still reporting a generation from the old one
+        let displayed_generation = self
+            .control
+            .displayed_generation
+            .load(Ordering::Acquire)
+            .min(generation);
+        let run_ahead = self.control.run_ahead.load(Ordering::Acquire) as u64;
+        if generation < displayed_generation + run_ahead {
+            1
+        } else {
+            0
+        }
+    }
+    // NOTE: turning it on mid run means it only starts watching from the next generation
+    fn sync_cycle_detector(&mut self) {
+        let history_len = self.control.cycle_history.load(Ordering::Acquire);
+        let current_len = self.cycle_detector.as_ref().map_or(0, |detector| detector.history_len());
+        if history_len != current_len {
+            self.cycle_detector = if history_len == 0 {
+                None
+            } else {
+                Some(CycleDetector::new(history_len))
+            };
+        }
+    }
+    fn sync_spaceship_tracker(&mut self) {
+        let on = self.control.track_spaceships.load(Ordering::Acquire);
+        if on != self.spaceship_tracker.is_some() {
+            self.spaceship_tracker = if on { Some(SpaceshipTracker::new()) } else { None };
+        }
+    }
+    fn sync_history(&mut self) {
+        let generations = self.control.generation_history.load(Ordering::Acquire);
+        let current = self.history.as_ref().map_or(0, |history| history.max_generations());
+        if generations != current {
+            self.history = if generations == 0 {
+                None
+            } else {
+                // starts from whatever is on screen
+                let latest = self.snapshots.latest();
+                let mut history = GenerationHistory::new(generations);
+                history.reset(latest.generation, &latest.vec);
+                Some(history)
+            };
+        }
+    }
+    // both start from whatever is on screen
+    fn sync_ages(&mut self) {
+        let on = self.control.track_ages.load(Ordering::Acquire);
+        if on != self.ages.is_some() {
+            self.ages = if on {
+                let mut ages = CellAges::new();
+                ages.reset(&self.snapshots.latest().vec);
+                Some(Arc::new(ages))
+            } else {
+                None
+            };
+        }
+        let window = self.control.activity_window.load(Ordering::Acquire).min(MAX_ACTIVITY_WINDOW);
+        let current = self.activity.as_ref().map_or(0, |activity| activity.window());
+        if window != current {
+            self.activity = if window == 0 {
+                None
+            } else {
+                let mut activity = Activity::new(window);
+                activity.reset(&self.snapshots.latest().vec);
+                Some(Arc::new(activity))
+            };
+        }
+    }
+    fn publish_seed(&mut self, seed: Snapshot) {
+        self.undo_stack.clear();
+        if let Some(history) = self.history.as_mut() {
+            history.reset(seed.generation, &seed.vec);
+        }
+        self.publish_restarted(seed, None);
+    }
+    // republishes the current generation with the changes on top
+    fn publish_changes(&mut self, changes: Vec<Change>) {
+        let mut snapshot = self.snapshots.latest().clone();
+        // cells the changes flipped, None once one of them went to another
+        // generation or rule
+        let mut changed = Some(CellDiff::default());
+        for change in changes {
+            let result = match change {
+                Change::Edit(edit) => Ok(Some(self.apply_edit(&mut snapshot, &edit))),
+                Change::Undo => self.undo(&mut snapshot, false),
+                Change::Redo => self.undo(&mut snapshot, true),
+                Change::SetRule(rule) => {
+                    self.set_rule(&mut snapshot, rule);
+                    Ok(None)
+                }
+                Change::StepBack(generations) => {
+                    let oldest = self.history.as_ref().and_then(|history| history.oldest());
+                    let rewound = oldest.and_then(|oldest| {
+                        let target = snapshot.generation.saturating_sub(generations).max(oldest);
+                        self.rewind(&mut snapshot, target)
+                    });
+                    Ok(if rewound.is_some() { None } else { Some(CellDiff::default()) })
+                }
+            };
+            match result {
+                Ok(Some(diff)) => changed = changed.map(|changed| changed.then(&diff)),
+                Ok(None) => changed = None,
+                // the renderer picks these up with take_errors
+                Err(err) => self.errors.grab_lock().push(err),
+            }
+        }
+        self.publish_restarted(snapshot, changed.as_ref());
+    }
+    fn set_rule(&mut self, snapshot: &mut Snapshot, rule: Arc<dyn Rule>) {
+        snapshot.vec.drop_states_from(rule.states());
+        self.update_engine = engine::create_engine_with_rule(self.update_method, rule);
+        self.undo_stack.clear();
+        if let Some(history) = self.history.as_mut() {
+            history.reset(snapshot.generation, &snapshot.vec);
+        }
+    }
+    // the cells it changed
+    fn apply_edit(&mut self, snapshot: &mut Snapshot, edit: &Edit) -> CellDiff {
+        let touched = edit.touched();
+        let before = snapshot.vec.copy_region(touched);
+        edit.apply(&mut snapshot.vec);
+        let diff = CellDiff::in_region(&before, &snapshot.vec, touched);
+        // e.g. clearing nothing, which is not worth an undo
+        if diff.is_empty() {
+            return diff;
+        }
+        if let Some(history) = self.history.as_mut() {
+            history.amend(&diff);
         }
+        self.undo_stack.push(EditRecord {
+            generation: snapshot.generation,
+            diff: diff.clone(),
+        });
+        diff
     }
-    fn backendMethodDispatch(&mut self) {
-        // so I can temporarily bypass arc + mutex restrictions
-        // for multi-threading purposes
-        let mut new_vec_lock = self.new_vec.grab_lock();
-        let new_vec_raw = new_vec_lock.deref_mut();
+    // undo and redo are the same thing, since flipping the edit's cells again
+    // takes them back to how they were. The cells that changed, or None if it
+    // had to go back to the generation the edit was made in
+    fn undo(&mut self, snapshot: &mut Snapshot, redo: bool) -> LifeResult<Option<CellDiff>> {
+        let record = if redo { self.undo_stack.pop_redo() } else { self.undo_stack.pop_undo() };
+        let record = match record {
+            Some(record) => record,
+            None => return Ok(Some(CellDiff::default())),
+        };
+        let rewound = record.generation != snapshot.generation;
+        if rewound && self.rewind(snapshot, record.generation) != Some(record.generation) {
+            return Err(LifeError::NotInHistory {
+                generation: record.generation,
+            });
+        }
+        self.flip(snapshot, &record.diff);
+        let changed = if rewound { None } else { Some(record.diff.clone()) };
+        if redo {
+            self.undo_stack.push_undo(record);
+        } else {
+            self.undo_stack.push_redo(record);
+        }
+        Ok(changed)
+    }
+    fn flip(&mut self, snapshot: &mut Snapshot, diff: &CellDiff) {
+        diff.apply(&mut snapshot.vec);
+        if let Some(history) = self.history.as_mut() {
+            history.amend(diff);
+        }
+    }
+    // None if the history is off or does not go back that far
+    fn rewind(&mut self, snapshot: &mut Snapshot, target: u64) -> Option<u64> {
+        let history = self.history.as_mut()?;
+        let mut generation = history.rewind(target, &mut snapshot.vec)?;
+        // the history only has what the renderer was handed, which skips
+        // generations while running to one, so the rest gets recomputed
+        let mut next = snapshot.vec.clone();
+        while generation < target {
+            self.update_engine.next_b_matrix(&snapshot.vec, &mut next);
+            generation += 1;
+            history.record(generation, &snapshot.vec, &next);
+            std::mem::swap(&mut snapshot.vec, &mut next);
+        }
+        snapshot.generation = generation;
+        self.undo_stack.forget_after(generation);
+        // otherwise a run_to_generation from before would take it straight back
+        self.control.target_generation.fetch_min(generation, Ordering::AcqRel);
+        Some(generation)
+    }
+    // NOTE: whatever the cycle detector and tracker remember is about a universe
+    // that no longer exists, so they start over from this snapshot. Ages and
+    // activity only do for the changed cells, or everywhere if that is None
+    fn publish_restarted(&mut self, mut snapshot: Snapshot, changed: Option<&CellDiff>) {
+        let generation = snapshot.generation;
+        snapshot.cycle = None;
+        if let Some(detector) = self.cycle_detector.as_mut() {
+            detector.reset();
+            snapshot.cycle = detector.observe(generation, &snapshot.vec);
+        }
+        snapshot.spaceships.clear();
+        snapshot.spaceship_events.clear();
+        if let Some(tracker) = self.spaceship_tracker.as_mut() {
+            tracker.reset();
+            tracker.observe(generation, &snapshot.vec);
+            snapshot.spaceships = tracker.tracks().cloned().collect();
+            snapshot.spaceship_events = tracker.events().to_vec();
+        }
+        // the latest snapshot still has them, so these make a copy, but only
+        // once an edit or seed
+        if let Some(ages) = self.ages.as_mut() {
+            let ages = Arc::make_mut(ages);
+            match changed {
+                Some(changed) => ages.reset_cells(&snapshot.vec, changed.indices()),
+                None => ages.reset(&snapshot.vec),
+            }
+        }
+        if let Some(activity) = self.activity.as_mut() {
+            let activity = Arc::make_mut(activity);
+            match changed {
+                Some(changed) => activity.reset_cells(&snapshot.vec, changed.indices()),
+                None => activity.reset(&snapshot.vec),
+            }
+        }
+        snapshot.ages.clone_from(&self.ages);
+        snapshot.activity.clone_from(&self.activity);
+        snapshot.alive_before = None;
+        snapshot.centroid = centroid_if_wanted(&self.control, &snapshot.vec);
+        snapshot.oldest_in_history = self.history.as_ref().and_then(|history| history.oldest());
+        *self.snapshots.back_mut() = snapshot;
+        self.snapshots.publish();
+    }
+    fn backendMethodDispatch(&mut self, generations: u32) {
+        let (old_snapshot, new_snapshot) = self.snapshots.latest_and_back_mut();
+        // back buffer/scratch may be from before a seed of a different size,
+        // or the same size turned the other way
+        let same_shape = |vec: &BMatrixVector| {
+            vec.width() == old_snapshot.vec.width() && vec.height() == old_snapshot.vec.height()
+        };
+        if !same_shape(&new_snapshot.vec) {
+            new_snapshot.vec = old_snapshot.vec.clone();
+        }
+        if generations > 1 && !same_shape(&self.scratch) {
+            self.scratch = old_snapshot.vec.clone();
+        }
+        let old_generation = old_snapshot.generation;
+        // only the generation right before the last one, so for more than one
+        // it has to be caught on the way past
+        let mut alive_before = new_snapshot.alive_before.take();
+        if !self.control.remember_alive_before.load(Ordering::Acquire) {
+            alive_before = None;
+        } else if generations == 1 {
+            alive_before.get_or_insert_with(AliveBefore::default).record(&old_snapshot.vec);
+        } else {
+            alive_before.get_or_insert_with(AliveBefore::default);
+        }
+        // the latest ones are still being drawn, so the next ones go in the
+        // back buffer's, unless something else still holds on to those
+        let mut next_ages = self
+            .ages
+            .as_ref()
+            .map(|_| unshared(new_snapshot.ages.take()).unwrap_or_default());
+        let mut next_activity = self
+            .activity
+            .as_ref()
+            .map(|current| unshared(new_snapshot.activity.take()).unwrap_or_else(|| Activity::new(current.window())));
+        // the generations in between only matter to whatever watches them go by
+        let observed = self.cycle_detector.is_some()
+            || self.spaceship_tracker.is_some()
+            || self.ages.is_some()
+            || self.activity.is_some()
+            || (alive_before.is_some() && generations > 1);
+        if !observed {
+            self.update_engine
+                .step_n(generations, &old_snapshot.vec, &mut new_snapshot.vec, &mut self.scratch);
+        } else {
+            let cycle_detector = &mut self.cycle_detector;
+            let spaceship_tracker = &mut self.spaceship_tracker;
+            let ages = self.ages.as_deref();
+            let activity = self.activity.as_deref();
+            self.update_engine.step_n_observed(
+                generations,
+                &old_snapshot.vec,
+                &mut new_snapshot.vec,
+                &mut self.scratch,
+                &mut |step, vec| {
+                    let generation = old_generation + step as u64;
+                    if let Some(detector) = cycle_detector.as_mut() {
+                        detector.observe(generation, vec);
+                    }
+                    if let Some(tracker) = spaceship_tracker.as_mut() {
+                        tracker.observe(generation, vec);
+                    }
+                    if let (Some(next), Some(ages)) = (next_ages.as_mut(), ages) {
+                        if step == 1 {
+                            next.observe_from(ages, vec);
+                        } else {
+                            next.observe(vec);
+                        }
+                    }
+                    if let (Some(next), Some(activity)) = (next_activity.as_mut(), activity) {
+                        if step == 1 {
+                            next.observe_from(activity, vec);
+                        } else {
+                            next.observe(vec);
+                        }
+                    }
+                    if step + 1 == generations {
+                        if let Some(alive_before) = alive_before.as_mut() {
+                            alive_before.record(vec);
+                        }
+                    }
+                },
+            );
+        }
+        new_snapshot.generation = old_generation + generations as u64;
+        new_snapshot.soups.clone_from(&old_snapshot.soups);
+        new_snapshot.cycle = self.cycle_detector.as_ref().and_then(|detector| detector.cycle());
+        new_snapshot.spaceships.clear();
+        new_snapshot.spaceship_events.clear();
+        if let Some(tracker) = self.spaceship_tracker.as_ref() {
+            new_snapshot.spaceships.extend(tracker.tracks().cloned());
+            new_snapshot.spaceship_events.extend_from_slice(tracker.events());
+        }
+        self.ages = next_ages.map(Arc::new);
+        self.activity = next_activity.map(Arc::new);
+        new_snapshot.ages.clone_from(&self.ages);
+        new_snapshot.activity.clone_from(&self.activity);
+        new_snapshot.alive_before = alive_before;
+        new_snapshot.centroid = centroid_if_wanted(&self.control, &new_snapshot.vec);
+        if let Some(history) = self.history.as_mut() {
+            history.record(new_snapshot.generation, &old_snapshot.vec, &new_snapshot.vec);
+        }
+        new_snapshot.oldest_in_history = self.history.as_ref().and_then(|history| history.oldest());
+        self.snapshots.publish();
+    }
+}
 
-        let vec_lock = self.vec.grab_reader_lock();
-        let vec_raw = vec_lock.deref();
+// what was in the Arc, if nothing else holds on to it
+fn unshared<T>(arc: Option<Arc<T>>) -> Option<T> {
+    arc.and_then(|arc| Arc::try_unwrap(arc).ok())
+}
 
-        self.update_engine.next_b_matrix(vec_raw,new_vec_raw);
+// free function so it can be called while the back buffer is borrowed
+fn centroid_if_wanted(control: &StepControl, vec: &BMatrixVector) -> Option<(f32, f32)> {
+    if control.compute_centroid.load(Ordering::Acquire) {
+        vec.centroid()
+    } else {
+        None
     }
 }
 
@@ -133,3 +759,365 @@ fn print_time_lapse(sys_time: SystemTime){
         .expect("System clock did something funny");
     println!("Time elapsed: {}ms", time_lapse.as_millis());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::PatternBuilder;
+    use std::time::Duration;
+
+    #[test]
+    fn test_BMatrix_renderer_and_worker_run_concurrently_without_torn_frames() {
+        let width = 64;
+        let phase_even = PatternBuilder::with_size(width, width)
+            .make_blinker(30, 30)
+            .make_square(5, 5)
+            .build();
+        let mut phase_odd = phase_even.clone();
+        create_engine(Backend::Single).next_b_matrix(&phase_even, &mut phase_odd);
+
+        let mut b_matrix = BMatrix::new(Backend::Rayon);
+        b_matrix.set_seed(phase_even.clone());
+
+        let start = SystemTime::now();
+        let mut last_generation = 0;
+        while last_generation < 500 {
+            b_matrix.sync_main_update_backend();
+            let snapshot = b_matrix.latest();
+            // still showing the initial empty universe until the seed is picked up
+            if snapshot.vec.width() != 0 {
+                let expected = if snapshot.generation % 2 == 0 {
+                    &phase_even
+                } else {
+                    &phase_odd
+                };
+                assert!(
+                    snapshot.vec == *expected,
+                    "generation {} does not match its contents",
+                    snapshot.generation
+                );
+                assert!(snapshot.generation >= last_generation);
+                last_generation = snapshot.generation;
+            }
+            assert!(start.elapsed().unwrap() < Duration::from_secs(60));
+            thread::yield_now();
+        }
+    }
+
+    // polls since the worker gets there on its own time
+    #[test]
+    fn test_BMatrix_queued_edits_land_between_generations() {
+        let mut b_matrix = BMatrix::new(Backend::Single);
+        b_matrix.set_seed(BMatrixVector::new(16, 16));
+        let block = PatternBuilder::with_size(2, 2).make_square(0, 0).build();
+        b_matrix.queue_edit(Edit::Paste { pattern: block, x: 3, y: 3 });
+        b_matrix.queue_edit(Edit::Clear(BoundingBox::spanning((4, 3), (4, 4))));
+        b_matrix.run_to_generation(6);
+
+        // half the block is not a still life, the whole one would still be there
+        let snapshot = wait_for_generation(&mut b_matrix, 6);
+        assert_eq!(snapshot.vec.population(), 0);
+
+        b_matrix.queue_edit(Edit::Paste {
+            pattern: PatternBuilder::with_size(2, 2).make_square(0, 0).build(),
+            x: 10,
+            y: 10,
+        });
+        b_matrix.run_to_generation(12);
+        let snapshot = wait_for_generation(&mut b_matrix, 12);
+        assert_eq!(
+            snapshot.vec.bounding_box(),
+            Some(BoundingBox { x: 10, y: 10, width: 2, height: 2 })
+        );
+    }
+
+    #[test]
+    fn test_BMatrix_undo_redo_and_step_back() {
+        let block = || PatternBuilder::with_size(2, 2).make_square(0, 0).build();
+        let mut b_matrix = BMatrix::new(Backend::Single);
+        b_matrix.set_run_ahead(0);
+        b_matrix.set_generation_history(32);
+        b_matrix.set_seed(BMatrixVector::new(16, 16));
+        b_matrix.queue_edit(Edit::Paste { pattern: block(), x: 3, y: 3 });
+        b_matrix.run_to_generation(5);
+        assert_eq!(wait_for_generation(&mut b_matrix, 5).vec.population(), 4);
+
+        // takes it back to the generation the block went in at
+        b_matrix.undo();
+        let snapshot = wait_until(&mut b_matrix, "undo", |snapshot| snapshot.generation == 0);
+        assert_eq!(snapshot.vec.population(), 0);
+        assert_eq!(snapshot.oldest_in_history, Some(0));
+        b_matrix.redo();
+        wait_until(&mut b_matrix, "redo", |snapshot| snapshot.vec.population() == 4);
+
+        b_matrix.run_to_generation(8);
+        wait_for_generation(&mut b_matrix, 8);
+        b_matrix.queue_edit(Edit::Paste { pattern: block(), x: 10, y: 10 });
+        wait_until(&mut b_matrix, "second block", |snapshot| snapshot.vec.population() == 8);
+        // generation 5 was never handed over on its own, so it gets recomputed
+        b_matrix.step_back(3);
+        let snapshot = wait_until(&mut b_matrix, "step back", |snapshot| snapshot.generation == 5);
+        assert_eq!(snapshot.vec.population(), 4);
+        // the second block never happened now, so undo goes straight to the first
+        b_matrix.redo();
+        b_matrix.undo();
+        let snapshot = wait_until(&mut b_matrix, "undo again", |snapshot| snapshot.generation == 0);
+        assert_eq!(snapshot.vec.population(), 0);
+    }
+
+    #[test]
+    fn test_BMatrix_undo_without_history_is_an_error() {
+        let mut b_matrix = BMatrix::new(Backend::Single);
+        b_matrix.set_run_ahead(0);
+        b_matrix.set_seed(BMatrixVector::new(16, 16));
+        let block = PatternBuilder::with_size(2, 2).make_square(0, 0).build();
+        b_matrix.queue_edit(Edit::Paste { pattern: block, x: 3, y: 3 });
+        b_matrix.run_to_generation(2);
+        wait_for_generation(&mut b_matrix, 2);
+        b_matrix.undo();
+        let start = SystemTime::now();
+        let errors = loop {
+            let errors = b_matrix.take_errors();
+            if !errors.is_empty() {
+                break errors;
+            }
+            assert!(start.elapsed().unwrap() < Duration::from_secs(60), "undo never failed");
+            thread::yield_now();
+        };
+        match errors[..] {
+            [LifeError::NotInHistory { generation: 0 }] => (),
+            _ => panic!("{:?}", errors),
+        }
+        assert_eq!(b_matrix.latest().vec.population(), 4);
+        assert!(b_matrix.take_errors().is_empty());
+    }
+
+    #[test]
+    fn test_BMatrix_set_rule() {
+        // Brian's Brain domino, see rule.rs
+        let mut seed = BMatrixVector::new(16, 16);
+        *seed.at_mut(5, 5).unwrap() = ALIVE;
+        *seed.at_mut(5, 6).unwrap() = ALIVE;
+        let mut b_matrix = BMatrix::new(Backend::Single);
+        b_matrix.set_run_ahead(0);
+        b_matrix.set_generation_history(8);
+        // queued before the seed, so it is in by the time the seed is picked up
+        b_matrix.set_rule(parse_rule("B2/S/C3").unwrap());
+        b_matrix.set_seed(seed);
+        b_matrix.run_to_generation(1);
+        let snapshot = wait_for_generation(&mut b_matrix, 1);
+        assert_eq!((snapshot.vec.state(5, 5), snapshot.vec.state(5, 6)), (2, 2));
+        assert_eq!(snapshot.vec.population(), 6);
+
+        // Life has no state 2, so the dying cells go, and the history starts again
+        b_matrix.set_rule(Arc::new(Generations::life()));
+        let snapshot = wait_until(&mut b_matrix, "Life", |snapshot| snapshot.vec.state(5, 5) == DEAD);
+        assert_eq!((snapshot.generation, snapshot.vec.population()), (1, 4));
+        assert_eq!(snapshot.oldest_in_history, Some(1));
+    }
+
+    fn wait_for_generation(b_matrix: &mut BMatrix, generation: u64) -> Snapshot {
+        wait_until(b_matrix, &format!("generation {}", generation), |snapshot| {
+            snapshot.generation >= generation
+        })
+    }
+
+    // skips the empty snapshot from before the seed got picked up
+    fn wait_until(b_matrix: &mut BMatrix, what: &str, done: impl Fn(&Snapshot) -> bool) -> Snapshot {
+        let start = SystemTime::now();
+        loop {
+            let snapshot = b_matrix.latest();
+            if snapshot.vec.width() != 0 && done(snapshot) {
+                return snapshot.clone();
+            }
+            assert!(
+                start.elapsed().unwrap() < Duration::from_secs(60),
+                "worker never got to {}",
+                what
+            );
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_BMatrix_run_ahead_stops_at_limit() {
+        let seed = PatternBuilder::with_size(32, 32).make_r_pentomino(14, 14).build();
+        let mut b_matrix = BMatrix::new(Backend::Single);
+        b_matrix.set_run_ahead(5);
+        b_matrix.set_seed(seed);
+
+        let snapshot = wait_for_generation(&mut b_matrix, 5);
+        assert_eq!(snapshot.generation, 5);
+        // renderer hasn't reported anything past 0, so the worker should be waiting
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(b_matrix.latest().generation, 5);
+
+        b_matrix.sync_main_update_backend();
+        let snapshot = wait_for_generation(&mut b_matrix, 10);
+        assert_eq!(snapshot.generation, 10);
+    }
+
+    #[test]
+    fn test_BMatrix_snapshots_keep_soups() {
+        let (seed, soups) = PatternBuilder::with_size(32, 32)
+            .make_random_seeded((8, 8), 16, 16, 1234, 0.4)
+            .build_with_soups();
+        let mut b_matrix = BMatrix::new(Backend::Single);
+        b_matrix.set_run_ahead(3);
+        b_matrix.set_seed_with_soups(seed, soups.clone());
+
+        let snapshot = wait_for_generation(&mut b_matrix, 3);
+        assert_eq!(snapshot.soups, soups);
+        assert_eq!(snapshot.soups[0].seed, 1234);
+    }
+
+    #[test]
+    fn test_BMatrix_reports_cycle_while_running_to_generation() {
+        // T-tetromino settles into a traffic light, see cycle.rs
+        let seed = PatternBuilder::with_size(32, 32).make_t(14, 14).build();
+        let mut b_matrix = BMatrix::new(Backend::Rayon);
+        b_matrix.set_cycle_detection(8);
+        b_matrix.set_seed(seed);
+        // chunks of RUN_TO_CHUNK still have to see every generation
+        b_matrix.run_to_generation(100);
+
+        let snapshot = wait_for_generation(&mut b_matrix, 100);
+        assert_eq!(
+            snapshot.cycle,
+            Some(Cycle {
+                period: 2,
+                start_generation: 9
+            })
+        );
+    }
+
+    #[test]
+    fn test_BMatrix_tracks_spaceships() {
+        let seed = PatternBuilder::with_size(64, 64).make_glider(50, 50).build();
+        let mut b_matrix = BMatrix::new(Backend::Single);
+        b_matrix.set_spaceship_tracking(true);
+        b_matrix.set_seed(seed);
+        b_matrix.run_to_generation(20);
+
+        let snapshot = wait_for_generation(&mut b_matrix, 20);
+        assert_eq!(snapshot.spaceships.len(), 1);
+        assert_eq!(snapshot.spaceships[0].code, "xq4_153");
+        assert_eq!(snapshot.spaceships[0].bounding_box.x, 45);
+        assert_eq!(snapshot.spaceship_events.len(), 1);
+    }
+
+    #[test]
+    fn test_BMatrix_centroid_follows_glider() {
+        let seed = PatternBuilder::with_size(64, 64).make_glider(50, 50).build();
+        let mut b_matrix = BMatrix::new(Backend::Single);
+        b_matrix.set_centroid_tracking(true);
+        b_matrix.set_seed(seed);
+        b_matrix.run_to_generation(40);
+
+        let snapshot = wait_for_generation(&mut b_matrix, 40);
+        // back in the same phase 10 cells up and to the left
+        let (x, y) = snapshot.centroid.unwrap();
+        let (start_x, start_y) = PatternBuilder::with_size(64, 64)
+            .make_glider(50, 50)
+            .build()
+            .centroid()
+            .unwrap();
+        assert_eq!((x, y), (start_x - 10.0, start_y - 10.0));
+    }
+
+    #[test]
+    fn test_BMatrix_tracks_ages_and_activity() {
+        let seed = PatternBuilder::with_size(32, 32)
+            .make_blinker(5, 4)
+            .make_square(20, 20)
+            .build();
+        let mut b_matrix = BMatrix::new(Backend::Single);
+        b_matrix.set_run_ahead(0);
+        b_matrix.set_age_tracking(true);
+        b_matrix.set_activity_tracking(4);
+        b_matrix.set_seed(seed);
+        b_matrix.run_to_generation(10);
+
+        let snapshot = wait_for_generation(&mut b_matrix, 10);
+        let ages = snapshot.ages.as_ref().unwrap();
+        assert_eq!(ages.since_birth(20, 20), Some(10));
+        assert_eq!(ages.since_birth(5, 5), Some(10));
+        assert_eq!(ages.since_birth(5, 4), Some(0));
+        let activity = snapshot.activity.as_ref().unwrap();
+        assert_eq!(activity.changes(5, 4), 4);
+        assert_eq!(activity.changes(20, 20), 0);
+
+        // an edit starts them over for the cells it changed, and only those
+        b_matrix.queue_edit(Edit::Clear(BoundingBox::spanning((20, 20), (21, 21))));
+        let snapshot = wait_until(&mut b_matrix, "edit", |snapshot| snapshot.vec.population() == 3);
+        let ages = snapshot.ages.as_ref().unwrap();
+        assert_eq!(ages.since_birth(20, 20), None);
+        assert_eq!(ages.since_alive(20, 20), None);
+        assert_eq!(ages.since_birth(5, 5), Some(10));
+        assert_eq!(snapshot.activity.as_ref().unwrap().changes(5, 4), 4);
+
+        b_matrix.set_age_tracking(false);
+        b_matrix.set_activity_tracking(0);
+        b_matrix.run_to_generation(11);
+        let snapshot = wait_for_generation(&mut b_matrix, 11);
+        assert!(snapshot.ages.is_none() && snapshot.activity.is_none());
+    }
+
+    #[test]
+    fn test_BMatrix_remembers_the_generation_before() {
+        // vertical blinker down (5,4) (5,5) (5,6)
+        let seed = PatternBuilder::with_size(16, 16).make_blinker(5, 4).build();
+        let mut b_matrix = BMatrix::new(Backend::Single);
+        b_matrix.set_run_ahead(0);
+        b_matrix.set_alive_before_tracking(true);
+        b_matrix.set_seed(seed);
+        // nothing came before the seed
+        assert!(wait_for_generation(&mut b_matrix, 0).alive_before.is_none());
+
+        // several generations in one go, so it only sees 10 on the way to 11
+        b_matrix.run_to_generation(11);
+        let snapshot = wait_for_generation(&mut b_matrix, 11);
+        let before = snapshot.alive_before.as_ref().unwrap();
+        assert!(before.was_alive(5, 4) && before.was_alive(5, 5));
+        assert!(!before.was_alive(4, 5));
+
+        b_matrix.set_alive_before_tracking(false);
+        b_matrix.run_to_generation(12);
+        assert!(wait_for_generation(&mut b_matrix, 12).alive_before.is_none());
+    }
+
+    #[test]
+    fn test_BMatrix_reseeding_with_the_same_cells_turned_the_other_way() {
+        let mut b_matrix = BMatrix::new(Backend::Single);
+        b_matrix.set_run_ahead(0);
+        for &(width, height) in [(40, 20), (20, 40)].iter() {
+            let seed = PatternBuilder::with_size(width, height).make_r_pentomino(8, 8).build();
+            let mut expected = seed.clone();
+            let mut scratch = seed.clone();
+            create_engine(Backend::Single).step_n(10, &seed, &mut expected, &mut scratch);
+            b_matrix.set_seed(seed);
+            b_matrix.run_to_generation(10);
+            let snapshot = wait_until(&mut b_matrix, "generation 10", |snapshot| {
+                snapshot.vec.height() == height && snapshot.generation >= 10
+            });
+            assert_eq!((snapshot.vec.width(), snapshot.vec.height()), (width, height));
+            assert!(snapshot.vec == expected, "{}x{}", width, height);
+        }
+    }
+
+    #[test]
+    fn test_BMatrix_run_to_generation() {
+        let seed = PatternBuilder::with_size(48, 48).make_r_pentomino(20, 20).build();
+        let mut expected = seed.clone();
+        let mut scratch = seed.clone();
+        create_engine(Backend::Single).step_n(250, &seed, &mut expected, &mut scratch);
+
+        let mut b_matrix = BMatrix::new(Backend::Rayon);
+        b_matrix.set_seed(seed);
+        b_matrix.run_to_generation(250);
+
+        let snapshot = wait_for_generation(&mut b_matrix, 250);
+        assert_eq!(snapshot.generation, 250);
+        assert!(snapshot.vec == expected);
+    }
+}
@@ -0,0 +1,169 @@
+// Which cells around a cell count as its neighbors. Radius 1 for the rules
+// that only look next door, any range for Larger than Life
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Neighborhood {
+    Moore,
+    VonNeumann,
+    /// Hexagonal cells on the square grid, the way Golly does it: every row
+    /// is shifted half a cell left of the one above, which leaves the
+    /// north east and south west corners out of the Moore neighborhood
+    Hexagonal,
+}
+
+impl Neighborhood {
+    /// (di,dj) of every neighbor, clockwise from north in the order the
+    /// transitions list them
+    pub fn offsets(self) -> &'static [(i32, i32)] {
+        match self {
+            Neighborhood::Moore => &[(0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1)],
+            Neighborhood::VonNeumann => &[(0, -1), (1, 0), (0, 1), (-1, 0)],
+            Neighborhood::Hexagonal => &[(0, -1), (1, 0), (1, 1), (0, 1), (-1, 0), (-1, -1)],
+        }
+    }
+
+    /// Live neighbors of (i, j) next door. Like life::get_count, which is the
+    /// quicker way to get Moore, only fully alive cells count
+    #[inline]
+    pub fn count(self, i: i32, j: i32, vec: &BMatrixVector) -> u32 {
+        self.offsets()
+            .iter()
+            .map(|&(di, dj)| vec.is_alive(i + di, j + dj) as u32)
+            .sum()
+    }
+
+    /// The di from and to that are within range of a cell dj rows away,
+    /// None if that row is out of range altogether
+    #[inline]
+    pub fn row_span(self, range: i32, dj: i32) -> Option<(i32, i32)> {
+        if dj.abs() > range {
+            return None;
+        }
+        match self {
+            Neighborhood::Moore => Some((-range, range)),
+            Neighborhood::VonNeumann => Some((dj.abs() - range, range - dj.abs())),
+            // hexagonal distance is the largest of |di|, |dj| and |di - dj|
+            Neighborhood::Hexagonal => Some(((dj - range).max(-range), (dj + range).min(range))),
+        }
+    }
+
+    /// Cells within range, the one in the middle included
+    pub fn cells_within(self, range: i32) -> u32 {
+        (-range..=range)
+            .filter_map(|dj| self.row_span(range, dj))
+            .map(|(from, to)| (to - from + 1) as u32)
+            .sum()
+    }
+
+    /// The letter Golly puts after a rule for it, none for Moore
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Neighborhood::Moore => "",
+            Neighborhood::VonNeumann => "V",
+            Neighborhood::Hexagonal => "H",
+        }
+    }
+}
+
+/// Running totals of the live cells over a band of rows, so the live cells in
+/// any rectangle come out of 4 lookups however big it is
+pub struct AliveSums {
+    top: i32,
+    rows: i32,
+    width: i32,
+    // live cells above and left of each corner, (width + 1) to a row
+    sums: Vec<u32>,
+}
+
+impl AliveSums {
+    /// Totals for the rows from top to bottom, both clamped to the grid
+    pub fn new(vec: &BMatrixVector, top: i32, bottom: i32) -> Self {
+        let top = top.max(0);
+        let rows = (bottom.min(vec.height() - 1) - top + 1).max(0);
+        let width = vec.width();
+        let stride = (width + 1) as usize;
+        let mut sums = vec![0; stride * (rows + 1) as usize];
+        for row in 0..rows {
+            let mut along = 0;
+            for i in 0..width {
+                along += vec.is_alive(i, top + row) as u32;
+                let corner = (row + 1) as usize * stride + (i + 1) as usize;
+                sums[corner] = sums[corner - stride] + along;
+            }
+        }
+        AliveSums { top, rows, width, sums }
+    }
+
+    /// Live cells from (left, top) to (right, bottom), inclusive. Anything
+    /// off the grid or outside the band is dead
+    #[inline]
+    pub fn count(&self, left: i32, top: i32, right: i32, bottom: i32) -> u32 {
+        let (left, right) = (left.max(0), right.min(self.width - 1));
+        let (top, bottom) = ((top - self.top).max(0), (bottom - self.top).min(self.rows - 1));
+        if left > right || top > bottom {
+            return 0;
+        }
+        let stride = (self.width + 1) as usize;
+        let at = |i: i32, j: i32| self.sums[j as usize * stride + i as usize];
+        at(right + 1, bottom + 1) + at(left, top) - at(left, bottom + 1) - at(right + 1, top)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::PatternBuilder;
+
+    #[test]
+    fn test_Neighborhood_count() {
+        let full = PatternBuilder::with_size(3, 3).make_random_seeded((0, 0), 3, 3, 0, 1.0).build();
+        assert_eq!(Neighborhood::Moore.count(1, 1, &full), 8);
+        assert_eq!(Neighborhood::VonNeumann.count(1, 1, &full), 4);
+        assert_eq!(Neighborhood::Hexagonal.count(1, 1, &full), 6);
+        // the corners hexagonal leaves out
+        let mut corners = BMatrixVector::new(3, 3);
+        *corners.at_mut(2, 0).unwrap() = ALIVE;
+        *corners.at_mut(0, 2).unwrap() = ALIVE;
+        assert_eq!(Neighborhood::Hexagonal.count(1, 1, &corners), 0);
+        assert_eq!(Neighborhood::Moore.count(1, 1, &corners), life::get_count(1, 1, &corners));
+    }
+
+    #[test]
+    fn test_Neighborhood_row_span_matches_offsets() {
+        for &neighborhood in [Neighborhood::Moore, Neighborhood::VonNeumann, Neighborhood::Hexagonal].iter() {
+            let mut within = Vec::new();
+            for dj in -1..=1 {
+                if let Some((from, to)) = neighborhood.row_span(1, dj) {
+                    within.extend((from..=to).map(|di| (di, dj)).filter(|&offset| offset != (0, 0)));
+                }
+            }
+            let mut offsets = neighborhood.offsets().to_vec();
+            within.sort();
+            offsets.sort();
+            assert_eq!(within, offsets, "{:?}", neighborhood);
+        }
+        assert_eq!(Neighborhood::Moore.cells_within(5), 121);
+        assert_eq!(Neighborhood::VonNeumann.cells_within(5), 61);
+        assert_eq!(Neighborhood::Hexagonal.cells_within(5), 91);
+    }
+
+    #[test]
+    fn test_AliveSums_count() {
+        let vec = PatternBuilder::with_size(13, 11).make_random_seeded((0, 0), 13, 11, 4, 0.5).build();
+        let by_hand = |left: i32, top: i32, right: i32, bottom: i32| -> u32 {
+            (top..=bottom)
+                .flat_map(|j| (left..=right).map(move |i| (i, j)))
+                .map(|(i, j)| vec.is_alive(i, j) as u32)
+                .sum()
+        };
+        let sums = AliveSums::new(&vec, 0, 10);
+        let rectangles = [(0, 0, 12, 10), (3, 2, 7, 9), (-4, -4, 2, 3), (10, 8, 20, 20), (5, 5, 4, 5)];
+        for &(left, top, right, bottom) in rectangles.iter() {
+            assert_eq!(sums.count(left, top, right, bottom), by_hand(left, top, right, bottom));
+        }
+        // rows outside the band are left out
+        let band = AliveSums::new(&vec, 3, 5);
+        assert_eq!(band.count(0, 0, 12, 10), by_hand(0, 3, 12, 5));
+    }
+}
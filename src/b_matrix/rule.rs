@@ -0,0 +1,396 @@
+// What a cell turns into next generation. Engines only decide which thread
+// works on which cells and leave the rest up to a Rule
+use super::*;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Most states a rule can have, since a cell is a byte
+pub const MAX_STATES: usize = CellState::MAX as usize + 1;
+
+pub trait Rule: fmt::Display + Send + Sync {
+    /// How many states a cell can be in, dead and alive included
+    fn states(&self) -> usize;
+
+    /// 2 for rules on the plane. 1D rules only evolve the bottom row and move
+    /// the rest up a row a generation, so the grid holds a spacetime diagram
+    fn dimensions(&self) -> usize {
+        2
+    }
+
+    fn next_state(&self, i: i32, j: i32, vec: &BMatrixVector) -> CellState;
+
+    /// next_state for a run of cells, out being the cells of vec from index start on.
+    /// Engines call this rather than next_state so the call per cell gets inlined
+    fn next_cells(&self, start: usize, vec: &BMatrixVector, out: &mut [CellState]) {
+        let (mut i, mut j) = get_location_from_idx(start, vec.width());
+        for cell in out.iter_mut() {
+            *cell = self.next_state(i, j, vec);
+            i += 1;
+            if i == vec.width() {
+                i = 0;
+                j += 1;
+            }
+        }
+    }
+
+    /// (r,g,b) to draw each state in, dead first
+    fn colors(&self) -> Vec<(u8, u8, u8)> {
+        default_colors(self.states())
+    }
+}
+
+/// Black for dead, white for alive, and anything in between fading from
+/// orange down to a dark red
+pub fn default_colors(states: usize) -> Vec<(u8, u8, u8)> {
+    let mut colors = vec![(0, 0, 0), (255, 255, 255)];
+    let dying = states.saturating_sub(2);
+    for state in 0..dying {
+        let fade = if dying == 1 { 0.0 } else { state as f32 / (dying - 1) as f32 };
+        let mix = |from: f32, to: f32| (from + (to - from) * fade).round() as u8;
+        colors.push((mix(255.0, 96.0), mix(160.0, 0.0), 0));
+    }
+    colors.truncate(states);
+    colors
+}
+
+/// Reads any rulestring one of the rules knows, e.g. "B3/S23", "B2/S/C3", "B2/S34H",
+/// "B2-a/S12", "R5,C0,M1,S34..58,B34..45,NM" or the 1D "W30", the name of a bundled rule table
+/// like "WireWorld", or the path to a .rule file
+pub fn parse_rule(rulestring: &str) -> LifeResult<Arc<dyn Rule>> {
+    if let Some(table) = bundled_table(rulestring) {
+        return Ok(Arc::new(table?));
+    }
+    let trimmed = rulestring.trim();
+    if trimmed.ends_with(".rule") {
+        return Ok(Arc::new(RuleTable::load(Path::new(trimmed))?));
+    }
+    if trimmed.starts_with(&['R', 'r'][..]) && trimmed[1..].starts_with(|c: char| c.is_ascii_digit()) {
+        return Ok(Arc::new(trimmed.parse::<LargerThanLife>()?));
+    }
+    if trimmed.starts_with(&['W', 'w'][..]) && trimmed[1..].starts_with(|c: char| c.is_ascii_digit()) {
+        return Ok(Arc::new(trimmed.parse::<Elementary>()?));
+    }
+    // outer totalistic rules are quicker to run as Generations, so anything
+    // that could be one gets that rule's error rather than Isotropic's
+    if is_isotropic_form(trimmed) {
+        Ok(Arc::new(rulestring.parse::<Isotropic>()?))
+    } else {
+        Ok(Arc::new(rulestring.parse::<Generations>()?))
+    }
+}
+
+// B2-a/S12 has letters or a - among the neighbor counts, which Generations
+// rules never do past the B, S, C or G in front and the H or V on the end
+fn is_isotropic_form(rulestring: &str) -> bool {
+    rulestring
+        .trim_end_matches(&['H', 'h', 'V', 'v'][..])
+        .split('/')
+        .any(|part| part.chars().skip(1).any(|c| c.is_ascii_alphabetic() || c == '-'))
+}
+
+pub mod life {
+    use super::*;
+
+    // since we are using this to survey around, x and y can now be negative
+    // but "is_alive" method covers this
+    // NOTE: only fully alive cells count, dying ones are as good as dead
+    #[inline]
+    pub fn get_count(i: i32, j: i32, b_matrix_vector: &BMatrixVector) -> u32 {
+        let mut total = 0;
+        total += b_matrix_vector.is_alive(i + 1, j) as u32;
+        total += b_matrix_vector.is_alive(i + 1, j + 1) as u32;
+        total += b_matrix_vector.is_alive(i, j + 1) as u32;
+        total += b_matrix_vector.is_alive(i - 1, j + 1) as u32;
+        total += b_matrix_vector.is_alive(i - 1, j) as u32;
+        total += b_matrix_vector.is_alive(i - 1, j - 1) as u32;
+        total += b_matrix_vector.is_alive(i, j - 1) as u32;
+        total + b_matrix_vector.is_alive(i + 1, j - 1) as u32
+    }
+}
+
+/// Outer totalistic rules, with cells that take `states - 2` generations to
+/// die once they stop surviving. Conway's Life and every other Life-like rule
+/// are just the ones with 2 states on the Moore neighborhood
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Generations {
+    // indexed by the number of live neighbors
+    birth: [bool; 9],
+    survival: [bool; 9],
+    states: usize,
+    neighborhood: Neighborhood,
+}
+
+impl Generations {
+    pub fn life() -> Self {
+        Generations::new(&[3], &[2, 3], 2)
+    }
+    pub fn new(birth: &[u32], survival: &[u32], states: usize) -> Self {
+        assert!(
+            (2..=MAX_STATES).contains(&states),
+            "Generations rules need 2 to {} states, got {}",
+            MAX_STATES,
+            states
+        );
+        let mut rule = Generations {
+            birth: [false; 9],
+            survival: [false; 9],
+            states,
+            neighborhood: Neighborhood::Moore,
+        };
+        for &count in birth.iter() {
+            rule.birth[count as usize] = true;
+        }
+        for &count in survival.iter() {
+            rule.survival[count as usize] = true;
+        }
+        rule
+    }
+    /// Same rule on von Neumann or hexagonal neighbors
+    pub fn with_neighborhood(mut self, neighborhood: Neighborhood) -> Self {
+        let neighbors = neighborhood.offsets().len();
+        assert!(
+            self.birth[neighbors + 1..].iter().chain(self.survival[neighbors + 1..].iter()).all(|&on| !on),
+            "{:?} cells only have {} neighbors",
+            neighborhood,
+            neighbors
+        );
+        self.neighborhood = neighborhood;
+        self
+    }
+    #[inline]
+    fn count(&self, i: i32, j: i32, vec: &BMatrixVector) -> usize {
+        match self.neighborhood {
+            Neighborhood::Moore => life::get_count(i, j, vec) as usize,
+            neighborhood => neighborhood.count(i, j, vec) as usize,
+        }
+    }
+}
+
+impl Default for Generations {
+    fn default() -> Self {
+        Generations::life()
+    }
+}
+
+impl Rule for Generations {
+    fn states(&self) -> usize {
+        self.states
+    }
+    #[inline]
+    fn next_state(&self, i: i32, j: i32, vec: &BMatrixVector) -> CellState {
+        match vec.state(i, j) {
+            DEAD => {
+                if self.birth[self.count(i, j, vec)] {
+                    ALIVE
+                } else {
+                    DEAD
+                }
+            }
+            ALIVE => {
+                if self.survival[self.count(i, j, vec)] {
+                    ALIVE
+                } else if self.states > 2 {
+                    ALIVE + 1
+                } else {
+                    DEAD
+                }
+            }
+            // dying cells just keep on dying, whatever is around them
+            dying if (dying as usize) + 1 < self.states => dying + 1,
+            _ => DEAD,
+        }
+    }
+}
+
+fn counts(counts: &[bool; 9]) -> String {
+    (0..9)
+        .filter(|&count| counts[count])
+        .map(|count| count.to_string())
+        .collect()
+}
+
+// B3/S23 for Life-like rules, B2/S/C3 for the rest, like Golly writes them,
+// and an H or V on the end for the other neighborhoods
+impl fmt::Display for Generations {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B{}/S{}", counts(&self.birth), counts(&self.survival))?;
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        write!(f, "{}", self.neighborhood.suffix())
+    }
+}
+
+pub(crate) fn invalid_rule(rulestring: &str, why: &str) -> LifeError {
+    LifeError::InvalidConfig(format!("{} is not a rule this knows, {}", rulestring, why))
+}
+
+/// Takes B3/S23, B2/S/C3 (or G3) with the parts in any order, as well as the
+/// older S/B and S/B/C forms like 23/3 and 345/2/4. An H or V at the very end
+/// makes it hexagonal or von Neumann, e.g. B2/S34H
+impl FromStr for Generations {
+    type Err = LifeError;
+    fn from_str(rulestring: &str) -> LifeResult<Self> {
+        let trimmed = rulestring.trim();
+        let neighborhood = [Neighborhood::VonNeumann, Neighborhood::Hexagonal]
+            .iter()
+            .cloned()
+            .find(|neighborhood| trimmed.to_ascii_uppercase().ends_with(neighborhood.suffix()))
+            .unwrap_or(Neighborhood::Moore);
+        let neighbors = neighborhood.offsets().len() as u32;
+        let parts: Vec<String> = trimmed[..trimmed.len() - neighborhood.suffix().len()]
+            .split('/')
+            .map(|part| part.trim().to_ascii_uppercase())
+            .collect();
+        let digits = |part: &str| -> LifeResult<Vec<u32>> {
+            part.chars()
+                .map(|c| match c.to_digit(10) {
+                    Some(count) if count <= neighbors => Ok(count),
+                    _ => Err(invalid_rule(rulestring, &format!("'{}' is not a neighbor count", c))),
+                })
+                .collect()
+        };
+        let (mut birth, mut survival, mut states) = (None, None, None);
+        let prefixed = parts
+            .iter()
+            .all(|part| part.starts_with(|c| "BSCG".contains(c)));
+        if prefixed {
+            for part in parts.iter() {
+                let (letter, rest) = part.split_at(1);
+                match letter {
+                    "B" if birth.is_none() => birth = Some(digits(rest)?),
+                    "S" if survival.is_none() => survival = Some(digits(rest)?),
+                    "C" | "G" if states.is_none() => states = Some(rest.to_string()),
+                    _ => return Err(invalid_rule(rulestring, &format!("{} comes up twice", letter))),
+                }
+            }
+        } else {
+            match parts.as_slice() {
+                [s, b] => {
+                    survival = Some(digits(s)?);
+                    birth = Some(digits(b)?);
+                }
+                [s, b, c] => {
+                    survival = Some(digits(s)?);
+                    birth = Some(digits(b)?);
+                    states = Some(c.clone());
+                }
+                _ => return Err(invalid_rule(rulestring, "expected something like B3/S23")),
+            }
+        }
+        let states = match states {
+            None => 2,
+            Some(states) => match states.parse::<usize>() {
+                Ok(states) if (2..=MAX_STATES).contains(&states) => states,
+                _ => {
+                    return Err(invalid_rule(
+                        rulestring,
+                        &format!("the number of states has to be 2 to {}", MAX_STATES),
+                    ))
+                }
+            },
+        };
+        match (birth, survival) {
+            (Some(birth), Some(survival)) => {
+                Ok(Generations::new(&birth, &survival, states).with_neighborhood(neighborhood))
+            }
+            _ => Err(invalid_rule(rulestring, "it needs both B and S")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::PatternBuilder;
+
+    fn rule(rulestring: &str) -> Generations {
+        rulestring.parse().unwrap()
+    }
+
+    fn step(rule: &Generations, vec: &BMatrixVector) -> BMatrixVector {
+        let mut next = vec.clone();
+        rule.next_cells(0, vec, &mut next);
+        next
+    }
+
+    #[test]
+    fn test_Generations_parse_and_display() {
+        assert_eq!(rule("B3/S23"), Generations::life());
+        assert_eq!(rule("23/3"), Generations::life());
+        assert_eq!(rule("s23/b3"), Generations::life());
+        assert_eq!(rule("B3/S23/C2"), Generations::life());
+        // Brian's Brain and Star Wars
+        assert_eq!(rule("B2/S/C3"), Generations::new(&[2], &[], 3));
+        assert_eq!(rule("/2/3"), Generations::new(&[2], &[], 3));
+        assert_eq!(rule("345/2/4"), Generations::new(&[2], &[3, 4, 5], 4));
+        assert_eq!(rule("B2/S345/G4").to_string(), "B2/S345/C4");
+        assert_eq!(Generations::life().to_string(), "B3/S23");
+        // hexagonal and von Neumann
+        assert_eq!(rule("B2/S34H"), Generations::new(&[2], &[3, 4], 2).with_neighborhood(Neighborhood::Hexagonal));
+        assert_eq!(rule("b2/s34h").to_string(), "B2/S34H");
+        assert_eq!(rule("S012/B1/C4v").to_string(), "B1/S012/C4V");
+        let bad_rules = ["B9/S23", "B3/S23/C1", "B3/S23/C257", "B3", "B3/B3/S23", "3/2/1/0", "Life", "B7/S23H", "B3/S5V"];
+        for bad in bad_rules.iter() {
+            assert!(bad.parse::<Generations>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_parse_rule_picks_generations_or_isotropic() {
+        assert_eq!(parse_rule("B3/S23").unwrap().to_string(), "B3/S23");
+        assert_eq!(parse_rule("345/2/4").unwrap().to_string(), "B2/S345/C4");
+        assert_eq!(parse_rule("B2-a/S12").unwrap().to_string(), "B2-a/S12");
+        assert_eq!(parse_rule("B2ea/S").unwrap().to_string(), "B2ea/S");
+        // a broken Generations rule says what is wrong with it as one
+        match parse_rule("B3/S23/C1") {
+            Err(err) => assert!(!err.to_string().contains("B2-a/S12"), "{}", err),
+            Ok(rule) => panic!("B3/S23/C1 parsed as {}", rule),
+        }
+    }
+
+    #[test]
+    fn test_Generations_cells_decay_through_dying_states() {
+        // Brian's Brain: a domino is born from the pair next to it, which
+        // then dies through state 2
+        let brain = rule("B2/S/C3");
+        let mut vec = BMatrixVector::new(5, 4);
+        *vec.at_mut(1, 1).unwrap() = ALIVE;
+        *vec.at_mut(1, 2).unwrap() = ALIVE;
+        let next = step(&brain, &vec);
+        assert_eq!((next.state(1, 1), next.state(1, 2)), (2, 2));
+        assert!(next.is_alive(0, 1) && next.is_alive(2, 1) && next.is_alive(0, 2) && next.is_alive(2, 2));
+        let after = step(&brain, &next);
+        assert_eq!((after.state(1, 1), after.state(1, 2)), (DEAD, DEAD));
+
+        // dying cells do not count as neighbors, so one live and one dying
+        // neighbor is not enough for a birth
+        let mut vec = BMatrixVector::new(3, 1);
+        *vec.at_mut(0, 0).unwrap() = ALIVE;
+        *vec.at_mut(2, 0).unwrap() = 2;
+        assert_eq!(step(&brain, &vec).state(1, 0), DEAD);
+        *vec.at_mut(2, 0).unwrap() = ALIVE;
+        assert_eq!(step(&brain, &vec).state(1, 0), ALIVE);
+    }
+
+    #[test]
+    fn test_Generations_life_never_leaves_dead_and_alive() {
+        let life = Generations::life();
+        let vertical = PatternBuilder::with_size(5, 5).make_blinker(2, 1).build();
+        let horizontal = step(&life, &vertical);
+        assert!(horizontal.is_alive(1, 2) && horizontal.is_alive(2, 2) && horizontal.is_alive(3, 2));
+        assert_eq!(horizontal.population(), 3);
+        assert!(step(&life, &horizontal) == vertical);
+        assert!(horizontal.iter().all(|&cell| cell == DEAD || cell == ALIVE));
+    }
+
+    #[test]
+    fn test_default_colors() {
+        assert_eq!(default_colors(2), vec![(0, 0, 0), (255, 255, 255)]);
+        let colors = default_colors(5);
+        assert_eq!(colors.len(), 5);
+        assert_eq!((colors[2], colors[4]), ((255, 160, 0), (96, 0, 0)));
+        assert_eq!(default_colors(3)[2], (255, 160, 0));
+    }
+}
@@ -0,0 +1,733 @@
+// Golly's rule tables, the @TABLE and @COLORS parts of a .rule file (see
+// https://golly.sourceforge.io/Help/formats.html#rule). Anything that is not
+// outer totalistic, WireWorld say, is written as one of these
+use super::*;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+// (name, .rule file) for the tables parse_rule knows by name
+const BUNDLED_TABLES: &[(&str, &str)] = &[(
+    "WireWorld",
+    include_str!("../../resources/rules/WireWorld.rule"),
+)];
+
+// rules with at most this many different neighborhoods get a lookup table
+// entry for every one of them. 4 states on the Moore neighborhood is 4^9
+const DENSE_LIMIT: usize = 1 << 22;
+// center plus the most neighbors a neighborhood has
+const MAX_POSITIONS: usize = 9;
+
+/// The table by that name out of the ones that come with the app, if there is one
+pub fn bundled_table(name: &str) -> Option<LifeResult<RuleTable>> {
+    BUNDLED_TABLES
+        .iter()
+        .find(|&&(bundled_name, _)| bundled_name.eq_ignore_ascii_case(name.trim()))
+        .map(|&(_, text)| RuleTable::parse(text))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableSymmetry {
+    None,
+    Rotate4,
+    Rotate8,
+    ReflectHorizontal,
+    Rotate4Reflect,
+    Rotate8Reflect,
+    Permute,
+}
+
+impl TableSymmetry {
+    /// Every order the neighbors can be read in. None for permute, which is
+    /// all of them and gets handled on its own
+    fn permutations(self, neighbors: usize) -> Option<Vec<Vec<usize>>> {
+        use TableSymmetry::*;
+        // a quarter turn is 2 neighbors on Moore, 1 on von Neumann
+        let quarter = neighbors / 4;
+        let rotate = |by: usize| -> Vec<usize> { (0..neighbors).map(|k| (k + by) % neighbors).collect() };
+        // left to right, north stays put
+        let mirror = |order: &Vec<usize>| -> Vec<usize> {
+            (0..neighbors).map(|k| order[(neighbors - k) % neighbors]).collect()
+        };
+        let rotations = |step: usize| -> Vec<Vec<usize>> { (0..neighbors).step_by(step).map(rotate).collect() };
+        let with_mirrors = |orders: Vec<Vec<usize>>| -> Vec<Vec<usize>> {
+            let mirrored: Vec<Vec<usize>> = orders.iter().map(mirror).collect();
+            orders.into_iter().chain(mirrored).collect()
+        };
+        match self {
+            None => Some(vec![rotate(0)]),
+            Rotate4 => Some(rotations(quarter)),
+            Rotate8 => Some(rotations(1)),
+            ReflectHorizontal => Some(with_mirrors(vec![rotate(0)])),
+            Rotate4Reflect => Some(with_mirrors(rotations(quarter))),
+            Rotate8Reflect => Some(with_mirrors(rotations(1))),
+            Permute => Option::None,
+        }
+    }
+}
+
+// which of the (up to 256) states an entry of a transition matches
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct StateSet([u64; 4]);
+
+impl StateSet {
+    fn single(state: CellState) -> Self {
+        let mut set = StateSet::default();
+        set.insert(state);
+        set
+    }
+    fn insert(&mut self, state: CellState) {
+        self.0[state as usize / 64] |= 1 << (state % 64);
+    }
+    fn union(&mut self, other: StateSet) {
+        for (word, other) in self.0.iter_mut().zip(other.0.iter()) {
+            *word |= other;
+        }
+    }
+    fn contains(&self, state: CellState) -> bool {
+        self.0[state as usize / 64] & (1 << (state % 64)) != 0
+    }
+    fn states(self) -> impl Iterator<Item = CellState> {
+        (0..=CellState::MAX).filter(move |&state| self.contains(state))
+    }
+}
+
+// center first, then the neighbors in the order of Neighborhood::offsets
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Transition {
+    inputs: Vec<StateSet>,
+    output: CellState,
+}
+
+// For each position and state, a bit for every transition that allows it
+// there. A neighborhood matches the transitions whose bit is set for all of
+// its positions, and the first of those wins, same as in Golly
+#[derive(Clone, Debug)]
+struct Matches {
+    states: usize,
+    // 64 transitions a word
+    words: usize,
+    bits: Vec<u64>,
+    outputs: Vec<CellState>,
+}
+
+impl Matches {
+    fn new(transitions: &[Transition], states: usize, positions: usize) -> Self {
+        let words = transitions.len().div_ceil(64);
+        let mut bits = vec![0; positions * states * words];
+        for (index, transition) in transitions.iter().enumerate() {
+            for (position, set) in transition.inputs.iter().enumerate() {
+                for state in set.states() {
+                    bits[(position * states + state as usize) * words + index / 64] |= 1 << (index % 64);
+                }
+            }
+        }
+        Matches {
+            states,
+            words,
+            bits,
+            outputs: transitions.iter().map(|transition| transition.output).collect(),
+        }
+    }
+    // None if no transition matches, which leaves the cell as it is
+    fn next_state(&self, neighborhood: &[CellState]) -> Option<CellState> {
+        for word in 0..self.words {
+            let mut matching = !0u64;
+            for (position, &state) in neighborhood.iter().enumerate() {
+                matching &= self.bits[(position * self.states + state as usize) * self.words + word];
+                if matching == 0 {
+                    break;
+                }
+            }
+            if matching != 0 {
+                return Some(self.outputs[word * 64 + matching.trailing_zeros() as usize]);
+            }
+        }
+        None
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Lookup {
+    // next state for every neighborhood there is, see RuleTable::next_state
+    Dense(Vec<CellState>),
+    Sparse(Matches),
+}
+
+/// A rule given as a list of transitions, each saying what a cell in some
+/// state turns into given what its neighbors are
+#[derive(Clone, Debug)]
+pub struct RuleTable {
+    name: String,
+    states: usize,
+    neighborhood: Neighborhood,
+    colors: Vec<(u8, u8, u8)>,
+    lookup: Lookup,
+}
+
+fn table_error(line: usize, message: String) -> LifeError {
+    LifeError::Parse { line, message }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Rule,
+    Table,
+    Colors,
+    // @NAMES, @ICONS and so on, which the viewer has no use for
+    Other,
+}
+
+// one entry of a transition, before variables get bound to a state
+#[derive(Clone, Debug, PartialEq)]
+enum Entry {
+    Set(StateSet),
+    Var(String),
+}
+
+// everything from the @TABLE section needed to build the lookup
+struct TableParser {
+    states: Option<usize>,
+    neighborhood: Neighborhood,
+    symmetry: TableSymmetry,
+    variables: Vec<(String, StateSet)>,
+    transitions: Vec<Transition>,
+    // to leave out the ones symmetries make twice
+    seen: HashSet<Transition>,
+}
+
+impl TableParser {
+    fn states(&self, line: usize) -> LifeResult<usize> {
+        self.states
+            .ok_or_else(|| table_error(line, "n_states has to come before this".to_string()))
+    }
+    fn state(&self, text: &str, line: usize) -> LifeResult<CellState> {
+        match text.parse::<usize>() {
+            Ok(state) if state < self.states(line)? => Ok(state as CellState),
+            _ => Err(table_error(line, format!("{} is not a state of this rule", text))),
+        }
+    }
+    fn variable(&self, name: &str) -> Option<StateSet> {
+        self.variables
+            .iter()
+            .find(|(variable, _)| variable == name)
+            .map(|&(_, set)| set)
+    }
+    // "{0,1,a}", with variables standing for all their states
+    fn set(&self, text: &str, line: usize) -> LifeResult<StateSet> {
+        let inner = text
+            .strip_prefix('{')
+            .and_then(|text| text.strip_suffix('}'))
+            .ok_or_else(|| table_error(line, format!("expected {{...}}, got {}", text)))?;
+        let mut set = StateSet::default();
+        for item in inner.split(',').map(str::trim) {
+            match self.variable(item) {
+                Some(variable) => set.union(variable),
+                None => set.insert(self.state(item, line)?),
+            }
+        }
+        Ok(set)
+    }
+    fn entry(&self, text: &str, line: usize) -> LifeResult<Entry> {
+        if text.starts_with('{') {
+            Ok(Entry::Set(self.set(text, line)?))
+        } else if self.variable(text).is_some() {
+            Ok(Entry::Var(text.to_string()))
+        } else {
+            Ok(Entry::Set(StateSet::single(self.state(text, line)?)))
+        }
+    }
+    fn setting(&mut self, key: &str, value: &str, line: usize) -> LifeResult<()> {
+        if !self.transitions.is_empty() {
+            return Err(table_error(line, format!("{} has to come before the transitions", key)));
+        }
+        match key {
+            "n_states" | "num_states" => match value.parse::<usize>() {
+                Ok(states) if (2..=MAX_STATES).contains(&states) => self.states = Some(states),
+                _ => {
+                    return Err(table_error(
+                        line,
+                        format!("n_states has to be 2 to {}, got {}", MAX_STATES, value),
+                    ))
+                }
+            },
+            "neighborhood" | "neighbourhood" => {
+                self.neighborhood = match value {
+                    "Moore" => Neighborhood::Moore,
+                    "vonNeumann" => Neighborhood::VonNeumann,
+                    _ => return Err(table_error(line, format!("unknown neighborhood {}", value))),
+                }
+            }
+            "symmetries" => {
+                self.symmetry = match value {
+                    "none" => TableSymmetry::None,
+                    "rotate4" => TableSymmetry::Rotate4,
+                    "rotate8" => TableSymmetry::Rotate8,
+                    "reflect_horizontal" => TableSymmetry::ReflectHorizontal,
+                    "rotate4reflect" => TableSymmetry::Rotate4Reflect,
+                    "rotate8reflect" => TableSymmetry::Rotate8Reflect,
+                    "permute" => TableSymmetry::Permute,
+                    _ => return Err(table_error(line, format!("unknown symmetries {}", value))),
+                }
+            }
+            _ => return Err(table_error(line, format!("unknown setting {}", key))),
+        }
+        Ok(())
+    }
+    fn line(&mut self, text: &str, line: usize) -> LifeResult<()> {
+        if let Some(var) = text.strip_prefix("var ") {
+            let mut parts = var.splitn(2, '=');
+            let name = parts.next().unwrap().trim();
+            let value = parts
+                .next()
+                .ok_or_else(|| table_error(line, "expected var name={...}".to_string()))?;
+            let set = self.set(value.trim(), line)?;
+            self.variables.retain(|(variable, _)| variable != name);
+            self.variables.push((name.to_string(), set));
+            return Ok(());
+        }
+        if let Some(split) = text.find(&[':', '='][..]) {
+            let (key, value) = text.split_at(split);
+            return self.setting(key.trim(), value[1..].trim(), line);
+        }
+        self.transition(text, line)
+    }
+    fn transition(&mut self, text: &str, line: usize) -> LifeResult<()> {
+        let states = self.states(line)?;
+        let positions = 1 + self.neighborhood.offsets().len();
+        // 0,1,{2,3},a,... or just 0123... when every state is one digit
+        let tokens: Vec<String> = if text.contains(',') || text.contains('{') {
+            let mut tokens = vec![String::new()];
+            let mut depth = 0;
+            for c in text.chars() {
+                match c {
+                    ',' if depth == 0 => tokens.push(String::new()),
+                    c if c.is_whitespace() => (),
+                    c => {
+                        depth += (c == '{') as i32 - (c == '}') as i32;
+                        tokens.last_mut().unwrap().push(c);
+                    }
+                }
+            }
+            tokens
+        } else if states <= 10 {
+            text.chars().filter(|c| !c.is_whitespace()).map(|c| c.to_string()).collect()
+        } else {
+            return Err(table_error(line, "transitions need commas with more than 10 states".to_string()));
+        };
+        if tokens.len() != positions + 1 {
+            return Err(table_error(
+                line,
+                format!("expected {} states in a transition, got {}", positions + 1, tokens.len()),
+            ));
+        }
+        let inputs = tokens[..positions]
+            .iter()
+            .map(|token| self.entry(token, line))
+            .collect::<LifeResult<Vec<Entry>>>()?;
+        let output = self.entry(&tokens[positions], line)?;
+
+        // a variable used more than once, or for the output, is the same
+        // state everywhere it is used, so one transition per state it can be
+        let mut bound: Vec<String> = Vec::new();
+        for entry in inputs.iter() {
+            if let Entry::Var(name) = entry {
+                let uses = inputs.iter().filter(|&other| other == entry).count();
+                if (uses > 1 || output == *entry) && !bound.contains(name) {
+                    bound.push(name.clone());
+                }
+            }
+        }
+        let output = match output {
+            Entry::Var(name) if !bound.contains(&name) => {
+                return Err(table_error(line, format!("{} has to be used before it can be the output", name)))
+            }
+            Entry::Set(set) if set.states().count() != 1 => {
+                return Err(table_error(line, "the output has to be a single state".to_string()))
+            }
+            output => output,
+        };
+        let choices: Vec<Vec<CellState>> = bound
+            .iter()
+            .map(|name| self.variable(name).unwrap().states().collect())
+            .collect();
+        let mut picks = vec![0; bound.len()];
+        loop {
+            let value = |entry: &Entry| match entry {
+                Entry::Var(name) => match bound.iter().position(|bound| bound == name) {
+                    Some(index) => StateSet::single(choices[index][picks[index]]),
+                    None => self.variable(name).unwrap(),
+                },
+                Entry::Set(set) => *set,
+            };
+            let transition = Transition {
+                inputs: inputs.iter().map(value).collect(),
+                output: value(&output).states().next().unwrap(),
+            };
+            self.add_symmetric(transition);
+            // next combination, like counting with a digit per bound variable
+            let mut index = 0;
+            while index < picks.len() && picks[index] + 1 == choices[index].len() {
+                picks[index] = 0;
+                index += 1;
+            }
+            if index == picks.len() {
+                return Ok(());
+            }
+            picks[index] += 1;
+        }
+    }
+    // transition along with every version of it the symmetries call for
+    fn add_symmetric(&mut self, transition: Transition) {
+        let neighbors = self.neighborhood.offsets().len();
+        let orders = self.symmetry.permutations(neighbors).unwrap_or_else(|| {
+            // permute: every distinct ordering of the neighbors there is
+            let mut sorted: Vec<usize> = (0..neighbors).collect();
+            sorted.sort_by_key(|&k| transition.inputs[1 + k]);
+            let mut orders = Vec::new();
+            loop {
+                orders.push(sorted.clone());
+                if !next_permutation(&mut sorted, |&a, &b| transition.inputs[1 + a].cmp(&transition.inputs[1 + b])) {
+                    return orders;
+                }
+            }
+        });
+        for order in orders {
+            let mut inputs = vec![transition.inputs[0]];
+            inputs.extend(order.iter().map(|&k| transition.inputs[1 + k]));
+            let permuted = Transition {
+                inputs,
+                output: transition.output,
+            };
+            if self.seen.insert(permuted.clone()) {
+                self.transitions.push(permuted);
+            }
+        }
+    }
+}
+
+// Rearranges items into the next ordering that compares greater, going by
+// compare. False, with items back in order, once it has been through them all.
+// Equal items never swap places, so each distinct ordering comes up once
+fn next_permutation<T>(items: &mut [T], compare: impl Fn(&T, &T) -> std::cmp::Ordering) -> bool {
+    use std::cmp::Ordering::Less;
+    let pivot = match (1..items.len()).rev().find(|&k| compare(&items[k - 1], &items[k]) == Less) {
+        Some(k) => k - 1,
+        None => {
+            items.reverse();
+            return false;
+        }
+    };
+    let successor = (pivot + 1..items.len())
+        .rev()
+        .find(|&k| compare(&items[pivot], &items[k]) == Less)
+        .unwrap();
+    items.swap(pivot, successor);
+    items[pivot + 1..].reverse();
+    true
+}
+
+impl RuleTable {
+    pub fn load(path: &Path) -> LifeResult<RuleTable> {
+        RuleTable::parse(&fs::read_to_string(path)?)
+    }
+    /// A .rule file, of which only @RULE, @TABLE and @COLORS get looked at
+    pub fn parse(text: &str) -> LifeResult<RuleTable> {
+        RuleTable::parse_with_dense_limit(text, DENSE_LIMIT)
+    }
+    fn parse_with_dense_limit(text: &str, dense_limit: usize) -> LifeResult<RuleTable> {
+        let mut name = None;
+        let mut section = None;
+        let mut table = TableParser {
+            states: None,
+            neighborhood: Neighborhood::Moore,
+            symmetry: TableSymmetry::None,
+            variables: Vec::new(),
+            transitions: Vec::new(),
+            seen: HashSet::new(),
+        };
+        let mut colors: Vec<(usize, (u8, u8, u8), usize)> = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('@') {
+                let mut parts = line.splitn(2, char::is_whitespace);
+                section = Some(match parts.next().unwrap() {
+                    "@RULE" => {
+                        name = parts.next().map(|name| name.trim().to_string());
+                        Section::Rule
+                    }
+                    "@TABLE" => Section::Table,
+                    "@COLORS" => Section::Colors,
+                    "@TREE" => {
+                        return Err(table_error(line_number, "only @TABLE rules are supported, not @TREE".to_string()))
+                    }
+                    _ => Section::Other,
+                });
+                continue;
+            }
+            match section {
+                Some(Section::Table) => table.line(line, line_number)?,
+                Some(Section::Colors) => {
+                    let numbers: Vec<usize> = line
+                        .split_whitespace()
+                        .map(|number| number.parse::<usize>())
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| table_error(line_number, format!("expected state r g b, got {}", line)))?;
+                    match numbers.as_slice() {
+                        &[state, r, g, b] if r < 256 && g < 256 && b < 256 => {
+                            colors.push((state, (r as u8, g as u8, b as u8), line_number))
+                        }
+                        _ => return Err(table_error(line_number, format!("expected state r g b, got {}", line))),
+                    }
+                }
+                // descriptions and the like
+                _ => (),
+            }
+        }
+        let name = name.ok_or_else(|| table_error(1, "no @RULE line".to_string()))?;
+        let states = table
+            .states
+            .ok_or_else(|| table_error(1, "no @TABLE with n_states in it".to_string()))?;
+        let mut rule_colors = default_colors(states);
+        for (state, color, line) in colors {
+            match rule_colors.get_mut(state) {
+                Some(rule_color) => *rule_color = color,
+                None => return Err(table_error(line, format!("{} is not a state of this rule", state))),
+            }
+        }
+        let positions = 1 + table.neighborhood.offsets().len();
+        let matches = Matches::new(&table.transitions, states, positions);
+        let lookup = match states.checked_pow(positions as u32) {
+            Some(neighborhoods) if neighborhoods <= dense_limit => {
+                let mut neighborhood = vec![0; positions];
+                let dense = (0..neighborhoods)
+                    .map(|index| {
+                        let mut rest = index;
+                        for state in neighborhood.iter_mut() {
+                            *state = (rest % states) as CellState;
+                            rest /= states;
+                        }
+                        matches.next_state(&neighborhood).unwrap_or(neighborhood[0])
+                    })
+                    .collect();
+                Lookup::Dense(dense)
+            }
+            _ => Lookup::Sparse(matches),
+        };
+        Ok(RuleTable {
+            name,
+            states,
+            neighborhood: table.neighborhood,
+            colors: rule_colors,
+            lookup,
+        })
+    }
+}
+
+impl Rule for RuleTable {
+    fn states(&self) -> usize {
+        self.states
+    }
+    #[inline]
+    fn next_state(&self, i: i32, j: i32, vec: &BMatrixVector) -> CellState {
+        let offsets = self.neighborhood.offsets();
+        let mut neighborhood = [DEAD; MAX_POSITIONS];
+        neighborhood[0] = vec.state(i, j);
+        for (state, &(di, dj)) in neighborhood[1..].iter_mut().zip(offsets.iter()) {
+            *state = vec.state(i + di, j + dj);
+        }
+        let neighborhood = &neighborhood[..1 + offsets.len()];
+        match &self.lookup {
+            // neighborhood as a number in base states, center the lowest digit
+            Lookup::Dense(dense) => {
+                let index = neighborhood
+                    .iter()
+                    .rev()
+                    .fold(0, |index, &state| index * self.states + state as usize);
+                dense[index]
+            }
+            Lookup::Sparse(matches) => matches.next_state(neighborhood).unwrap_or(neighborhood[0]),
+        }
+    }
+    fn colors(&self) -> Vec<(u8, u8, u8)> {
+        self.colors.clone()
+    }
+}
+
+impl fmt::Display for RuleTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wireworld() -> RuleTable {
+        bundled_table("wireworld").unwrap().unwrap()
+    }
+
+    fn vec_from_rows(rows: &[&str]) -> BMatrixVector {
+        let mut vec = BMatrixVector::new(rows[0].len() as i32, rows.len() as i32);
+        for (j, row) in rows.iter().enumerate() {
+            for (i, c) in row.chars().enumerate() {
+                *vec.at_mut(i as i32, j as i32).unwrap() = c.to_digit(10).unwrap() as CellState;
+            }
+        }
+        vec
+    }
+
+    fn step(rule: &dyn Rule, vec: &BMatrixVector) -> BMatrixVector {
+        let mut next = vec.clone();
+        rule.next_cells(0, vec, &mut next);
+        next
+    }
+
+    #[test]
+    fn test_RuleTable_wireworld_electron_runs_along_wire() {
+        let rule = wireworld();
+        assert_eq!((rule.states(), rule.to_string()), (4, "WireWorld".to_string()));
+        let vec = vec_from_rows(&["0000000", "2133333", "0000000"]);
+        let vec = step(&rule, &vec);
+        assert!(vec == vec_from_rows(&["0000000", "3213333", "0000000"]));
+        let vec = step(&rule, &vec);
+        assert!(vec == vec_from_rows(&["0000000", "3321333", "0000000"]));
+        // heads at both ends each set off the wire next to them
+        let vec = step(&rule, &vec_from_rows(&["13331", "00000"]));
+        assert!(vec == vec_from_rows(&["21312", "00000"]));
+        // but three heads are too many
+        let vec = step(&rule, &vec_from_rows(&["111", "030", "000"]));
+        assert_eq!(vec.state(1, 1), 3);
+    }
+
+    #[test]
+    fn test_RuleTable_colors() {
+        let rule = wireworld();
+        assert_eq!(rule.colors()[1], (0, 128, 255));
+        assert_eq!(rule.colors()[3], (255, 128, 0));
+    }
+
+    // one transition, checked against every rotation and reflection of it
+    fn single(symmetries: &str, neighborhood: &str, transition: &str) -> RuleTable {
+        RuleTable::parse(&format!(
+            "@RULE Test\n@TABLE\nn_states:3\nneighborhood:{}\nsymmetries:{}\n{}\n",
+            neighborhood, symmetries, transition
+        ))
+        .unwrap()
+    }
+
+    fn fires(rule: &RuleTable, neighbors: &[CellState]) -> bool {
+        let mut vec = BMatrixVector::new(3, 3);
+        for (&state, &(di, dj)) in neighbors.iter().zip(rule.neighborhood.offsets().iter()) {
+            *vec.at_mut(1 + di, 1 + dj).unwrap() = state;
+        }
+        rule.next_state(1, 1, &vec) == 2
+    }
+
+    #[test]
+    fn test_RuleTable_symmetries() {
+        // N and NE
+        let transition = "0,1,1,0,0,0,0,0,0,2";
+        let rotated = [0, 0, 1, 1, 0, 0, 0, 0];
+        let turned = [0, 1, 1, 0, 0, 0, 0, 0];
+        let mirrored = [1, 0, 0, 0, 0, 0, 0, 1];
+        let apart = [1, 0, 1, 0, 0, 0, 0, 0];
+        let cases = [
+            ("none", [true, false, false, false, false]),
+            ("rotate4", [true, true, false, false, false]),
+            // N and NW is just N and NE turned the other way
+            ("rotate8", [true, true, true, true, false]),
+            ("reflect_horizontal", [true, false, false, true, false]),
+            ("rotate8reflect", [true, true, true, true, false]),
+            ("permute", [true, true, true, true, true]),
+        ];
+        for &(symmetries, expected) in cases.iter() {
+            let rule = single(symmetries, "Moore", transition);
+            let got: Vec<bool> = [[1, 1, 0, 0, 0, 0, 0, 0], rotated, turned, mirrored, apart]
+                .iter()
+                .map(|neighbors| fires(&rule, neighbors))
+                .collect();
+            assert_eq!(got, expected, "{}", symmetries);
+        }
+        // compact transitions and von Neumann
+        let rule = single("rotate4", "vonNeumann", "010002");
+        assert!(fires(&rule, &[1, 0, 0, 0]) && fires(&rule, &[0, 0, 0, 1]));
+        assert!(!fires(&rule, &[1, 1, 0, 0]));
+    }
+
+    #[test]
+    fn test_RuleTable_variables_are_bound() {
+        // a cell copies its north neighbor, but only if south is the same
+        let rule = RuleTable::parse(
+            "@RULE Copy\n@TABLE\nn_states:3\nneighborhood:vonNeumann\nsymmetries:none\n\
+             var a={1,2}\nvar b={0,1,2}\nvar c={b}\n0,a,b,a,c,a\n",
+        )
+        .unwrap();
+        let next = |north, south| {
+            let mut vec = BMatrixVector::new(3, 3);
+            *vec.at_mut(1, 0).unwrap() = north;
+            *vec.at_mut(1, 2).unwrap() = south;
+            *vec.at_mut(0, 1).unwrap() = 2;
+            rule.next_state(1, 1, &vec)
+        };
+        assert_eq!(next(1, 1), 1);
+        assert_eq!(next(2, 2), 2);
+        // no transition matches, so it stays as it was
+        assert_eq!(next(1, 2), 0);
+        assert_eq!(next(0, 0), 0);
+    }
+
+    #[test]
+    fn test_RuleTable_sparse_agrees_with_dense() {
+        let dense = wireworld();
+        let sparse = RuleTable::parse_with_dense_limit(BUNDLED_TABLES[0].1, 0).unwrap();
+        match (&dense.lookup, &sparse.lookup) {
+            (Lookup::Dense(_), Lookup::Sparse(_)) => (),
+            _ => panic!("expected one of each"),
+        }
+        // every state all over, so plenty of neighborhoods come up
+        let mut vec = BMatrixVector::new(40, 40);
+        for (index, cell) in vec.iter_mut().enumerate() {
+            *cell = ((index * 7 + index / 40 * 3) % 5 % 4) as CellState;
+        }
+        for _ in 0..4 {
+            let next = step(&dense, &vec);
+            assert!(next == step(&sparse, &vec));
+            vec = next;
+        }
+    }
+
+    #[test]
+    fn test_RuleTable_parse_errors() {
+        let message = |text: &str| match RuleTable::parse(text) {
+            Err(LifeError::Parse { line, message }) => (line, message),
+            _ => panic!("expected a parse error for {}", text),
+        };
+        let table = |body: &str| format!("@RULE Bad\n@TABLE\n{}", body);
+        assert_eq!(message(&table("n_states:1\n")), (3, "n_states has to be 2 to 256, got 1".to_string()));
+        assert_eq!(message(&table("n_states:2\nsymmetries:rotate3\n")).1, "unknown symmetries rotate3");
+        assert_eq!(message(&table("n_states:2\n0,1,2\n")).1, "expected 10 states in a transition, got 3");
+        assert_eq!(message(&table("n_states:2\n0,1,1,0,0,0,0,0,0,2\n")).1, "2 is not a state of this rule");
+        assert_eq!(message(&table("n_states:2\nvar a={0,1}\n0,1,0,0,0,0,0,0,0,a\n")).1, "a has to be used before it can be the output");
+        assert_eq!(message(&table("0,1,0,0,0,0,0,0,0,1\n")).1, "n_states has to come before this");
+        assert_eq!(message("@RULE Bad\n@TREE\n").1, "only @TABLE rules are supported, not @TREE");
+        assert_eq!(message(&table("n_states:2\n@COLORS\n2 1 2 3\n")), (5, "2 is not a state of this rule".to_string()));
+    }
+
+    #[test]
+    fn test_next_permutation() {
+        let mut items = vec![1, 1, 2];
+        let mut seen = vec![items.clone()];
+        while next_permutation(&mut items, |a, b| a.cmp(b)) {
+            seen.push(items.clone());
+        }
+        assert_eq!(seen, vec![vec![1, 1, 2], vec![1, 2, 1], vec![2, 1, 1]]);
+        assert_eq!(items, vec![1, 1, 2]);
+    }
+}
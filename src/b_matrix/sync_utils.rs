@@ -1,4 +1,6 @@
-use std::sync::{Arc,Mutex,MutexGuard,RwLock,RwLockWriteGuard,RwLockReadGuard};
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc,Mutex,MutexGuard};
 use std::ops::{Deref, DerefMut};
 
 pub struct MyArcMut<T>(Arc<Mutex<T>>);
@@ -7,7 +9,7 @@ impl<T> MyArcMut<T>{
         MyArcMut(Arc::new(Mutex::new(value)))
     }
     // use this only if set and get are too limiting
-    pub fn grab_lock(&self) -> MutexGuard<T>{
+    pub fn grab_lock(&self) -> MutexGuard<'_, T>{
         self.0.lock().unwrap()
     }
 }
@@ -27,20 +29,186 @@ impl<T> Clone for MyArcMut<T>{
     }
 }
 
-pub struct MyArcRwLock<T>(Arc<RwLock<T>>);
-impl<T> MyArcRwLock<T>{
-    pub fn new(value: T)->Self{
-        MyArcRwLock(Arc::new(RwLock::new(value)))
+// ************  TRIPLE BUFFER  ************
+// The writer always owns a back buffer and the reader always owns a front buffer,
+// so neither ever waits on the other. The third(middle) buffer is handed between
+// them by swapping its index through a single atomic.
+const INDEX_MASK: usize = 0b011;
+// set by the writer on publish, cleared when the reader picks it up
+const FRESH: usize = 0b100;
+
+struct TripleBuffer<T> {
+    buffers: [UnsafeCell<T>; 3],
+    middle: AtomicUsize,
+}
+// each buffer is only ever written by the one thread that owns it as its back buffer
+unsafe impl<T: Send + Sync> Sync for TripleBuffer<T> {}
+
+pub fn triple_buffer<T: Clone>(value: T) -> (TripleBufferWriter<T>, TripleBufferReader<T>) {
+    let shared = Arc::new(TripleBuffer {
+        buffers: [
+            UnsafeCell::new(value.clone()),
+            UnsafeCell::new(value.clone()),
+            UnsafeCell::new(value),
+        ],
+        middle: AtomicUsize::new(1),
+    });
+    let writer = TripleBufferWriter {
+        shared: shared.clone(),
+        back: 2,
+        // nobody has published yet, but the middle buffer holds the initial value
+        // and only the writer can make the reader swap it out
+        last: 1,
+    };
+    let reader = TripleBufferReader { shared, front: 0 };
+    (writer, reader)
+}
+
+pub struct TripleBufferWriter<T> {
+    shared: Arc<TripleBuffer<T>>,
+    back: usize,
+    // last published buffer. Sitting in either middle or front, which the
+    // reader only ever reads, so we can keep reading from it too
+    last: usize,
+}
+impl<T> TripleBufferWriter<T> {
+    pub fn latest(&self) -> &T {
+        unsafe { &*self.shared.buffers[self.last].get() }
     }
-    pub fn grab_writer_lock(&self) -> RwLockWriteGuard<T>{
-        self.0.write().unwrap()
+    pub fn back_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.shared.buffers[self.back].get() }
+    }
+    /// Last published value along with the back buffer to write the next one into
+    pub fn latest_and_back_mut(&mut self) -> (&T, &mut T) {
+        // back is never equal to last, see publish
+        unsafe {
+            (
+                &*self.shared.buffers[self.last].get(),
+                &mut *self.shared.buffers[self.back].get(),
+            )
+        }
+    }
+    pub fn publish(&mut self) {
+        let old_middle = self.shared.middle.swap(self.back | FRESH, Ordering::AcqRel);
+        self.last = self.back;
+        // the three indices are always a permutation, so this can't be last
+        self.back = old_middle & INDEX_MASK;
+    }
+}
+
+pub struct TripleBufferReader<T> {
+    shared: Arc<TripleBuffer<T>>,
+    front: usize,
+}
+impl<T> TripleBufferReader<T> {
+    pub fn has_update(&self) -> bool {
+        self.shared.middle.load(Ordering::Acquire) & FRESH != 0
     }
-    pub fn grab_reader_lock(&self) -> RwLockReadGuard<T>{
-        self.0.read().unwrap()
+    /// Latest published value. Never blocks, just reads the previous one again
+    /// if the writer hasn't published since
+    pub fn read(&mut self) -> &T {
+        if self.has_update() {
+            let old_middle = self.shared.middle.swap(self.front, Ordering::AcqRel);
+            self.front = old_middle & INDEX_MASK;
+        }
+        unsafe { &*self.shared.buffers[self.front].get() }
     }
 }
-impl<T> Clone for MyArcRwLock<T>{
-    fn clone(&self) -> MyArcRwLock<T>{
-        MyArcRwLock(self.0.clone())
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    const FRAME_LEN: usize = 4096;
+
+    #[test]
+    fn test_triple_buffer_reader_sees_initial_value() {
+        let (_writer, mut reader) = triple_buffer(7);
+        assert!(!reader.has_update());
+        assert_eq!(*reader.read(), 7);
+    }
+
+    #[test]
+    fn test_triple_buffer_reader_sees_latest_publish() {
+        let (mut writer, mut reader) = triple_buffer(0);
+        for value in 1..4 {
+            *writer.back_mut() = value;
+            writer.publish();
+        }
+        assert!(reader.has_update());
+        assert_eq!(*reader.read(), 3);
+        assert!(!reader.has_update());
+        assert_eq!(*reader.read(), 3);
+    }
+
+    #[test]
+    fn test_triple_buffer_writer_reads_what_it_published() {
+        let (mut writer, mut reader) = triple_buffer(0);
+        for _ in 0..10 {
+            let (latest, back) = writer.latest_and_back_mut();
+            *back = latest + 1;
+            writer.publish();
+            // reader picking up in between shouldn't matter
+            reader.read();
+        }
+        assert_eq!(*writer.latest(), 10);
+        assert_eq!(*reader.read(), 10);
+    }
+
+    #[test]
+    fn test_triple_buffer_stress_no_torn_frames() {
+        // every frame is FRAME_LEN copies of its frame number, so a frame the
+        // writer was halfway through would show up as a mix
+        let num_frames = 20_000;
+        let (mut writer, mut reader) = triple_buffer(vec![0usize; FRAME_LEN]);
+
+        let writer_thread = thread::spawn(move || {
+            for frame in 1..=num_frames {
+                let (latest, back) = writer.latest_and_back_mut();
+                assert_eq!(latest[0], frame - 1);
+                for cell in back.iter_mut() {
+                    *cell = frame;
+                }
+                writer.publish();
+            }
+        });
+
+        let mut last_seen = 0;
+        let mut frames_seen = 0;
+        while last_seen < num_frames {
+            let frame = reader.read();
+            let frame_number = frame[0];
+            assert!(frame.iter().all(|&cell| cell == frame_number), "torn frame");
+            assert!(frame_number >= last_seen, "frames went backwards");
+            if frame_number != last_seen {
+                frames_seen += 1;
+            }
+            last_seen = frame_number;
+        }
+        writer_thread.join().unwrap();
+        assert!(frames_seen > 0);
+    }
+
+    #[test]
+    fn test_triple_buffer_writer_never_waits_on_slow_reader() {
+        let (mut writer, mut reader) = triple_buffer(vec![0usize; FRAME_LEN]);
+        // reader grabs a frame and sits on it while the writer keeps going
+        *writer.back_mut() = vec![1; FRAME_LEN];
+        writer.publish();
+        let held = reader.read();
+        let writer_thread = thread::spawn(move || {
+            for frame in 2..1000 {
+                for cell in writer.back_mut().iter_mut() {
+                    *cell = frame;
+                }
+                writer.publish();
+            }
+            writer
+        });
+        let writer = writer_thread.join().unwrap();
+        assert!(held.iter().all(|&cell| cell == 1));
+        assert_eq!(writer.latest()[0], 999);
+        assert_eq!(reader.read()[0], 999);
     }
 }
@@ -0,0 +1,233 @@
+use super::*;
+use crate::library::{LibraryEntry, PatternLibrary};
+use crate::patterns::{Mirror, PatternBuilder, Transform};
+use ggez::graphics::{DrawMode, MeshBuilder, Rect, Text};
+use nalgebra::geometry::Point2;
+
+const PANEL_WIDTH: f32 = 360.0;
+const PANEL_MARGIN: f32 = 10.0;
+const PANEL_PADDING: f32 = 6.0;
+// patterns listed at once, the list scrolls to keep the selected one in view
+const PANEL_ROWS: usize = 16;
+// characters per line of description
+const WRAP_WIDTH: usize = 40;
+const PREVIEW_SIZE: f32 = PANEL_WIDTH - 2.0 * PANEL_PADDING;
+
+// Panel on the right for picking a pattern out of the library. The viewer
+// stamps whatever is selected wherever the cursor is, turned and flipped
+// however the rotate and flip keys left it
+pub struct LibraryBrowser {
+    library: PatternLibrary,
+    selected: usize,
+    visible: bool,
+    transform: Transform,
+}
+
+// words of text, joined up again into lines no longer than width where possible
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.len() + 1 + word.len() <= width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
+
+// pattern transformed, then shifted so its top left corner is back at (0,0)
+fn oriented(pattern: &BMatrixVector, transform: Transform) -> BMatrixVector {
+    let (x0, y0) = transform.apply(0, 0);
+    let (x1, y1) = transform.apply(pattern.width() - 1, pattern.height() - 1);
+    PatternBuilder::with_size((x1 - x0).abs() + 1, (y1 - y0).abs() + 1)
+        .stamp(pattern, 0, 0, transform.then(Transform::translate(-x0.min(x1), -y0.min(y1))))
+        .build()
+}
+
+impl LibraryBrowser {
+    pub fn new(library: PatternLibrary) -> Self {
+        LibraryBrowser {
+            library,
+            selected: 0,
+            visible: false,
+            transform: Transform::IDENTITY,
+        }
+    }
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+    // both wrap round at the ends of the list
+    pub fn select_next(&mut self) {
+        let count = self.library.entries().len();
+        if count > 0 {
+            self.selected = (self.selected + 1) % count;
+        }
+    }
+    pub fn select_previous(&mut self) {
+        let count = self.library.entries().len();
+        if count > 0 {
+            self.selected = (self.selected + count - 1) % count;
+        }
+    }
+    pub fn selected(&self) -> Option<&LibraryEntry> {
+        self.library.entries().get(self.selected)
+    }
+    // these stick when another pattern gets picked
+    pub fn rotate(&mut self) {
+        self.transform = self.transform.then(Transform::rotate_clockwise(1));
+    }
+    pub fn flip_horizontal(&mut self) {
+        self.transform = self.transform.then(Transform::reflect(Mirror::Vertical));
+    }
+    pub fn flip_vertical(&mut self) {
+        self.transform = self.transform.then(Transform::reflect(Mirror::Horizontal));
+    }
+    /// The selected pattern, the way round it gets stamped
+    pub fn pattern(&self) -> Option<BMatrixVector> {
+        self.selected().map(|entry| oriented(&entry.pattern, self.transform))
+    }
+    /// The list around the selected pattern, then all about the selected one
+    pub fn lines(&self) -> Vec<String> {
+        let entries = self.library.entries();
+        let first = self
+            .selected
+            .saturating_sub(PANEL_ROWS / 2)
+            .min(entries.len().saturating_sub(PANEL_ROWS));
+        let mut lines: Vec<String> = entries
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(PANEL_ROWS)
+            .map(|(index, entry)| {
+                let marker = if index == self.selected { ">" } else { " " };
+                format!("{} {}/{}", marker, entry.category, entry.name)
+            })
+            .collect();
+        if let (Some(entry), Some(pattern)) = (self.selected(), self.pattern()) {
+            lines.push(String::new());
+            lines.push(entry.title.clone());
+            lines.extend(wrap(&entry.description, WRAP_WIDTH));
+            lines.push(format!("{}x{}, {} cells", pattern.width(), pattern.height(), pattern.population()));
+        }
+        lines
+    }
+    pub fn draw(&self, ctx: &mut Context) -> GameResult {
+        if !self.visible {
+            return Ok(());
+        }
+        let (screen_width, _) = graphics::drawable_size(ctx);
+        let left = screen_width - PANEL_WIDTH - PANEL_MARGIN;
+        let text = Text::new(self.lines().join("\n"));
+        let (_, text_height) = text.dimensions(ctx);
+        let preview_top = PANEL_MARGIN + text_height as f32 + PANEL_PADDING;
+        let background = graphics::Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(
+                left - PANEL_PADDING,
+                PANEL_MARGIN - PANEL_PADDING,
+                PANEL_WIDTH,
+                text_height as f32 + PREVIEW_SIZE + 3.0 * PANEL_PADDING,
+            ),
+            BLACK!(),
+        )?;
+        graphics::draw(ctx, &background, DrawParam::default())?;
+        if let Some(pattern) = self.pattern() {
+            self.draw_preview(ctx, &pattern, left, preview_top)?;
+        }
+        // glyphs need alpha blending, everything else is drawn with Replace
+        graphics::set_blend_mode(ctx, BlendMode::Alpha)?;
+        graphics::draw(
+            ctx,
+            &text,
+            DrawParam::default()
+                .dest(Point2::new(left, PANEL_MARGIN))
+                .color(WHITE!()),
+        )?;
+        graphics::set_blend_mode(ctx, BlendMode::Replace)
+    }
+    // the whole pattern scaled to fit, never bigger than it is in the universe
+    fn draw_preview(&self, ctx: &mut Context, pattern: &BMatrixVector, left: f32, top: f32) -> GameResult {
+        if pattern.population() == 0 {
+            return Ok(());
+        }
+        let cell = (PREVIEW_SIZE / pattern.width().max(pattern.height()) as f32)
+            .min(CELL_SIZE + CELL_GAP);
+        let mut mesh = MeshBuilder::new();
+        for j in 0..pattern.height() {
+            for i in 0..pattern.width() {
+                if pattern.is_alive(i, j) {
+                    mesh.rectangle(
+                        DrawMode::fill(),
+                        Rect::new(left + i as f32 * cell, top + j as f32 * cell, cell * 0.85, cell * 0.85),
+                        WHITE!(),
+                    );
+                }
+            }
+        }
+        let mesh = mesh.build(ctx)?;
+        graphics::draw(ctx, &mesh, DrawParam::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_LibraryBrowser_wrap() {
+        assert_eq!(wrap("a bb ccc dddd", 6), vec!["a bb", "ccc", "dddd"]);
+        // longer words stay whole on a line of their own
+        assert_eq!(wrap("tiny enormous", 4), vec!["tiny", "enormous"]);
+        assert!(wrap("   ", 10).is_empty());
+    }
+
+    #[test]
+    fn test_LibraryBrowser_selection_wraps_round_and_shows_in_lines() {
+        let mut browser = LibraryBrowser::new(PatternLibrary::bundled());
+        let count = browser.library.entries().len();
+        assert_eq!(browser.selected().unwrap().name, "gosper_glider_gun");
+        browser.select_previous();
+        assert_eq!(browser.selected().unwrap().name, "mwss");
+        browser.select_next();
+        browser.select_next();
+        let selected = browser.selected().unwrap().name.clone();
+        let lines = browser.lines();
+        assert_eq!(
+            lines.iter().filter(|line| line.starts_with('>')).collect::<Vec<_>>(),
+            vec![&format!("> guns/{}", selected)]
+        );
+        assert_eq!(lines.last().unwrap(), "33x21, 36 cells");
+        // never more of the list than fits
+        for _ in 0..count {
+            browser.select_next();
+            assert!(browser.lines().iter().filter(|line| line.contains('/')).count() <= PANEL_ROWS);
+        }
+    }
+
+    #[test]
+    fn test_LibraryBrowser_turns_the_pattern_it_stamps() {
+        let mut browser = LibraryBrowser::new(PatternLibrary::bundled());
+        let gun = browser.pattern().unwrap();
+        browser.rotate();
+        let turned = browser.pattern().unwrap();
+        assert!(turned == gun.rotated_clockwise());
+        // and it stays turned for the next pattern
+        browser.select_next();
+        let next = browser.selected().unwrap().pattern.clone();
+        assert!(browser.pattern().unwrap() == next.rotated_clockwise());
+        browser.select_previous();
+        browser.flip_horizontal();
+        browser.flip_vertical();
+        browser.rotate();
+        assert!(browser.pattern().unwrap() == gun);
+        browser.flip_horizontal();
+        assert!(browser.pattern().unwrap() == gun.flipped_horizontal());
+    }
+}
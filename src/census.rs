@@ -0,0 +1,560 @@
+// Splits a settled universe into objects and counts them, like apgsearch does.
+// Objects are named by their apgcode (see https://conwaylife.com/wiki/Apgcode),
+// so unknown ones can still be looked up on Catagolue
+use super::*;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::mem;
+
+// Longest period classify looks for
+const MAX_PERIOD: u32 = 64;
+// Room around an object for it to oscillate or fly in. Nothing in Life moves
+// faster than c/2, so it never gets further than this in max_period generations
+fn margin(max_period: u32) -> i32 {
+    max_period as i32 / 2 + 2
+}
+
+// Column values in Wechsler format are 5 bit numbers
+const WECHSLER_DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+// Nowhere near all of them, just the ones that make up most of the ash
+const KNOWN_OBJECTS: [(&str, &str); 20] = [
+    ("xs4_33", "block"),
+    ("xs4_252", "tub"),
+    ("xs5_253", "boat"),
+    ("xs6_696", "beehive"),
+    ("xs6_356", "ship"),
+    ("xs6_25a4", "barge"),
+    ("xs6_39c", "aircraft carrier"),
+    ("xs7_2596", "loaf"),
+    ("xs7_25ac", "long boat"),
+    ("xs7_178c", "eater 1"),
+    ("xs8_6996", "pond"),
+    ("xp2_7", "blinker"),
+    ("xp2_7e", "toad"),
+    ("xp2_318c", "beacon"),
+    ("xp3_co9nas0san9oczgoldlo0oldlogz1047210127401", "pulsar"),
+    ("xp15_4r4z4r4", "pentadecathlon"),
+    ("xq4_153", "glider"),
+    ("xq4_6frc", "lightweight spaceship"),
+    ("xq4_27dee6", "middleweight spaceship"),
+    ("xq4_27deee6", "heavyweight spaceship"),
+];
+
+pub fn name(code: &str) -> Option<&'static str> {
+    KNOWN_OBJECTS
+        .iter()
+        .find(|&&(known_code, _)| known_code == code)
+        .map(|&(_, name)| name)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectKind {
+    StillLife,
+    Oscillator { period: u32 },
+    /// moves by (dx,dy) every period
+    Spaceship { period: u32, dx: i32, dy: i32 },
+    /// didn't come back within MAX_PERIOD generations, or grew out of its margin
+    Unknown,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Object {
+    pub code: String,
+    pub kind: ObjectKind,
+    // where it was in the universe
+    pub bounding_box: BoundingBox,
+    pub population: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Census {
+    pub objects: Vec<Object>,
+}
+
+impl Census {
+    /// apgcode to how many there are
+    pub fn counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for object in self.objects.iter() {
+            *counts.entry(object.code.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+// most common first, like apgsearch's summaries
+impl fmt::Display for Census {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut counts: Vec<(String, usize)> = self.counts().into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        for (code, count) in counts {
+            match name(&code) {
+                Some(name) => writeln!(f, "{:>6} {} ({})", count, code, name)?,
+                None => writeln!(f, "{:>6} {}", count, code)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// ************  SHAPES  ************
+// Copy of just the bounding box, so two phases can be compared no matter where they are
+fn crop(vec: &BMatrixVector, bounding_box: BoundingBox) -> BMatrixVector {
+    let mut shape = BMatrixVector::new(bounding_box.width, bounding_box.height);
+    for j in 0..bounding_box.height {
+        for i in 0..bounding_box.width {
+            if vec.is_alive(bounding_box.x + i, bounding_box.y + j) {
+                *shape.at_mut(i, j).unwrap() = ALIVE;
+            }
+        }
+    }
+    shape
+}
+
+// 0 to 7 covers every rotation and reflection: bit 2 transposes, then bits 0
+// and 1 flip horizontally and vertically
+fn transform(shape: &BMatrixVector, transformation: u8) -> BMatrixVector {
+    let transpose = transformation & 4 != 0;
+    let (width, height) = if transpose {
+        (shape.height(), shape.width())
+    } else {
+        (shape.width(), shape.height())
+    };
+    let mut transformed = BMatrixVector::new(width, height);
+    for j in 0..shape.height() {
+        for i in 0..shape.width() {
+            let (mut x, mut y) = if transpose { (j, i) } else { (i, j) };
+            if transformation & 1 != 0 {
+                x = width - 1 - x;
+            }
+            if transformation & 2 != 0 {
+                y = height - 1 - y;
+            }
+            *transformed.at_mut(x, y).unwrap() = shape.at(i, j).unwrap();
+        }
+    }
+    transformed
+}
+
+// Runs of zeros get shortened: w is 2 of them, x is 3, y followed by a digit is 4 to 39
+fn compress_zeros(strip: &str) -> String {
+    let mut compressed = String::new();
+    let mut zeros = 0;
+    let flush = |compressed: &mut String, mut zeros: usize| {
+        while zeros >= 4 {
+            let run = zeros.min(39);
+            compressed.push('y');
+            compressed.push(WECHSLER_DIGITS[run - 4] as char);
+            zeros -= run;
+        }
+        match zeros {
+            3 => compressed.push('x'),
+            2 => compressed.push('w'),
+            1 => compressed.push('0'),
+            _ => (),
+        }
+    };
+    for c in strip.chars() {
+        if c == '0' {
+            zeros += 1;
+        } else {
+            flush(&mut compressed, zeros);
+            zeros = 0;
+            compressed.push(c);
+        }
+    }
+    flush(&mut compressed, zeros);
+    compressed
+}
+
+// Extended Wechsler format: strips of 5 rows top to bottom separated by z, each
+// column in a strip written as a base 32 digit with the top row as the low bit
+fn wechsler(shape: &BMatrixVector) -> String {
+    let mut strips = Vec::new();
+    for top in (0..shape.height()).step_by(5) {
+        let mut strip = String::new();
+        for i in 0..shape.width() {
+            let column = (0..5)
+                .filter(|&row| shape.is_alive(i, top + row))
+                .fold(0, |column, row| column | 1 << row);
+            strip.push(WECHSLER_DIGITS[column] as char);
+        }
+        strips.push(compress_zeros(strip.trim_end_matches('0')));
+    }
+    strips.join("z")
+}
+
+// shortest code over every phase and orientation, ties go to whichever sorts first
+fn canonical_wechsler(phases: &[BMatrixVector]) -> String {
+    phases
+        .iter()
+        .flat_map(|phase| (0..8).map(move |transformation| wechsler(&transform(phase, transformation))))
+        .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+        .unwrap_or_default()
+}
+
+// ************  CLASSIFYING  ************
+/// apgcode and kind of a single object, given as the live cells of one phase.
+/// The object gets run on its own, so anything it was interacting with is ignored
+pub fn classify(cells: &[(i32, i32)]) -> (String, ObjectKind) {
+    classify_up_to(cells, MAX_PERIOD)
+}
+
+/// classify, giving up as Unknown past `max_period`. Quite a bit cheaper for
+/// callers that only care about short periods
+pub fn classify_up_to(cells: &[(i32, i32)], max_period: u32) -> (String, ObjectKind) {
+    let margin = margin(max_period);
+    let population = cells.len();
+    if population == 0 {
+        return ("xs0_0".to_string(), ObjectKind::StillLife);
+    }
+    let area = bounding_box_of(cells);
+    let (width, height) = (area.width + 2 * margin, area.height + 2 * margin);
+
+    let mut old_vec = BMatrixVector::new(width, height);
+    for &(i, j) in cells.iter() {
+        *old_vec.at_mut(i - area.x + margin, j - area.y + margin).unwrap() = ALIVE;
+    }
+    let first_box = old_vec.bounding_box().unwrap();
+    let mut phases = vec![crop(&old_vec, first_box)];
+
+    let mut engine = create_engine(Backend::Single);
+    let mut new_vec = old_vec.clone();
+    for period in 1..=max_period {
+        engine.next_b_matrix(&old_vec, &mut new_vec);
+        mem::swap(&mut old_vec, &mut new_vec);
+        let bounding_box = match old_vec.bounding_box() {
+            Some(bounding_box) => bounding_box,
+            // died out, so it wasn't an object on its own
+            None => break,
+        };
+        // anything touching the edge has been cut off by it
+        if bounding_box.x == 0
+            || bounding_box.y == 0
+            || bounding_box.x + bounding_box.width == width
+            || bounding_box.y + bounding_box.height == height
+        {
+            break;
+        }
+        let phase = crop(&old_vec, bounding_box);
+        if phase == phases[0] {
+            let (dx, dy) = (bounding_box.x - first_box.x, bounding_box.y - first_box.y);
+            let code = canonical_wechsler(&phases);
+            return if dx != 0 || dy != 0 {
+                (format!("xq{}_{}", period, code), ObjectKind::Spaceship { period, dx, dy })
+            } else if period == 1 {
+                (format!("xs{}_{}", population, code), ObjectKind::StillLife)
+            } else {
+                (format!("xp{}_{}", period, code), ObjectKind::Oscillator { period })
+            };
+        }
+        phases.push(phase);
+    }
+    (format!("zz_UNKNOWN_{}", population), ObjectKind::Unknown)
+}
+
+/// Groups of live cells in `cells` where every cell is within `reach` (Chebyshev
+/// distance) of another one in its group, each in row major order
+pub fn connected_components(cells: &BMatrixVector, reach: i32) -> Vec<Vec<(i32, i32)>> {
+    let mut seen = BMatrixVector::new(cells.width(), cells.height());
+    let mut components = Vec::new();
+    for j in 0..cells.height() {
+        for i in 0..cells.width() {
+            if !cells.is_alive(i, j) || seen.is_alive(i, j) {
+                continue;
+            }
+            let mut component = Vec::new();
+            let mut queue = VecDeque::new();
+            *seen.at_mut(i, j).unwrap() = ALIVE;
+            queue.push_back((i, j));
+            while let Some((x, y)) = queue.pop_front() {
+                component.push((x, y));
+                for ny in y - reach..=y + reach {
+                    for nx in x - reach..=x + reach {
+                        if cells.is_alive(nx, ny) && !seen.is_alive(nx, ny) {
+                            *seen.at_mut(nx, ny).unwrap() = ALIVE;
+                            queue.push_back((nx, ny));
+                        }
+                    }
+                }
+            }
+            component.sort_by_key(|&(x, y)| (y, x));
+            components.push(component);
+        }
+    }
+    components
+}
+
+pub fn bounding_box_of(cells: &[(i32, i32)]) -> BoundingBox {
+    let left = cells.iter().map(|&(i, _)| i).min().unwrap();
+    let top = cells.iter().map(|&(_, j)| j).min().unwrap();
+    let right = cells.iter().map(|&(i, _)| i).max().unwrap();
+    let bottom = cells.iter().map(|&(_, j)| j).max().unwrap();
+    BoundingBox {
+        x: left,
+        y: top,
+        width: right - left + 1,
+        height: bottom - top + 1,
+    }
+}
+
+// Every generation from 1 to `generations` of cells run on their own, in a
+// universe that is `area` plus enough margin that they never reach the edge
+fn evolve_alone(cells: &[(i32, i32)], area: BoundingBox, generations: u64) -> Vec<BMatrixVector> {
+    let margin = margin(generations.min(u32::MAX as u64) as u32);
+    let mut old_vec = BMatrixVector::new(area.width + 2 * margin, area.height + 2 * margin);
+    for &(i, j) in cells.iter() {
+        *old_vec.at_mut(i - area.x + margin, j - area.y + margin).unwrap() = ALIVE;
+    }
+    let mut engine = create_engine(Backend::Single);
+    let mut new_vec = old_vec.clone();
+    let mut history = Vec::new();
+    for _ in 0..generations {
+        engine.next_b_matrix(&old_vec, &mut new_vec);
+        mem::swap(&mut old_vec, &mut new_vec);
+        history.push(old_vec.clone());
+    }
+    history
+}
+
+// Pieces can be split up if running them one by one gives the same thing as
+// running them together
+fn evolve_independently(pieces: &[Vec<(i32, i32)>], generations: u64) -> bool {
+    let all_cells: Vec<(i32, i32)> = pieces.iter().flatten().cloned().collect();
+    let area = bounding_box_of(&all_cells);
+    let together = evolve_alone(&all_cells, area, generations);
+    let mut apart: Vec<BMatrixVector> = together
+        .iter()
+        .map(|vec| BMatrixVector::new(vec.width(), vec.height()))
+        .collect();
+    for piece in pieces.iter() {
+        for (combined, piece_vec) in apart.iter_mut().zip(evolve_alone(piece, area, generations)) {
+            for (cell, &alive) in combined.iter_mut().zip(piece_vec.iter()) {
+                *cell |= alive;
+            }
+        }
+    }
+    together == apart
+}
+
+/// Splits vec into objects and classifies each one. `period` is how often the
+/// whole universe repeats (1 if it isn't known).
+///
+/// Cells that come within 2 of each other in any phase over that period could
+/// be affecting each other, so they start out as one object. That object only
+/// gets split into its 8-connected pieces if the pieces evolve the same on their
+/// own, which keeps a pulsar in one piece while splitting the four blinkers of a
+/// traffic light apart
+pub fn take_census(vec: &BMatrixVector, period: u64) -> Census {
+    let period = period.max(1);
+    let mut union = vec.clone();
+    let mut engine = create_engine(Backend::Single);
+    let mut old_vec = vec.clone();
+    let mut new_vec = vec.clone();
+    for _ in 1..period {
+        engine.next_b_matrix(&old_vec, &mut new_vec);
+        mem::swap(&mut old_vec, &mut new_vec);
+        for (cell, &alive) in union.iter_mut().zip(old_vec.iter()) {
+            *cell |= alive;
+        }
+    }
+    let current_cells = |component: Vec<(i32, i32)>| -> Vec<(i32, i32)> {
+        component
+            .into_iter()
+            .filter(|&(i, j)| vec.is_alive(i, j))
+            .collect()
+    };
+
+    let mut objects = Vec::new();
+    for group in connected_components(&union, 2) {
+        let mut group_union = BMatrixVector::new(union.width(), union.height());
+        for &(i, j) in group.iter() {
+            *group_union.at_mut(i, j).unwrap() = ALIVE;
+        }
+        let pieces: Vec<Vec<(i32, i32)>> = connected_components(&group_union, 1)
+            .into_iter()
+            .map(current_cells)
+            .filter(|cells| !cells.is_empty())
+            .collect();
+        // still worth checking something with period 1 for a couple of generations
+        let pieces = if pieces.len() > 1 && evolve_independently(&pieces, period.max(2)) {
+            pieces
+        } else {
+            vec![current_cells(group)]
+        };
+
+        for cells in pieces.into_iter().filter(|cells| !cells.is_empty()) {
+            let (code, kind) = classify(&cells);
+            objects.push(Object {
+                code,
+                kind,
+                bounding_box: bounding_box_of(&cells),
+                population: cells.len(),
+            });
+        }
+    }
+    Census { objects }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    fn assert_named(rows: &[&str], expected_name: &str) {
+        let (code, _) = classify(&cells(rows));
+        assert_eq!(name(&code), Some(expected_name), "got {}", code);
+    }
+
+    #[test]
+    fn test_compress_zeros() {
+        assert_eq!(compress_zeros("1001"), "1w1");
+        assert_eq!(compress_zeros("10001"), "1x1");
+        assert_eq!(compress_zeros("100001"), "1y01");
+        assert_eq!(compress_zeros(&format!("1{}1", "0".repeat(39))), "1yz1");
+        assert_eq!(compress_zeros(&format!("1{}1", "0".repeat(41))), "1yzw1");
+    }
+
+    #[test]
+    fn test_classify_still_lifes() {
+        assert_named(&["OO", "OO"], "block");
+        assert_named(&[".O.", "O.O", ".O."], "tub");
+        assert_named(&["OO.", "O.O", ".O."], "boat");
+        assert_named(&[".OO.", "O..O", ".OO."], "beehive");
+        assert_named(&["OO.", "O.O", ".OO"], "ship");
+        assert_named(&[".O..", "O.O.", ".O.O", "..O."], "barge");
+        assert_named(&["OO..", "O..O", "..OO"], "aircraft carrier");
+        assert_named(&[".OO.", "O..O", ".O.O", "..O."], "loaf");
+        assert_named(&[".O..", "O.O.", ".O.O", "..OO"], "long boat");
+        assert_named(&["OO..", "O.O.", "..O.", "..OO"], "eater 1");
+        assert_named(&[".OO.", "O..O", "O..O", ".OO."], "pond");
+    }
+
+    #[test]
+    fn test_classify_oscillators() {
+        assert_named(&["OOO"], "blinker");
+        assert_named(&[".OOO", "OOO."], "toad");
+        assert_named(&["OO..", "OO..", "..OO", "..OO"], "beacon");
+        assert_named(&["..O....O..", "OO.OOOO.OO", "..O....O.."], "pentadecathlon");
+        assert_named(
+            &[
+                "..OOO...OOO..",
+                ".............",
+                "O....O.O....O",
+                "O....O.O....O",
+                "O....O.O....O",
+                "..OOO...OOO..",
+                ".............",
+                "..OOO...OOO..",
+                "O....O.O....O",
+                "O....O.O....O",
+                "O....O.O....O",
+                ".............",
+                "..OOO...OOO..",
+            ],
+            "pulsar",
+        );
+        let (_, kind) = classify(&cells(&["OOO"]));
+        assert_eq!(kind, ObjectKind::Oscillator { period: 2 });
+    }
+
+    #[test]
+    fn test_classify_spaceships() {
+        assert_named(&["OOO", "O..", ".O."], "glider");
+        assert_named(&[".O..O", "O....", "O...O", "OOOO."], "lightweight spaceship");
+        assert_named(
+            &["...O..", ".O...O", "O.....", "O....O", "OOOOO."],
+            "middleweight spaceship",
+        );
+        assert_named(
+            &["...OO..", ".O....O", "O......", "O.....O", "OOOOOO."],
+            "heavyweight spaceship",
+        );
+        let (code, kind) = classify(&cells(&["OOO", "O..", ".O."]));
+        assert_eq!(code, "xq4_153");
+        assert_eq!(
+            kind,
+            ObjectKind::Spaceship {
+                period: 4,
+                dx: -1,
+                dy: -1
+            }
+        );
+    }
+
+    #[test]
+    fn test_classify_same_code_in_every_orientation() {
+        let loaf = [".OO.", "O..O", ".O.O", "..O."];
+        let mut shape = BMatrixVector::new(4, 4);
+        place(&mut shape, 0, 0, &loaf);
+        for transformation in 0..8 {
+            let transformed = transform(&shape, transformation);
+            let mut transformed_cells = Vec::new();
+            for j in 0..transformed.height() {
+                for i in 0..transformed.width() {
+                    if transformed.is_alive(i, j) {
+                        transformed_cells.push((i, j));
+                    }
+                }
+            }
+            assert_eq!(classify(&transformed_cells).0, "xs7_2596");
+        }
+    }
+
+    #[test]
+    fn test_classify_unknown() {
+        // R-pentomino takes over a thousand generations to settle
+        let (code, kind) = classify(&cells(&[".OO", "OO.", ".O."]));
+        assert_eq!(kind, ObjectKind::Unknown);
+        assert_eq!(code, "zz_UNKNOWN_5");
+    }
+
+    #[test]
+    fn test_take_census_counts_objects() {
+        let mut vec = BMatrixVector::new(60, 40);
+        place(&mut vec, 2, 2, &["OO", "OO"]);
+        place(&mut vec, 10, 2, &["OO", "OO"]);
+        place(&mut vec, 20, 10, &[".OO.", "O..O", ".OO."]);
+        place(&mut vec, 40, 20, &["OOO"]);
+        place(&mut vec, 30, 30, &["OOO", "O..", ".O."]);
+        let census = take_census(&vec, 1);
+        let counts = census.counts();
+        assert_eq!(counts["xs4_33"], 2);
+        assert_eq!(counts["xs6_696"], 1);
+        assert_eq!(counts["xp2_7"], 1);
+        assert_eq!(counts["xq4_153"], 1);
+        assert_eq!(census.objects.len(), 5);
+        assert!(census.to_string().starts_with("     2 xs4_33 (block)\n"));
+    }
+
+    #[test]
+    fn test_take_census_splits_traffic_light() {
+        // T-tetromino turns into a traffic light: 4 blinkers that never touch
+        let mut vec = BMatrixVector::new(32, 32);
+        place(&mut vec, 14, 14, &[".O.", "OOO"]);
+        let mut engine = create_engine(Backend::Single);
+        let mut next = vec.clone();
+        let mut scratch = vec.clone();
+        engine.step_n(20, &vec, &mut next, &mut scratch);
+        let census = take_census(&next, 2);
+        assert_eq!(census.counts().get("xp2_7"), Some(&4));
+        assert_eq!(census.objects.len(), 4);
+    }
+
+    #[test]
+    fn test_take_census_keeps_oscillator_phases_together() {
+        // every phase of a pulsar, some of which fall apart into pieces
+        let mut vec = BMatrixVector::new(40, 40);
+        place(&mut vec, 12, 12, &PULSAR);
+        let mut engine = create_engine(Backend::Single);
+        for _ in 0..3 {
+            let census = take_census(&vec, 3);
+            assert_eq!(census.objects.len(), 1);
+            assert_eq!(name(&census.objects[0].code), Some("pulsar"));
+            let mut next = vec.clone();
+            engine.next_b_matrix(&vec, &mut next);
+            vec = next;
+        }
+    }
+}
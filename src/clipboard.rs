@@ -0,0 +1,82 @@
+// Text on the OS clipboard, so RLE can go back and forth with Golly and the
+// browser. There is no clipboard in ggez, so this goes through whatever
+// command line tool the platform has
+use super::*;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[cfg(target_os = "macos")]
+fn copy_command() -> Command {
+    Command::new("pbcopy")
+}
+#[cfg(target_os = "macos")]
+fn paste_command() -> Command {
+    Command::new("pbpaste")
+}
+
+#[cfg(target_os = "windows")]
+fn copy_command() -> Command {
+    Command::new("clip")
+}
+#[cfg(target_os = "windows")]
+fn paste_command() -> Command {
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-Command", "Get-Clipboard -Raw"]);
+    command
+}
+
+// NOTE: needs xclip on X11 and wl-clipboard on Wayland
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn copy_command() -> Command {
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        Command::new("wl-copy")
+    } else {
+        let mut command = Command::new("xclip");
+        command.args(["-selection", "clipboard", "-in"]);
+        command
+    }
+}
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn paste_command() -> Command {
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        let mut command = Command::new("wl-paste");
+        command.arg("--no-newline");
+        command
+    } else {
+        let mut command = Command::new("xclip");
+        command.args(["-selection", "clipboard", "-out"]);
+        command
+    }
+}
+
+fn failed(what: &str, status: std::process::ExitStatus) -> LifeError {
+    LifeError::Io(std::io::Error::other(format!(
+        "{} clipboard failed with {}",
+        what, status
+    )))
+}
+
+pub fn set_text(text: &str) -> LifeResult<()> {
+    let mut child = copy_command().stdin(Stdio::piped()).spawn()?;
+    // stdin has to be closed before the tool sees the end of the text
+    child
+        .stdin
+        .take()
+        .expect("stdin was piped")
+        .write_all(text.as_bytes())?;
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(failed("copying to", status))
+    }
+}
+
+pub fn get_text() -> LifeResult<String> {
+    let output = paste_command().stderr(Stdio::null()).output()?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(failed("pasting from", output.status))
+    }
+}
@@ -0,0 +1,96 @@
+use std::{error, fmt, io};
+
+// NOTE: no ggez in here so the backend can use it too. Converting into a
+// GameError is the viewer's job
+#[derive(Debug)]
+pub enum LifeError {
+    /// (i,j) fell outside of a width by height matrix
+    OutOfBounds {
+        i: i32,
+        j: i32,
+        width: i32,
+        height: i32,
+    },
+    /// offset was on the wrong side of the cell edge it is measured from
+    InvalidOffset { offset: f32, edge: f32 },
+    /// line is 1-indexed
+    Parse { line: usize, message: String },
+    Io(io::Error),
+    InvalidConfig(String),
+    /// undo needed to go back to a generation the generation history no longer has
+    NotInHistory { generation: u64 },
+}
+
+pub type LifeResult<T> = Result<T, LifeError>;
+
+impl fmt::Display for LifeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LifeError::OutOfBounds {
+                i,
+                j,
+                width,
+                height,
+            } => write!(
+                f,
+                "IndexError: ({},{}) is outside of a {} by {} matrix",
+                i, j, width, height
+            ),
+            LifeError::InvalidOffset { offset, edge } => write!(
+                f,
+                "OffsetError: offset {} is on the wrong side of cell edge {}",
+                offset, edge
+            ),
+            LifeError::Parse { line, message } => {
+                write!(f, "ParseError on line {}: {}", line, message)
+            }
+            LifeError::Io(err) => write!(f, "IOError: {}", err),
+            LifeError::InvalidConfig(message) => write!(f, "ConfigError: {}", message),
+            LifeError::NotInHistory { generation } => write!(
+                f,
+                "HistoryError: generation {} is no longer in the generation history",
+                generation
+            ),
+        }
+    }
+}
+
+impl error::Error for LifeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            LifeError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LifeError {
+    fn from(err: io::Error) -> Self {
+        LifeError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_LifeError_out_of_bounds_message_has_coordinates() {
+        let err = LifeError::OutOfBounds {
+            i: -1,
+            j: 7,
+            width: 10,
+            height: 5,
+        };
+        assert_eq!(
+            err.to_string(),
+            "IndexError: (-1,7) is outside of a 10 by 5 matrix"
+        );
+    }
+
+    #[test]
+    fn test_LifeError_from_io_keeps_source() {
+        let err: LifeError = io::Error::new(io::ErrorKind::NotFound, "no file").into();
+        assert!(error::Error::source(&err).is_some());
+    }
+}
@@ -0,0 +1,47 @@
+// Patterns and helpers the tests share. Rows are given top to bottom, 'O' is
+// alive and anything else is dead
+use super::*;
+
+pub const PULSAR: [&str; 13] = [
+    "..OOO...OOO..",
+    ".............",
+    "O....O.O....O",
+    "O....O.O....O",
+    "O....O.O....O",
+    "..OOO...OOO..",
+    ".............",
+    "..OOO...OOO..",
+    "O....O.O....O",
+    "O....O.O....O",
+    "O....O.O....O",
+    ".............",
+    "..OOO...OOO..",
+];
+
+/// Live cells of rows, relative to their top left corner
+pub fn cells(rows: &[&str]) -> Vec<(i32, i32)> {
+    let mut cells = Vec::new();
+    for (j, row) in rows.iter().enumerate() {
+        for (i, c) in row.chars().enumerate() {
+            if c == 'O' {
+                cells.push((i as i32, j as i32));
+            }
+        }
+    }
+    cells
+}
+
+/// Turns on the live cells of rows with their top left corner at (x,y),
+/// leaving everything else as it was
+pub fn place(vec: &mut BMatrixVector, x: i32, y: i32, rows: &[&str]) {
+    for (i, j) in cells(rows) {
+        *vec.at_mut(x + i, y + j).unwrap() = ALIVE;
+    }
+}
+
+/// Just big enough for rows
+pub fn from_rows(rows: &[&str]) -> BMatrixVector {
+    let mut vec = BMatrixVector::new(rows[0].len() as i32, rows.len() as i32);
+    place(&mut vec, 0, 0, rows);
+    vec
+}
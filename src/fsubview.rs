@@ -1,5 +1,6 @@
 use ggez::graphics;
 use ggez::graphics::spritebatch;
+use ggez::graphics::{BlendMode, Drawable};
 use ggez::{Context, GameResult};
 //use ggez::nalgebra as na;
 use super::*;
@@ -8,6 +9,176 @@ use nalgebra::geometry::Point2;
 #[cfg(test)]
 use mocktopus::macros::*;
 
+/// How cells are drawn, picked at startup
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    Square,
+    /// Hexagons, for rules on the hexagonal neighborhood(an H on the end of the
+    /// rule). Every row sits half a cell left of the one above, so the 6 cells
+    /// touching a hexagon are the 6 neighbors Golly's emulation gives it
+    Hexagonal,
+}
+
+// distance between the middles of two cells side by side
+const PITCH: f32 = CELL_SIZE + CELL_GAP;
+
+impl Layout {
+    /// Distance between the tops of two rows. Hexagons fit in between the ones
+    /// above, so their rows are closer together
+    pub fn row_height(self) -> f32 {
+        match self {
+            Layout::Square => PITCH,
+            Layout::Hexagonal => PITCH * 3f32.sqrt() / 2.0,
+        }
+    }
+
+    /// How far right of where it would be on a square grid row j gets drawn,
+    /// when the top of the window is at offset_y. Rows keep still relative to
+    /// the top of the window, so scrolling down slides everything up and to
+    /// the right, along the rows' own diagonal
+    pub fn row_shift(self, j: f32, offset_y: f32) -> f32 {
+        match self {
+            Layout::Square => 0.0,
+            Layout::Hexagonal => -(j - offset_y / self.row_height()) * PITCH / 2.0,
+        }
+    }
+
+    // sprites a window of this size needs, across and down
+    fn sections(self, screen: Screen) -> (i32, i32) {
+        match self {
+            Layout::Square => (get_1d_section(screen.width), get_1d_section(screen.height)),
+            Layout::Hexagonal => {
+                // a row and a column either side for the partly shown ones, and
+                // as many more columns as the rows on screen push each other left
+                let rows = (screen.height / self.row_height()).ceil() as i32 + 3;
+                let columns = (screen.width / PITCH).ceil() as i32 + 3 + (rows + 1) / 2;
+                (columns, rows)
+            }
+        }
+    }
+
+    // where the sprite for the cell relative_i, relative_j cells from the top
+    // left of the window goes, relative to that top left cell
+    fn sprite_at(self, relative_i: i32, relative_j: i32) -> DrawParam {
+        match self {
+            Layout::Square => new_cell(relative_i, relative_j),
+            Layout::Hexagonal => DrawParam::default().dest(Point2::new(
+                relative_i as f32 * PITCH - relative_j as f32 * PITCH / 2.0,
+                relative_j as f32 * self.row_height(),
+            )),
+        }
+    }
+
+    /// Middle of the cell (i, j) on screen
+    pub fn screen_center_of(self, offset: Point, i: i32, j: i32) -> (f32, f32) {
+        let top = j as f32 * self.row_height() - offset.y;
+        let middle = match self {
+            Layout::Square => CELL_SIZE / 2.0,
+            Layout::Hexagonal => hexagon_height().ceil() / 2.0,
+        };
+        (
+            i as f32 * PITCH + CELL_SIZE / 2.0 + self.row_shift(j as f32, offset.y) - offset.x,
+            top + middle,
+        )
+    }
+
+    /// Cell under the screen position (x,y), e.g. the mouse
+    pub fn cell_at_screen(self, offset: Point, x: f32, y: f32) -> (i32, i32) {
+        match self {
+            Layout::Square => user::cell_at_screen(offset, x, y),
+            // the hexagon with the closest middle, out of the rows it could be on
+            Layout::Hexagonal => {
+                let row = ((offset.y + y - hexagon_height() / 2.0) / self.row_height()).round() as i32;
+                let distance = |(i, j): (i32, i32)| {
+                    let (center_x, center_y) = self.screen_center_of(offset, i, j);
+                    (center_x - x).powi(2) + (center_y - y).powi(2)
+                };
+                (row - 1..=row + 1)
+                    .map(|j| {
+                        let unshifted = x + offset.x - self.row_shift(j as f32, offset.y) - CELL_SIZE / 2.0;
+                        ((unshifted / PITCH).round() as i32, j)
+                    })
+                    .min_by(|&a, &b| distance(a).partial_cmp(&distance(b)).unwrap())
+                    .unwrap()
+            }
+        }
+    }
+
+    /// Offset that puts cell coordinates (not pixels) in the middle of the window
+    pub fn offset_centered_on(self, cell_x: f32, cell_y: f32, screen: Screen) -> (f32, f32) {
+        match self {
+            Layout::Square => user::offset_centered_on(cell_x, cell_y, screen),
+            Layout::Hexagonal => {
+                let y = cell_y * self.row_height() + hexagon_height().ceil() / 2.0 - screen.height / 2.0;
+                // half a window down, rows have moved left by that much
+                let shift = self.row_shift(cell_y, y);
+                (cell_x * PITCH + CELL_SIZE / 2.0 + shift - screen.width / 2.0, y)
+            }
+        }
+    }
+
+    /// Offset that puts the middle of cell (i, j) in the middle of the window.
+    /// Square cell coordinates run from the corner, hexagonal ones from the middle
+    pub fn offset_centered_on_cell(self, i: i32, j: i32, screen: Screen) -> (f32, f32) {
+        match self {
+            Layout::Square => self.offset_centered_on(i as f32 + 0.5, j as f32 + 0.5, screen),
+            Layout::Hexagonal => self.offset_centered_on(i as f32, j as f32, screen),
+        }
+    }
+
+    /// Corners of the outline around region on screen, clockwise from the top
+    /// left. A rectangle of cells is a parallelogram of hexagons
+    pub fn outline_of(self, offset: Point, region: BoundingBox) -> [Point2<f32>; 4] {
+        match self {
+            Layout::Square => {
+                let (x, y, width, height) = user::screen_rect_of(offset, region);
+                [
+                    Point2::new(x, y),
+                    Point2::new(x + width, y),
+                    Point2::new(x + width, y + height),
+                    Point2::new(x, y + height),
+                ]
+            }
+            Layout::Hexagonal => {
+                let (right, bottom) = (region.x + region.width - 1, region.y + region.height - 1);
+                let corner = |i: i32, j: i32, left: bool, top: bool| {
+                    let (x, y) = self.screen_center_of(offset, i, j);
+                    let (dx, dy) = (PITCH / 2.0, (hexagon_height() + CELL_GAP) / 2.0);
+                    Point2::new(if left { x - dx } else { x + dx }, if top { y - dy } else { y + dy })
+                };
+                [
+                    corner(region.x, region.y, true, true),
+                    corner(right, region.y, false, true),
+                    corner(right, bottom, false, false),
+                    corner(region.x, bottom, true, false),
+                ]
+            }
+        }
+    }
+}
+
+// hexagons are CELL_SIZE across their flat sides, with a point up and down
+fn hexagon_height() -> f32 {
+    CELL_SIZE * 2.0 / 3f32.sqrt()
+}
+
+// one hexagon in color, see-through around it
+fn hexagon_image(ctx: &mut Context, color: (u8, u8, u8)) -> GameResult<Image> {
+    let (width, height) = (CELL_SIZE as u16, hexagon_height().ceil() as u16);
+    let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height {
+        for x in 0..width {
+            // from the middle of the pixel to the middle of the hexagon
+            let dx = (x as f32 + 0.5 - width as f32 / 2.0).abs();
+            let dy = (y as f32 + 0.5 - height as f32 / 2.0).abs();
+            let inside = dy <= hexagon_height() / 2.0 - dx * hexagon_height() / (2.0 * CELL_SIZE);
+            let alpha = if inside { 255 } else { 0 };
+            rgba.extend_from_slice(&[color.0, color.1, color.2, alpha]);
+        }
+    }
+    Image::from_rgba8(ctx, width, height, &rgba)
+}
+
 struct SpriteBatchHandler {
     spritebatch: spritebatch::SpriteBatch,
     // we needs this vec b/c SpriteIdx wraps around a private field
@@ -16,12 +187,18 @@ struct SpriteBatchHandler {
     handle_list: Vec<spritebatch::SpriteIdx>,
     sw_horizontal_sections: i32,
     sw_vertical_sections: i32,
+    layout: Layout,
 }
 
 /// responsible for drawing onto the canvas
 pub struct FSubview {
-    black_sb_handler: SpriteBatchHandler,
-    white_sb_handler: SpriteBatchHandler,
+    // one per cell state, in the rule's colors
+    sb_handlers: Vec<SpriteBatchHandler>,
+    colors: Vec<(u8, u8, u8)>,
+    layout: Layout,
+    screen: Screen,
+    // which handler has each cell of the window in the right place, None for nobody yet
+    shown_states: Vec<Option<CellState>>,
     // NOTE: relative_offset should be positive -> draw will take care of negative
     relative_offset: Point,
 
@@ -33,53 +210,84 @@ pub struct FSubview {
 fn get_section_given_offset(offset: f32) -> i32 {
     (offset / (CELL_SIZE + CELL_GAP)).ceil() as i32
 }
-fn get_1d_section(length: usize) -> i32 {
-    let one_shift_offset = CELL_SIZE + CELL_GAP + length as f32;
+fn get_1d_section(length: f32) -> i32 {
+    let one_shift_offset = CELL_SIZE + CELL_GAP + length;
     get_section_given_offset(one_shift_offset)
 }
 
 impl FSubview {
     pub fn new(ctx: &mut Context) -> GameResult<FSubview> {
-        let sw_horizontal_sections = get_1d_section(WINDOW_WIDTH);
-        let sw_vertical_sections = get_1d_section(WINDOW_HEIGHT);
-
-        // create both handles with invalid locations for all sprites
-        let white_image = Image::solid(ctx, CELL_SIZE as u16, WHITE!()).unwrap();
-        let white_sb_handler = fsubview::SpriteBatchHandler::new(
-            white_image,
-            sw_horizontal_sections,
-            sw_vertical_sections,
-        );
-
-        let black_image = Image::solid(ctx, CELL_SIZE as u16, BLACK!()).unwrap();
-        let black_sb_handler = fsubview::SpriteBatchHandler::new(
-            black_image,
-            sw_horizontal_sections,
-            sw_vertical_sections,
-        );
-
-        Ok(FSubview {
-            black_sb_handler,
-            white_sb_handler,
+        let screen = Screen::default();
+        let (sw_horizontal_sections, sw_vertical_sections) = Layout::Square.sections(screen);
+
+        let mut f_subview = FSubview {
+            sb_handlers: Vec::new(),
+            colors: Vec::new(),
+            layout: Layout::Square,
+            screen,
+            shown_states: Vec::new(),
             relative_offset: Point { x: 0.0, y: 0.0 },
             sw_horizontal_sections,
             sw_vertical_sections,
-        })
+        };
+        f_subview.set_colors(ctx, &default_colors(2))?;
+        Ok(f_subview)
+    }
+    /// A color for each state the rule has, dead first. Everything shown so
+    /// far is gone until the cells get added to the view again
+    pub fn set_colors(&mut self, ctx: &mut Context, colors: &[(u8, u8, u8)]) -> GameResult {
+        // create the handles with invalid locations for all sprites
+        let mut sb_handlers = Vec::with_capacity(colors.len());
+        for &color in colors.iter() {
+            let image = match self.layout {
+                Layout::Square => Image::solid(ctx, CELL_SIZE as u16, color.into())?,
+                Layout::Hexagonal => hexagon_image(ctx, color)?,
+            };
+            sb_handlers.push(SpriteBatchHandler::new(
+                image,
+                self.sw_horizontal_sections,
+                self.sw_vertical_sections,
+                self.layout,
+            ));
+        }
+        self.sb_handlers = sb_handlers;
+        self.colors = colors.to_vec();
+        self.shown_states = vec![None; (self.sw_horizontal_sections * self.sw_vertical_sections) as usize];
+        Ok(())
+    }
+    /// Switches to drawing cells as squares or hexagons. Like set_colors,
+    /// everything has to be added to the view again
+    pub fn set_layout(&mut self, ctx: &mut Context, layout: Layout) -> GameResult {
+        self.layout = layout;
+        self.rebuild(ctx)
+    }
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+    /// Sizes the sprite pools to a window of this size. Like set_colors,
+    /// everything has to be added to the view again
+    pub fn resize(&mut self, ctx: &mut Context, screen: Screen) -> GameResult {
+        self.screen = screen;
+        self.rebuild(ctx)
+    }
+    pub fn screen(&self) -> Screen {
+        self.screen
+    }
+    fn rebuild(&mut self, ctx: &mut Context) -> GameResult {
+        let (sw_horizontal_sections, sw_vertical_sections) = self.layout.sections(self.screen);
+        self.sw_horizontal_sections = sw_horizontal_sections;
+        self.sw_vertical_sections = sw_vertical_sections;
+        let colors = self.colors.clone();
+        self.set_colors(ctx, &colors)
     }
     pub fn startView(&mut self) {
         //self.black_sb_handler.spritebatch.clear();
         //self.white_sb_handler.spritebatch.clear();
         //self.mesh_builder = graphics::MeshBuilder::new();
     }
-    pub fn addWhiteToView(&mut self, relative_i: i32, relative_j: i32) {
-        //self.white_sb_handler.spritebatch.add(new_cell(relative_i,relative_j));
+    pub fn addStateToView(&mut self, relative_i: i32, relative_j: i32, state: CellState) {
         //self.mesh_builder.rectangle(DrawMode::fill(),new_rect(relative_i,relative_j),WHITE!());
-        self.change_to_white(relative_i, relative_j);
-    }
-    pub fn addBlackToView(&mut self, relative_i: i32, relative_j: i32) {
-        //self.black_sb_handler.spritebatch.add(new_cell(relative_i,relative_j));
-        //self.mesh_builder.rectangle(DrawMode::fill(),new_rect(relative_i,relative_j),BLACK!());
-        self.change_to_black(relative_i, relative_j);
+        self.change_to(relative_i, relative_j, state);
     }
     pub fn endView(&mut self, ctx: &mut Context) {
         //self.mesh = self.mesh_builder.build(ctx).expect("Something went wrong during Mesh Building");
@@ -95,29 +303,52 @@ impl FSubview {
             -self.relative_offset.x,
             -self.relative_offset.y,
         ));
-        match graphics::draw(ctx, &self.black_sb_handler.spritebatch, offset_draw_param) {
-            Ok(_value) => {
-                graphics::draw(ctx, &self.white_sb_handler.spritebatch, offset_draw_param)
-            }
-            Err(x) => Err(x),
+        for sb_handler in self.sb_handlers.iter() {
+            graphics::draw(ctx, &sb_handler.spritebatch, offset_draw_param)?;
         }
+        Ok(())
         //graphics::draw(ctx,&self.mesh,offset_draw_param)
     }
 }
 
 //#[mockable]
 impl FSubview {
-    fn change_to_black(&mut self, relative_i: i32, relative_j: i32) {
-        //set corresponding black sprite to correct location given i,j
-        self.black_sb_handler.set_correct(relative_i, relative_j);
-        // set corresponding white sprite to invalid location
-        self.white_sb_handler.set_invalid(relative_i, relative_j);
+    fn change_to(&mut self, relative_i: i32, relative_j: i32, state: CellState) {
+        let idx = (relative_j * self.sw_horizontal_sections + relative_i) as usize;
+        // states past the last sprite batch are drawn like the last state
+        let state = state.min((self.sb_handlers.len() - 1) as CellState);
+        let shown = self.shown_states[idx];
+        if shown == Some(state) {
+            return;
+        }
+        // set the sprite of whatever state was there before to invalid location
+        if let Some(shown) = shown {
+            self.sb_handlers[shown as usize].set_invalid(relative_i, relative_j);
+        }
+        //set corresponding sprite to correct location given i,j
+        self.sb_handlers[state as usize].set_correct(relative_i, relative_j);
+        self.shown_states[idx] = Some(state);
     }
-    fn change_to_white(&mut self, relative_i: i32, relative_j: i32) {
-        self.white_sb_handler.set_correct(relative_i, relative_j);
-        self.black_sb_handler.set_invalid(relative_i, relative_j);
+    /// How far the window is past the top left cell of the sliding window, for
+    /// update_relative_offset
+    pub fn get_relative_offset(&self, offset: Point, left_idx: i32, top_idx: i32) -> LifeResult<(f32, f32)> {
+        let distance_to_top = match self.layout {
+            Layout::Square => get_distance_to_top(offset.y, top_idx)?,
+            Layout::Hexagonal => offset.y - top_idx as f32 * self.layout.row_height(),
+        };
+        let distance_to_left = get_distance_to_left(offset.x, left_idx)?;
+        // the top left cell's row is shifted along with every other row
+        let shift = self.layout.row_shift(top_idx as f32, offset.y);
+        Ok((distance_to_left - shift, distance_to_top))
     }
     pub fn get_horizontal_window_range(&self, x_left: f32, x_right: f32) -> (i32, i32) {
+        if self.layout == Layout::Hexagonal {
+            // rows further down are pushed left, so the window reaches right
+            // from the column at the left edge of the top row, one before it
+            // for the row just above the window which is pushed right instead
+            let left_idx = ((x_left / PITCH).floor() as i32 - 1).max(0);
+            return (left_idx, left_idx + self.sw_horizontal_sections - 1);
+        }
         let num_sections_crossed = (x_right / (CELL_SIZE + CELL_GAP)).ceil() as i32;
 
         let left_idx: i32;
@@ -135,6 +366,12 @@ impl FSubview {
     }
 
     pub fn get_vertical_window_range(&self, y_top: f32, y_bottom: f32) -> (i32, i32) {
+        if self.layout == Layout::Hexagonal {
+            // hexagons are taller than their rows, so the row above the one at
+            // the top can still poke into the window
+            let top_idx = ((y_top / self.layout.row_height()).floor() as i32 - 1).max(0);
+            return (top_idx, top_idx + self.sw_vertical_sections - 1);
+        }
         let num_sections_crossed = (y_bottom / (CELL_SIZE + CELL_GAP)).ceil() as i32;
 
         let top_idx: i32;
@@ -157,9 +394,15 @@ impl SpriteBatchHandler {
         image: Image,
         sw_horizontal_sections: i32,
         sw_vertical_sections: i32,
+        layout: Layout,
     ) -> SpriteBatchHandler {
         // 1. create the spritebatch
         let mut spritebatch = spritebatch::SpriteBatch::new(image);
+        // hexagons overlap each other's see-through corners, which Replace
+        // would paint over the neighbors with
+        if layout == Layout::Hexagonal {
+            spritebatch.set_blend_mode(Some(BlendMode::Alpha));
+        }
 
         // 2. Create the vector of handles then wrap
         let mut handle_list = Vec::new();
@@ -178,12 +421,13 @@ impl SpriteBatchHandler {
             handle_list,
             sw_horizontal_sections,
             sw_vertical_sections,
+            layout,
         }
     }
     fn set_correct(&mut self, relative_i: i32, relative_j: i32) {
         let sprite_handle = self.at(relative_i, relative_j).unwrap();
         self.spritebatch
-            .set(sprite_handle, new_cell(relative_i, relative_j))
+            .set(sprite_handle, self.layout.sprite_at(relative_i, relative_j))
             .unwrap();
     }
     fn set_invalid(&mut self, relative_i: i32, relative_j: i32) {
@@ -192,34 +436,28 @@ impl SpriteBatchHandler {
             .set(sprite_handle, new_cell(INVALID_X, INVALID_Y))
             .unwrap();
     }
+    fn out_of_bounds(&self, i: i32, j: i32) -> LifeError {
+        LifeError::OutOfBounds {
+            i,
+            j,
+            width: self.sw_horizontal_sections,
+            height: self.sw_vertical_sections,
+        }
+    }
 }
 
 impl MatrixView for SpriteBatchHandler {
     type Item = spritebatch::SpriteIdx;
-    fn at(&self, i: i32, j: i32) -> GameResult<Self::Item> {
-        if i < 0 || j < 0 {
-            Err(GameError::EventLoopError(
-                "IndexError(View Matrix.at): i and j must be nonnegative".to_string(),
-            ))
-        } else if i >= self.sw_horizontal_sections || j >= self.sw_vertical_sections {
-            Err(GameError::EventLoopError(format!(
-                "IndexError: View Matrix's i must be less than {} and j must be less than {}",
-                self.sw_horizontal_sections, self.sw_vertical_sections
-            )))
+    fn at(&self, i: i32, j: i32) -> LifeResult<Self::Item> {
+        if i < 0 || j < 0 || i >= self.sw_horizontal_sections || j >= self.sw_vertical_sections {
+            Err(self.out_of_bounds(i, j))
         } else {
             Ok(self.handle_list[(j * self.sw_horizontal_sections + i) as usize])
         }
     }
-    fn at_mut(&mut self, i: i32, j: i32) -> GameResult<&mut Self::Item> {
-        if i < 0 || j < 0 {
-            Err(GameError::EventLoopError(
-                "IndexError(View Matrix.at): i and j must be nonnegative".to_string(),
-            ))
-        } else if i >= self.sw_horizontal_sections || j >= self.sw_vertical_sections {
-            Err(GameError::EventLoopError(format!(
-                "IndexError: View Matrix's i must be less than {} and j must be less than {}",
-                self.sw_horizontal_sections, self.sw_vertical_sections
-            )))
+    fn at_mut(&mut self, i: i32, j: i32) -> LifeResult<&mut Self::Item> {
+        if i < 0 || j < 0 || i >= self.sw_horizontal_sections || j >= self.sw_vertical_sections {
+            Err(self.out_of_bounds(i, j))
         } else {
             Ok(&mut self.handle_list[(j * self.sw_horizontal_sections + i) as usize])
         }
@@ -299,7 +537,8 @@ pub fn new_cell(i: i32, j: i32) -> DrawParam {
 fn get_top_of_cell(j: i32) -> f32 {
     j as f32 * (CELL_SIZE + CELL_GAP)
 }
-pub fn get_distance_to_top(offset_y: f32, top_idx: i32) -> GameResult<f32> {
+// Top bounding cell should be above current offset
+pub fn get_distance_to_top(offset_y: f32, top_idx: i32) -> LifeResult<f32> {
     let top_of_upper_bound_cell = get_top_of_cell(top_idx);
     //println!("offset_y: {}",offset_y);
     //println!("top_of_upper_bound_cell: {}",top_of_upper_bound_cell);
@@ -307,22 +546,25 @@ pub fn get_distance_to_top(offset_y: f32, top_idx: i32) -> GameResult<f32> {
     if offset_y >= top_of_upper_bound_cell {
         Ok(offset_y - top_of_upper_bound_cell)
     } else {
-        Err(GameError::EventLoopError(
-            "Top bounding cell should be above current offset".to_string(),
-        ))
+        Err(LifeError::InvalidOffset {
+            offset: offset_y,
+            edge: top_of_upper_bound_cell,
+        })
     }
 }
 fn get_left_of_cell(i: i32) -> f32 {
     i as f32 * (CELL_SIZE + CELL_GAP)
 }
-pub fn get_distance_to_left(offset_x: f32, left_idx: i32) -> GameResult<f32> {
+// Left bounding cell should be to left of current offset
+pub fn get_distance_to_left(offset_x: f32, left_idx: i32) -> LifeResult<f32> {
     let left_of_upper_bound_cell = get_left_of_cell(left_idx);
     if offset_x >= left_of_upper_bound_cell {
         Ok(offset_x - left_of_upper_bound_cell)
     } else {
-        Err(GameError::EventLoopError(
-            "Left bounding cell should be to left of current offset".to_string(),
-        ))
+        Err(LifeError::InvalidOffset {
+            offset: offset_x,
+            edge: left_of_upper_bound_cell,
+        })
     }
 }
 
@@ -415,6 +657,17 @@ mod tests {
         );
     }
     #[test]
+    fn test_get_distance_to_top_below_offset() {
+        let offset_y = CELL_SIZE / 2.0;
+        match fsubview::get_distance_to_top(offset_y, 1) {
+            Err(LifeError::InvalidOffset { offset, edge }) => {
+                assert_approx_eq!(offset, offset_y, 1e-3f32);
+                assert_approx_eq!(edge, CELL_SIZE + CELL_GAP, 1e-3f32);
+            }
+            _ => panic!("expected an InvalidOffset error"),
+        }
+    }
+    #[test]
     fn test_get_distance_to_left_inside() {
         let offset_x = CELL_SIZE + CELL_GAP + CELL_SIZE / 2.0;
         // based off variable above
@@ -465,7 +718,7 @@ mod tests {
         Grid::sync_update_view.mock_safe(|my_self: &mut Grid, ctx: &mut Context| {
             // change one of the blocks to white now ->
             // Assumes SpriteBatchHandler::new sets everything to invalid at first
-            my_self.f_subview.change_to_white(20, 20);
+            my_self.f_subview.change_to(20, 20, ALIVE);
             MockResult::Return(Ok(()))
         });
 
@@ -481,7 +734,7 @@ mod tests {
         Grid::sync_update_view.mock_safe(|my_self: &mut Grid, ctx: &mut Context| {
             // change one of the blocks to black now ->
             // Assumes SpriteBatchHandler::new sets everything to invalid at first
-            my_self.f_subview.change_to_black(20, 20);
+            my_self.f_subview.change_to(20, 20, DEAD);
             MockResult::Return(Ok(()))
         });
 
@@ -492,6 +745,146 @@ mod tests {
         event::run(&mut globals.ctx, &mut globals.event_loop, &mut grid);
     }
 
+    // just the window math, no sprites
+    fn hexagonal_subview() -> FSubview {
+        let (sw_horizontal_sections, sw_vertical_sections) = Layout::Hexagonal.sections(Screen::default());
+        FSubview {
+            sb_handlers: Vec::new(),
+            colors: Vec::new(),
+            layout: Layout::Hexagonal,
+            screen: Screen::default(),
+            shown_states: Vec::new(),
+            relative_offset: Point { x: 0.0, y: 0.0 },
+            sw_horizontal_sections,
+            sw_vertical_sections,
+        }
+    }
+
+    #[test]
+    fn test_Layout_sections_grow_with_the_window() {
+        let screen = Screen::default();
+        let bigger = Screen::new(screen.width * 2.0, screen.height + CELL_SIZE + CELL_GAP);
+        for &layout in [Layout::Square, Layout::Hexagonal].iter() {
+            let (across, down) = layout.sections(screen);
+            let (bigger_across, bigger_down) = layout.sections(bigger);
+            assert!(bigger_across > across && bigger_down > down, "{:?}", layout);
+        }
+        // enough for a window this size to never show a gap
+        let (across, down) = Layout::Square.sections(bigger);
+        assert!(across as f32 * (CELL_SIZE + CELL_GAP) >= bigger.width + CELL_SIZE + CELL_GAP);
+        assert!(down as f32 * (CELL_SIZE + CELL_GAP) >= bigger.height + CELL_SIZE + CELL_GAP);
+    }
+
+    #[test]
+    fn test_Layout_hexagons_touch_their_neighbors() {
+        // the 6 neighbors the rules use are the ones right next to it on
+        // screen, and the 2 corners the emulation leaves out are not
+        let hexagonal = Layout::Hexagonal;
+        let offset = Point::new(300.0, 200.0);
+        let (x, y) = hexagonal.screen_center_of(offset, 40, 30);
+        let distance = |(di, dj): (i32, i32)| {
+            let (neighbor_x, neighbor_y) = hexagonal.screen_center_of(offset, 40 + di, 30 + dj);
+            ((neighbor_x - x).powi(2) + (neighbor_y - y).powi(2)).sqrt()
+        };
+        for &neighbor in Neighborhood::Hexagonal.offsets().iter() {
+            assert_approx_eq!(distance(neighbor), CELL_SIZE + CELL_GAP, 1e-3f32);
+        }
+        for &corner in [(1, -1), (-1, 1)].iter() {
+            assert!(distance(corner) > 1.5 * (CELL_SIZE + CELL_GAP));
+        }
+    }
+
+    #[test]
+    fn test_Layout_cell_at_screen_hexagonal() {
+        let hexagonal = Layout::Hexagonal;
+        for &(offset_x, offset_y) in [(0.0, 0.0), (1234.5, 678.9), (50.0, 3000.0)].iter() {
+            let offset = Point::new(offset_x, offset_y);
+            for j in 30..36 {
+                for i in 60..64 {
+                    let (x, y) = hexagonal.screen_center_of(offset, i, j);
+                    // anywhere inside the hexagon, not just its middle
+                    let inside = [(0.0, 0.0), (CELL_SIZE * 0.45, 0.0), (0.0, -CELL_SIZE * 0.55), (-3.0, 4.0)];
+                    for &(dx, dy) in inside.iter() {
+                        assert_eq!(hexagonal.cell_at_screen(offset, x + dx, y + dy), (i, j));
+                    }
+                }
+            }
+        }
+        // square cells go through user::cell_at_screen
+        let offset = Point::new(10.0, 10.0);
+        assert_eq!(
+            Layout::Square.cell_at_screen(offset, 50.0, 50.0),
+            user::cell_at_screen(offset, 50.0, 50.0)
+        );
+    }
+
+    #[test]
+    fn test_Layout_offset_centered_on_hexagonal() {
+        let (x, y) = Layout::Hexagonal.offset_centered_on(500.0, 400.0, Screen::default());
+        let middle = (WINDOW_WIDTH as f32 / 2.0, WINDOW_HEIGHT as f32 / 2.0);
+        let cell = Layout::Hexagonal.cell_at_screen(Point::new(x, y), middle.0, middle.1);
+        assert_eq!(cell, (500, 400));
+    }
+
+    #[test]
+    fn test_Layout_offset_centered_on_cell() {
+        // what a bookmark saves is whatever is in the middle, so going back
+        // to it has to put that in the middle again
+        let screen = Screen::new(1001.0, 777.0);
+        let middle = (screen.width / 2.0, screen.height / 2.0);
+        for &layout in [Layout::Square, Layout::Hexagonal].iter() {
+            for &cell in [(500, 400), (123, 4567), (3001, 170)].iter() {
+                let (x, y) = layout.offset_centered_on_cell(cell.0, cell.1, screen);
+                assert_eq!(layout.cell_at_screen(Point::new(x, y), middle.0, middle.1), cell, "{:?}", layout);
+            }
+        }
+    }
+
+    #[test]
+    fn test_hexagonal_window_covers_the_screen() {
+        let f_subview = hexagonal_subview();
+        for &(offset_x, offset_y) in [(0.0, 0.0), (777.7, 55.5), (4000.0, 9000.0)].iter() {
+            let offset = Point::new(offset_x, offset_y);
+            let (left_idx, right_idx) =
+                f_subview.get_horizontal_window_range(offset.x, offset.x + WINDOW_WIDTH as f32);
+            let (top_idx, bottom_idx) =
+                f_subview.get_vertical_window_range(offset.y, offset.y + WINDOW_HEIGHT as f32);
+            assert_eq!(right_idx - left_idx + 1, f_subview.sw_horizontal_sections);
+            assert_eq!(bottom_idx - top_idx + 1, f_subview.sw_vertical_sections);
+            let (rel_x, rel_y) = f_subview.get_relative_offset(offset, left_idx, top_idx).unwrap();
+            let top_of_hexagon = hexagon_height().ceil() / 2.0;
+            // every bit of the screen has its cell in the window, drawn where
+            // the mouse would find it
+            for y in (0..=WINDOW_HEIGHT).step_by(7) {
+                for x in (0..=WINDOW_WIDTH).step_by(7) {
+                    let (i, j) = Layout::Hexagonal.cell_at_screen(offset, x as f32, y as f32);
+                    if i < 0 || j < 0 {
+                        continue;
+                    }
+                    assert!((left_idx..=right_idx).contains(&i) && (top_idx..=bottom_idx).contains(&j));
+                    let sprite = Layout::Hexagonal.sprite_at(i - left_idx, j - top_idx).dest;
+                    let (center_x, center_y) = Layout::Hexagonal.screen_center_of(offset, i, j);
+                    assert_approx_eq!(sprite.x - rel_x + CELL_SIZE / 2.0, center_x, 1e-2f32);
+                    assert_approx_eq!(sprite.y - rel_y + top_of_hexagon, center_y, 1e-2f32);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_Layout_outline_of_square_is_screen_rect_of() {
+        let offset = Point::new(100.0, 50.0);
+        let region = BoundingBox::spanning((4, 2), (6, 5));
+        let (x, y, width, height) = user::screen_rect_of(offset, region);
+        let corners = Layout::Square.outline_of(offset, region);
+        assert_eq!((corners[0].x, corners[0].y), (x, y));
+        assert_eq!((corners[2].x, corners[2].y), (x + width, y + height));
+        // hexagons lean left going down, the same as their rows
+        let hexagonal = Layout::Hexagonal.outline_of(offset, region);
+        assert!(hexagonal[3].x < hexagonal[0].x && hexagonal[2].x < hexagonal[1].x);
+        assert_approx_eq!(hexagonal[0].x - hexagonal[3].x, 3.0 * (CELL_SIZE + CELL_GAP) / 2.0, 1e-3f32);
+    }
+
     #[should_panic]
     #[test]
     fn test_SpriteBatchHandler_at_outOfBounds() {
@@ -503,7 +896,7 @@ mod tests {
         // columns first
         let sw_horizontal_sections = grid.f_subview.sw_horizontal_sections;
         grid.f_subview
-            .white_sb_handler
+            .sb_handlers[ALIVE as usize]
             .at(sw_horizontal_sections, 1)
             .unwrap();
     }
@@ -518,7 +911,7 @@ mod tests {
         let sw_horizontal_sections = grid.f_subview.sw_horizontal_sections;
         let sw_vertical_sections = grid.f_subview.sw_vertical_sections;
         grid.f_subview
-            .white_sb_handler
+            .sb_handlers[ALIVE as usize]
             .at(sw_horizontal_sections - 1, sw_vertical_sections - 1)
             .unwrap();
     }
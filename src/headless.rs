@@ -0,0 +1,371 @@
+// Runs a soup without opening a window and prints what happened to it:
+//     cargo run -- --headless --seed 1234 --density 0.4 --symmetry D8
+// The census and spaceship tracking only know Life objects, so they are
+// best left alone under any other --rule
+use super::*;
+use crate::census::{take_census, Census};
+use crate::patterns::{PatternBuilder, SoupInfo, Symmetry};
+use crate::tracker::{SpaceshipTracker, Track};
+use std::io::Write;
+use std::mem;
+
+pub struct HeadlessArgs {
+    pub width: i32,
+    pub height: i32,
+    pub soup_width: i32,
+    pub soup_height: i32,
+    // None picks a random one, which still gets printed
+    pub seed: Option<u64>,
+    pub density: f64,
+    pub symmetry: Symmetry,
+    pub backend: Backend,
+    pub rule: Arc<dyn Rule>,
+    pub generations: u64,
+    pub cycle_history: usize,
+    // print the population every this many generations, 0 for never
+    pub report_every: u64,
+    // follow spaceships and print what happens to them as it happens
+    pub track: bool,
+}
+
+impl Default for HeadlessArgs {
+    // 16x16 soups in a universe big enough that most of them never reach the edge
+    fn default() -> Self {
+        HeadlessArgs {
+            width: 256,
+            height: 256,
+            soup_width: 16,
+            soup_height: 16,
+            seed: None,
+            density: 0.5,
+            symmetry: Symmetry::C1,
+            backend: Backend::Rayon,
+            rule: Arc::new(Generations::life()),
+            generations: 10000,
+            cycle_history: 64,
+            report_every: 1000,
+            track: false,
+        }
+    }
+}
+
+fn invalid(message: String) -> LifeError {
+    LifeError::InvalidConfig(message)
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> LifeResult<T> {
+    let value = value.ok_or_else(|| invalid(format!("{} needs a value", flag)))?;
+    value
+        .parse()
+        .map_err(|_| invalid(format!("bad value for {}: {}", flag, value)))
+}
+
+// "WxH"
+fn parse_size(flag: &str, value: Option<String>) -> LifeResult<(i32, i32)> {
+    let value: String = parse_value(flag, value)?;
+    let mut parts = value.split('x');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(width), Some(height), None) => match (width.parse(), height.parse()) {
+            (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok((width, height)),
+            _ => Err(invalid(format!("bad size for {}: {}", flag, value))),
+        },
+        _ => Err(invalid(format!("{} expects WIDTHxHEIGHT, got {}", flag, value))),
+    }
+}
+
+// single, rayon or threads:N. Not skip, which never steps, so every report
+// would just be the seed
+fn parse_backend(value: Option<String>) -> LifeResult<Backend> {
+    let value: String = parse_value("--backend", value)?;
+    match value.as_str() {
+        "single" => Ok(Backend::Single),
+        "rayon" => Ok(Backend::Rayon),
+        "skip" => Err(invalid("skip never steps the universe, so there would be nothing to report".to_string())),
+        _ if value.starts_with("threads:") => {
+            let worker_count = parse_value("--backend threads:", Some(value[8..].to_string()))?;
+            Ok(Backend::MultiThreaded(worker_count))
+        }
+        _ => Err(invalid(format!("unknown backend {}", value))),
+    }
+}
+
+impl HeadlessArgs {
+    /// None unless --headless was passed, so the viewer runs as usual
+    pub fn from_args<I: Iterator<Item = String>>(args: I) -> LifeResult<Option<Self>> {
+        let mut args = args.peekable();
+        let mut headless = false;
+        let mut parsed = HeadlessArgs::default();
+        while let Some(flag) = args.next() {
+            match flag.as_str() {
+                "--headless" => headless = true,
+                "--track" => parsed.track = true,
+                "--size" => {
+                    let (width, height) = parse_size("--size", args.next())?;
+                    parsed.width = width;
+                    parsed.height = height;
+                }
+                "--soup" => {
+                    let (width, height) = parse_size("--soup", args.next())?;
+                    parsed.soup_width = width;
+                    parsed.soup_height = height;
+                }
+                "--seed" => parsed.seed = Some(parse_value("--seed", args.next())?),
+                "--density" => parsed.density = parse_value("--density", args.next())?,
+                "--symmetry" => parsed.symmetry = parse_value("--symmetry", args.next())?,
+                "--backend" => parsed.backend = parse_backend(args.next())?,
+                "--rule" => parsed.rule = parse_rule(&parse_value::<String>("--rule", args.next())?)?,
+                "--generations" => parsed.generations = parse_value("--generations", args.next())?,
+                "--cycle-history" => parsed.cycle_history = parse_value("--cycle-history", args.next())?,
+                "--report-every" => parsed.report_every = parse_value("--report-every", args.next())?,
+                _ => return Err(invalid(format!("unknown argument {}", flag))),
+            }
+        }
+        if !headless {
+            return Ok(None);
+        }
+        if parsed.soup_width > parsed.width || parsed.soup_height > parsed.height {
+            return Err(invalid("soup does not fit in the universe".to_string()));
+        }
+        if !(0.0..=1.0).contains(&parsed.density) {
+            return Err(invalid(format!("density {} is not between 0 and 1", parsed.density)));
+        }
+        Ok(Some(parsed))
+    }
+}
+
+/// How the run ended
+#[derive(Debug, PartialEq)]
+pub struct Report {
+    pub soup: SoupInfo,
+    pub generation: u64,
+    pub population: usize,
+    pub cycle: Option<Cycle>,
+    pub census: Census,
+    // still flying at the end, empty unless tracking
+    pub spaceships: Vec<Track>,
+}
+
+// Stops as soon as the soup turns periodic, nothing new happens after that
+pub fn run<W: Write>(args: &HeadlessArgs, out: &mut W) -> LifeResult<Report> {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let start_point = (
+        (args.width - args.soup_width) / 2,
+        (args.height - args.soup_height) / 2,
+    );
+    let (mut old_vec, soups) = PatternBuilder::with_size(args.width, args.height)
+        .make_symmetric_soup(
+            start_point,
+            args.soup_width,
+            args.soup_height,
+            seed,
+            args.density,
+            args.symmetry,
+        )
+        .build_with_soups();
+    let soup = soups[0];
+    writeln!(out, "{}", soup)?;
+    writeln!(out, "Rule: {}", args.rule)?;
+
+    let mut engine = create_engine_with_rule(args.backend, args.rule.clone());
+    let mut detector = CycleDetector::new(args.cycle_history.max(1));
+    let mut new_vec = old_vec.clone();
+    let mut generation = 0;
+    let mut tracker = if args.track {
+        Some(SpaceshipTracker::new())
+    } else {
+        None
+    };
+    let mut cycle = detector.observe(generation, &old_vec);
+    if let Some(tracker) = tracker.as_mut() {
+        tracker.observe(generation, &old_vec);
+    }
+    while cycle.is_none() && generation < args.generations {
+        engine.next_b_matrix(&old_vec, &mut new_vec);
+        mem::swap(&mut old_vec, &mut new_vec);
+        generation += 1;
+        cycle = detector.observe(generation, &old_vec);
+        if let Some(tracker) = tracker.as_mut() {
+            for event in tracker.observe(generation, &old_vec) {
+                writeln!(out, "{}", event)?;
+            }
+        }
+        if args.report_every != 0 && generation % args.report_every == 0 {
+            writeln!(
+                out,
+                "Generation {}: population {}",
+                generation,
+                old_vec.population()
+            )?;
+        }
+    }
+
+    // objects only make sense once things have settled, but whatever is
+    // there still gets counted, the unsettled parts just come out as unknown
+    let census = take_census(&old_vec, cycle.map_or(1, |cycle| cycle.period));
+    let report = Report {
+        soup,
+        generation,
+        population: old_vec.population(),
+        cycle,
+        census,
+        spaceships: tracker.map_or(Vec::new(), |tracker| tracker.tracks().cloned().collect()),
+    };
+    match report.cycle {
+        Some(cycle) => writeln!(out, "{}", cycle)?,
+        None => writeln!(out, "No cycle found within {} generations", generation)?,
+    }
+    writeln!(
+        out,
+        "Final generation {}: population {}",
+        report.generation, report.population
+    )?;
+    writeln!(out, "Census:")?;
+    write!(out, "{}", report.census)?;
+    if args.track {
+        writeln!(out, "Spaceships still flying: {}", report.spaceships.len())?;
+        for track in report.spaceships.iter() {
+            writeln!(out, "  {}", track)?;
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> LifeResult<Option<HeadlessArgs>> {
+        HeadlessArgs::from_args(list.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_HeadlessArgs_without_flag_runs_viewer() {
+        assert!(args(&[]).unwrap().is_none());
+        assert!(args(&["--seed", "3"]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_HeadlessArgs_parses_options() {
+        let parsed = args(&[
+            "--headless",
+            "--size",
+            "64x48",
+            "--soup",
+            "8x8",
+            "--seed",
+            "99",
+            "--density",
+            "0.3",
+            "--symmetry",
+            "D8",
+            "--backend",
+            "threads:3",
+            "--rule",
+            "B2/S/C3",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!((parsed.width, parsed.height), (64, 48));
+        assert_eq!((parsed.soup_width, parsed.soup_height), (8, 8));
+        assert_eq!(parsed.seed, Some(99));
+        assert_eq!(parsed.density, 0.3);
+        assert_eq!(parsed.symmetry, Symmetry::D8);
+        assert_eq!(parsed.backend, Backend::MultiThreaded(3));
+        assert_eq!(parsed.rule.to_string(), "B2/S/C3");
+    }
+
+    #[test]
+    fn test_HeadlessArgs_rejects_bad_input() {
+        for bad in [
+            &["--headless", "--size", "64"][..],
+            &["--headless", "--density", "2"][..],
+            &["--headless", "--symmetry", "C3"][..],
+            &["--headless", "--seed"][..],
+            &["--headless", "--soup", "300x8"][..],
+            &["--headless", "--frobnicate"][..],
+            &["--headless", "--rule", "B3/S23/C1"][..],
+            &["--headless", "--backend", "skip"][..],
+        ]
+        .iter()
+        {
+            match args(bad) {
+                Err(LifeError::InvalidConfig(_)) => (),
+                _ => panic!("{:?} should not parse", bad),
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_same_seed_same_report() {
+        let parsed = args(&["--headless", "--size", "48x48", "--seed", "7", "--backend", "single"])
+            .unwrap()
+            .unwrap();
+        let first = run(&parsed, &mut Vec::new()).unwrap();
+        let second = run(&parsed, &mut Vec::new()).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.soup.seed, 7);
+    }
+
+    #[test]
+    fn test_run_census_accounts_for_every_cell() {
+        let parsed = args(&["--headless", "--size", "96x96", "--seed", "2", "--backend", "single"])
+            .unwrap()
+            .unwrap();
+        let mut out = Vec::new();
+        let report = run(&parsed, &mut out).unwrap();
+        let counted: usize = report.census.objects.iter().map(|object| object.population).sum();
+        assert_eq!(counted, report.population);
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Census:"), "{}", out);
+    }
+
+    #[test]
+    fn test_run_tracks_spaceships() {
+        // looking for a soup that throws out a glider would tie the test to the
+        // rng, so just check the tracker runs and agrees with the census
+        let parsed = args(&[
+            "--headless",
+            "--size",
+            "96x96",
+            "--seed",
+            "5",
+            "--track",
+            "--backend",
+            "single",
+            "--generations",
+            "500",
+        ])
+            .unwrap()
+            .unwrap();
+        let mut out = Vec::new();
+        let report = run(&parsed, &mut out).unwrap();
+        let gliders_in_census = report.census.counts().get("xq4_153").cloned().unwrap_or(0);
+        let gliders_tracked = report
+            .spaceships
+            .iter()
+            .filter(|track| track.code == "xq4_153")
+            .count();
+        assert_eq!(gliders_tracked, gliders_in_census);
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Spaceships still flying"), "{}", out);
+    }
+
+    #[test]
+    fn test_run_reports_cycle() {
+        // nothing alive is a still life right away
+        let parsed = args(&["--headless", "--size", "32x32", "--density", "0"])
+            .unwrap()
+            .unwrap();
+        let mut out = Vec::new();
+        let report = run(&parsed, &mut out).unwrap();
+        assert_eq!(
+            report.cycle,
+            Some(Cycle {
+                period: 1,
+                start_generation: 0
+            })
+        );
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Stable since generation 0"), "{}", out);
+    }
+}
@@ -0,0 +1,189 @@
+use super::*;
+use crate::user::CameraTarget;
+use ggez::graphics::{DrawMode, Rect, Text};
+use nalgebra::geometry::Point2;
+
+const HUD_MARGIN: f32 = 10.0;
+const HUD_PADDING: f32 = 6.0;
+// more than this and the HUD covers half the screen in a busy soup
+const HUD_MAX_SPACESHIPS: usize = 3;
+const HUD_MAX_EVENTS: usize = 3;
+
+// Text overlay in the top left corner, with whatever you need to reproduce the run
+pub struct Hud {
+    visible: bool,
+}
+
+impl Hud {
+    pub fn new() -> Self {
+        Hud { visible: true }
+    }
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+    pub fn lines(snapshot: &Snapshot) -> Vec<String> {
+        let mut lines = vec![format!("Generation: {}", snapshot.generation)];
+        for soup in snapshot.soups.iter() {
+            lines.push(soup.to_string());
+        }
+        if let Some(cycle) = snapshot.cycle {
+            lines.push(cycle.to_string());
+        }
+        if let Some(oldest) = snapshot.oldest_in_history {
+            lines.push(format!("Can step back to generation {}", oldest));
+        }
+        if !snapshot.spaceships.is_empty() {
+            lines.push(format!("Spaceships: {}", snapshot.spaceships.len()));
+            for track in snapshot.spaceships.iter().take(HUD_MAX_SPACESHIPS) {
+                lines.push(format!("  {}", track));
+            }
+        }
+        let skip = snapshot.spaceship_events.len().saturating_sub(HUD_MAX_EVENTS);
+        for event in snapshot.spaceship_events.iter().skip(skip) {
+            lines.push(event.to_string());
+        }
+        lines
+    }
+    // what the camera is following, by name if the tracker knows the ship yet
+    pub fn follow_line(target: CameraTarget, snapshot: &Snapshot) -> String {
+        match target {
+            CameraTarget::Centroid => "Following: centroid".to_string(),
+            CameraTarget::Spaceship(id) => match snapshot.spaceships.iter().find(|track| track.id == id) {
+                Some(track) => {
+                    let name = crate::census::name(&track.code).unwrap_or(&track.code);
+                    format!("Following: #{} {}", id, name)
+                }
+                None => format!("Following: #{}", id),
+            },
+        }
+    }
+    pub fn selection_line(selection: BoundingBox) -> String {
+        format!(
+            "Selection: {}x{} at ({},{})",
+            selection.width, selection.height, selection.x, selection.y
+        )
+    }
+    // lines is Hud::lines plus whatever the viewer wants to add about itself
+    pub fn draw(&self, ctx: &mut Context, lines: &[String]) -> GameResult {
+        if !self.visible {
+            return Ok(());
+        }
+        let text = Text::new(lines.join("\n"));
+        let (width, height) = text.dimensions(ctx);
+        let background = graphics::Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(
+                HUD_MARGIN - HUD_PADDING,
+                HUD_MARGIN - HUD_PADDING,
+                width as f32 + 2.0 * HUD_PADDING,
+                height as f32 + 2.0 * HUD_PADDING,
+            ),
+            BLACK!(),
+        )?;
+        graphics::draw(ctx, &background, DrawParam::default())?;
+        // glyphs need alpha blending, everything else is drawn with Replace
+        graphics::set_blend_mode(ctx, BlendMode::Alpha)?;
+        graphics::draw(
+            ctx,
+            &text,
+            DrawParam::default()
+                .dest(Point2::new(HUD_MARGIN, HUD_MARGIN))
+                .color(WHITE!()),
+        )?;
+        graphics::set_blend_mode(ctx, BlendMode::Replace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::PatternBuilder;
+    use crate::tracker::SpaceshipTracker;
+
+    #[test]
+    fn test_Hud_lines_show_generation_and_seed() {
+        let (vec, soups) = PatternBuilder::with_size(8, 8)
+            .make_random_seeded((0, 0), 8, 8, 987654321, 0.25)
+            .build_with_soups();
+        let snapshot = Snapshot {
+            vec,
+            generation: 42,
+            soups,
+            ..Snapshot::empty()
+        };
+        let lines = Hud::lines(&snapshot);
+        assert_eq!(lines[0], "Generation: 42");
+        assert_eq!(
+            lines[1],
+            "Soup: seed 987654321, density 0.25, C1, 8x8 at (0,0)"
+        );
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[test]
+    fn test_Hud_lines_show_cycle_and_history() {
+        let snapshot = Snapshot {
+            generation: 120,
+            cycle: Some(Cycle {
+                period: 2,
+                start_generation: 97,
+            }),
+            oldest_in_history: Some(64),
+            ..Snapshot::empty()
+        };
+        assert_eq!(
+            Hud::lines(&snapshot),
+            vec![
+                "Generation: 120".to_string(),
+                "Periodic with period 2 since generation 97".to_string(),
+                "Can step back to generation 64".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_Hud_lines_show_spaceships() {
+        let mut tracker = SpaceshipTracker::new();
+        let mut vec = PatternBuilder::with_size(32, 32).make_glider(20, 20).build();
+        let mut next = vec.clone();
+        let mut engine = create_engine(Backend::Single);
+        for generation in 0..8 {
+            tracker.observe(generation, &vec);
+            engine.next_b_matrix(&vec, &mut next);
+            std::mem::swap(&mut vec, &mut next);
+        }
+        let snapshot = Snapshot {
+            generation: 7,
+            spaceships: tracker.tracks().cloned().collect(),
+            spaceship_events: tracker.events().to_vec(),
+            ..Snapshot::empty()
+        };
+        assert_eq!(
+            Hud::lines(&snapshot),
+            vec![
+                "Generation: 7".to_string(),
+                "Spaceships: 1".to_string(),
+                "  #0 glider c/4 diagonal, up-left at (18,18)".to_string(),
+                "Generation 0: #0 glider emitted at (20,20)".to_string(),
+            ]
+        );
+        assert_eq!(
+            Hud::follow_line(CameraTarget::Spaceship(0), &snapshot),
+            "Following: #0 glider"
+        );
+        // not confirmed yet, or already gone
+        assert_eq!(
+            Hud::follow_line(CameraTarget::Spaceship(3), &snapshot),
+            "Following: #3"
+        );
+    }
+
+    #[test]
+    fn test_Hud_selection_line() {
+        assert_eq!(
+            Hud::selection_line(BoundingBox::spanning((12, 7), (10, 9))),
+            "Selection: 3x3 at (10,7)"
+        );
+    }
+}
@@ -0,0 +1,521 @@
+// Which keys and mouse buttons do what. The defaults are resources/keys.txt,
+// and a keys.txt in the config directory can move any of them around with
+// `action = key` lines of its own
+use super::*;
+use crate::navigation::BOOKMARK_SLOTS;
+use ggez::filesystem;
+use ggez::graphics::{DrawMode, Rect, Text};
+use nalgebra::geometry::Point2;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const DEFAULT_KEYS: &str = include_str!("../resources/keys.txt");
+const HELP_MARGIN: f32 = 10.0;
+const HELP_PADDING: f32 = 6.0;
+
+/// ggez's config directory for this game, next to the bookmarks
+pub fn keys_path(ctx: &Context) -> PathBuf {
+    filesystem::user_config_dir(ctx).join("keys.txt")
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    StepBack,
+    StepForward,
+    Pause,
+    ScrollLeft,
+    ScrollRight,
+    ScrollUp,
+    ScrollDown,
+    PageLeft,
+    PageRight,
+    PageUp,
+    PageDown,
+    Pan,
+    Select,
+    Deselect,
+    Copy,
+    Cut,
+    Paste,
+    Undo,
+    Redo,
+    Rotate,
+    FlipHorizontal,
+    FlipVertical,
+    FillRandom,
+    Clear,
+    ToggleHud,
+    ToggleHelp,
+    CycleShading,
+    CycleTheme,
+    ToggleLibrary,
+    Stamp,
+    FollowSpaceship,
+    FollowCentroid,
+    GoTo,
+    NearestAlive,
+    AllLiveCells,
+    Bookmark(usize),
+    SaveBookmark(usize),
+}
+
+// every action but the bookmarks, by its name in keys.txt and what the help
+// calls it, in the order the help lists them
+const ACTIONS: &[(Action, &str, &str)] = &[
+    (Action::Quit, "quit", "Quit"),
+    (Action::StepBack, "step_back", "Back a generation"),
+    (Action::StepForward, "step_forward", "Forward a generation"),
+    (Action::Pause, "pause", "Pause"),
+    (Action::ScrollLeft, "scroll_left", "Scroll left"),
+    (Action::ScrollRight, "scroll_right", "Scroll right"),
+    (Action::ScrollUp, "scroll_up", "Scroll up"),
+    (Action::ScrollDown, "scroll_down", "Scroll down"),
+    (Action::PageLeft, "page_left", "A window left"),
+    (Action::PageRight, "page_right", "A window right"),
+    (Action::PageUp, "page_up", "A window up"),
+    (Action::PageDown, "page_down", "A window down"),
+    (Action::Pan, "pan", "Drag the grid around"),
+    (Action::Select, "select", "Select"),
+    (Action::Deselect, "deselect", "Drop the selection"),
+    (Action::Copy, "copy", "Copy"),
+    (Action::Cut, "cut", "Cut"),
+    (Action::Paste, "paste", "Paste"),
+    (Action::Undo, "undo", "Undo"),
+    (Action::Redo, "redo", "Redo"),
+    (Action::Rotate, "rotate", "Rotate the selection, or the library's pattern"),
+    (Action::FlipHorizontal, "flip_horizontal", "Flip it left to right"),
+    (Action::FlipVertical, "flip_vertical", "Flip it upside down"),
+    (Action::FillRandom, "fill_random", "Fill it with a soup"),
+    (Action::Clear, "clear", "Clear it"),
+    (Action::ToggleHud, "toggle_hud", "Show or hide the HUD"),
+    (Action::ToggleHelp, "toggle_help", "Show or hide this"),
+    (Action::CycleShading, "cycle_shading", "Next shading"),
+    (Action::CycleTheme, "cycle_theme", "Next theme"),
+    (Action::ToggleLibrary, "toggle_library", "Open or close the library"),
+    (Action::Stamp, "stamp", "Stamp the library's pattern"),
+    (Action::FollowSpaceship, "follow_spaceship", "Follow the next spaceship"),
+    (Action::FollowCentroid, "follow_centroid", "Follow the live cells"),
+    (Action::GoTo, "go_to", "Go to a cell"),
+    (Action::NearestAlive, "nearest_alive", "Nearest live cell"),
+    (Action::AllLiveCells, "all_live_cells", "Middle of the live cells"),
+];
+
+impl Action {
+    /// Held down, these keep happening
+    pub fn repeats(self) -> bool {
+        matches!(
+            self,
+            Action::StepBack
+                | Action::StepForward
+                | Action::ScrollUp
+                | Action::ScrollDown
+                | Action::PageLeft
+                | Action::PageRight
+                | Action::PageUp
+                | Action::PageDown
+        )
+    }
+}
+
+impl FromStr for Action {
+    type Err = ();
+    fn from_str(name: &str) -> Result<Self, ()> {
+        if let Some(&(action, _, _)) = ACTIONS.iter().find(|&&(_, action_name, _)| action_name == name) {
+            return Ok(action);
+        }
+        let slot = |prefix: &str| {
+            name.strip_prefix(prefix)
+                .and_then(|slot| slot.parse::<usize>().ok())
+                .filter(|&slot| slot < BOOKMARK_SLOTS)
+        };
+        match (slot("bookmark_"), slot("save_bookmark_")) {
+            (Some(slot), _) => Ok(Action::Bookmark(slot)),
+            (_, Some(slot)) => Ok(Action::SaveBookmark(slot)),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Input {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+// names in keys.txt, which are what ggez calls them, bar the digits
+const KEY_NAMES: &[(&str, KeyCode)] = &[
+    ("A", KeyCode::A),
+    ("B", KeyCode::B),
+    ("C", KeyCode::C),
+    ("D", KeyCode::D),
+    ("E", KeyCode::E),
+    ("F", KeyCode::F),
+    ("G", KeyCode::G),
+    ("H", KeyCode::H),
+    ("I", KeyCode::I),
+    ("J", KeyCode::J),
+    ("K", KeyCode::K),
+    ("L", KeyCode::L),
+    ("M", KeyCode::M),
+    ("N", KeyCode::N),
+    ("O", KeyCode::O),
+    ("P", KeyCode::P),
+    ("Q", KeyCode::Q),
+    ("R", KeyCode::R),
+    ("S", KeyCode::S),
+    ("T", KeyCode::T),
+    ("U", KeyCode::U),
+    ("V", KeyCode::V),
+    ("W", KeyCode::W),
+    ("X", KeyCode::X),
+    ("Y", KeyCode::Y),
+    ("Z", KeyCode::Z),
+    ("0", KeyCode::Key0),
+    ("1", KeyCode::Key1),
+    ("2", KeyCode::Key2),
+    ("3", KeyCode::Key3),
+    ("4", KeyCode::Key4),
+    ("5", KeyCode::Key5),
+    ("6", KeyCode::Key6),
+    ("7", KeyCode::Key7),
+    ("8", KeyCode::Key8),
+    ("9", KeyCode::Key9),
+    ("F1", KeyCode::F1),
+    ("F2", KeyCode::F2),
+    ("F3", KeyCode::F3),
+    ("F4", KeyCode::F4),
+    ("F5", KeyCode::F5),
+    ("F6", KeyCode::F6),
+    ("F7", KeyCode::F7),
+    ("F8", KeyCode::F8),
+    ("F9", KeyCode::F9),
+    ("F10", KeyCode::F10),
+    ("F11", KeyCode::F11),
+    ("F12", KeyCode::F12),
+    ("Escape", KeyCode::Escape),
+    ("Space", KeyCode::Space),
+    ("Return", KeyCode::Return),
+    ("Back", KeyCode::Back),
+    ("Tab", KeyCode::Tab),
+    ("Insert", KeyCode::Insert),
+    ("Delete", KeyCode::Delete),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Comma", KeyCode::Comma),
+    ("Period", KeyCode::Period),
+    ("Slash", KeyCode::Slash),
+    ("Minus", KeyCode::Minus),
+    ("Equals", KeyCode::Equals),
+];
+
+const MOUSE_NAMES: &[(&str, MouseButton)] = &[
+    ("MouseLeft", MouseButton::Left),
+    ("MouseRight", MouseButton::Right),
+    ("MouseMiddle", MouseButton::Middle),
+];
+
+/// A key or mouse button, with the modifiers that have to be held with it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Binding {
+    input: Input,
+    ctrl: bool,
+    shift: bool,
+}
+
+impl Binding {
+    fn matches(self, input: Input, keymods: keyboard::KeyMods) -> bool {
+        self.input == input
+            && self.ctrl == keymods.contains(keyboard::KeyMods::CTRL)
+            && self.shift == keymods.contains(keyboard::KeyMods::SHIFT)
+    }
+}
+
+/// e.g. Ctrl+Shift+J, the way keys.txt writes them
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        let name = match self.input {
+            Input::Key(keycode) => KEY_NAMES.iter().find(|&&(_, key)| key == keycode).map(|&(name, _)| name),
+            Input::Mouse(button) => MOUSE_NAMES.iter().find(|&&(_, mouse)| mouse == button).map(|&(name, _)| name),
+        };
+        write!(f, "{}", name.unwrap_or("?"))
+    }
+}
+
+/// Key names go either case, modifiers too
+impl FromStr for Binding {
+    type Err = String;
+    fn from_str(text: &str) -> Result<Self, String> {
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let name = parts.pop().unwrap_or("");
+        let (mut ctrl, mut shift) = (false, false);
+        for modifier in parts {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" => ctrl = true,
+                "shift" => shift = true,
+                _ => return Err(format!("{:?} is not Ctrl or Shift", modifier)),
+            }
+        }
+        let key = KEY_NAMES
+            .iter()
+            .find(|&&(key_name, _)| key_name.eq_ignore_ascii_case(name))
+            .map(|&(_, keycode)| Input::Key(keycode));
+        let mouse = MOUSE_NAMES
+            .iter()
+            .find(|&&(mouse_name, _)| mouse_name.eq_ignore_ascii_case(name))
+            .map(|&(_, button)| Input::Mouse(button));
+        let input = key.or(mouse).ok_or_else(|| format!("there is no key called {:?}", name))?;
+        Ok(Binding { input, ctrl, shift })
+    }
+}
+
+pub struct KeyBindings {
+    bindings: Vec<(Binding, Action)>,
+}
+
+impl KeyBindings {
+    pub fn defaults() -> KeyBindings {
+        // the tests make sure it parses
+        KeyBindings::parse_over(KeyBindings { bindings: Vec::new() }, DEFAULT_KEYS)
+            .expect("bundled keys.txt does not parse")
+    }
+
+    /// The defaults, with every action the text has lines for moved to the
+    /// keys it gives
+    pub fn parse(text: &str) -> LifeResult<KeyBindings> {
+        KeyBindings::parse_over(KeyBindings::defaults(), text)
+    }
+
+    fn parse_over(mut base: KeyBindings, text: &str) -> LifeResult<KeyBindings> {
+        let mut moved: Vec<Action> = Vec::new();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parse_error = |message: String| LifeError::Parse { line: idx + 1, message };
+            let (name, key) = match line.find('=') {
+                Some(at) => (line[..at].trim(), line[at + 1..].trim()),
+                None => return Err(parse_error(format!("expected action = key, got {:?}", line))),
+            };
+            let action: Action = name
+                .parse()
+                .map_err(|_| parse_error(format!("there is no action called {:?}", name)))?;
+            let binding: Binding = key.parse().map_err(parse_error)?;
+            if !moved.contains(&action) {
+                base.bindings.retain(|&(_, bound)| bound != action);
+                moved.push(action);
+            }
+            base.bindings.push((binding, action));
+        }
+        Ok(base)
+    }
+
+    /// The defaults if there is no keys.txt
+    pub fn load(path: &Path) -> LifeResult<KeyBindings> {
+        match fs::read_to_string(path) {
+            Ok(text) => KeyBindings::parse(&text),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(KeyBindings::defaults()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// What pressing input with exactly these modifiers held does
+    pub fn action_for(&self, input: Input, keymods: keyboard::KeyMods) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|&&(binding, _)| binding.matches(input, keymods))
+            .map(|&(_, action)| action)
+    }
+
+    /// Whether input is one of action's, whatever modifiers go with it. For
+    /// letting go, when the modifiers might not be held any more
+    pub fn is_bound(&self, input: Input, action: Action) -> bool {
+        self.bindings
+            .iter()
+            .any(|&(binding, bound)| bound == action && binding.input == input)
+    }
+
+    /// Whether any of action's keys are down right now, with at least their
+    /// modifiers. Mouse buttons don't count, they come as events
+    pub fn is_held(&self, ctx: &Context, action: Action) -> bool {
+        let keymods = keyboard::active_mods(ctx);
+        self.bindings.iter().any(|&(binding, bound)| {
+            bound == action
+                && match binding.input {
+                    Input::Key(keycode) => keyboard::is_key_pressed(ctx, keycode),
+                    Input::Mouse(_) => false,
+                }
+                && (!binding.ctrl || keymods.contains(keyboard::KeyMods::CTRL))
+                && (!binding.shift || keymods.contains(keyboard::KeyMods::SHIFT))
+        })
+    }
+
+    fn keys_for(&self, action: Action) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|&&(_, bound)| bound == action)
+            .map(|(binding, _)| binding.to_string())
+            .collect()
+    }
+
+    /// A line per action with whatever it is bound to, and the bookmarks
+    /// squeezed onto a line each
+    pub fn help_lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = ACTIONS
+            .iter()
+            .filter_map(|&(action, _, description)| {
+                let keys = self.keys_for(action);
+                if keys.is_empty() {
+                    None
+                } else {
+                    Some(format!("{}: {}", description, keys.join(", ")))
+                }
+            })
+            .collect();
+        let bookmarks = |bookmark: fn(usize) -> Action, description: &str| {
+            let keys: Vec<String> = (0..BOOKMARK_SLOTS)
+                .map(|slot| self.keys_for(bookmark(slot)).first().cloned().unwrap_or_else(|| "-".to_string()))
+                .collect();
+            format!("{}: {}", description, keys.join(" "))
+        };
+        lines.push(bookmarks(Action::Bookmark, "Go to bookmark 0-9"));
+        lines.push(bookmarks(Action::SaveBookmark, "Save bookmark 0-9"));
+        lines
+    }
+}
+
+// Every binding in a panel in the middle of the window
+pub struct Help {
+    visible: bool,
+}
+
+impl Help {
+    pub fn new() -> Self {
+        Help { visible: false }
+    }
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+    pub fn draw(&self, ctx: &mut Context, lines: &[String]) -> GameResult {
+        if !self.visible {
+            return Ok(());
+        }
+        let (screen_width, screen_height) = graphics::drawable_size(ctx);
+        let text = Text::new(lines.join("\n"));
+        let (width, height) = text.dimensions(ctx);
+        let left = ((screen_width - width as f32) / 2.0).max(HELP_MARGIN);
+        let top = ((screen_height - height as f32) / 2.0).max(HELP_MARGIN);
+        let background = graphics::Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(
+                left - HELP_PADDING,
+                top - HELP_PADDING,
+                width as f32 + 2.0 * HELP_PADDING,
+                height as f32 + 2.0 * HELP_PADDING,
+            ),
+            BLACK!(),
+        )?;
+        graphics::draw(ctx, &background, DrawParam::default())?;
+        // glyphs need alpha blending, everything else is drawn with Replace
+        graphics::set_blend_mode(ctx, BlendMode::Alpha)?;
+        graphics::draw(
+            ctx,
+            &text,
+            DrawParam::default().dest(Point2::new(left, top)).color(WHITE!()),
+        )?;
+        graphics::set_blend_mode(ctx, BlendMode::Replace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(keycode: KeyCode) -> Input {
+        Input::Key(keycode)
+    }
+
+    #[test]
+    fn test_KeyBindings_defaults() {
+        let keys = KeyBindings::defaults();
+        let none = keyboard::KeyMods::NONE;
+        let ctrl = keyboard::KeyMods::CTRL;
+        assert_eq!(keys.action_for(key(KeyCode::Space), none), Some(Action::Pause));
+        assert_eq!(keys.action_for(key(KeyCode::C), none), Some(Action::FollowCentroid));
+        assert_eq!(keys.action_for(key(KeyCode::C), ctrl), Some(Action::Copy));
+        assert_eq!(keys.action_for(key(KeyCode::J), keyboard::KeyMods::SHIFT), Some(Action::AllLiveCells));
+        assert_eq!(keys.action_for(key(KeyCode::Key3), ctrl), Some(Action::SaveBookmark(3)));
+        assert_eq!(keys.action_for(Input::Mouse(MouseButton::Middle), none), Some(Action::Pan));
+        // nothing is on Ctrl+Space
+        assert_eq!(keys.action_for(key(KeyCode::Space), ctrl), None);
+        assert!(keys.is_bound(key(KeyCode::W), Action::ScrollUp));
+        assert_eq!("save_bookmark_0".parse::<Action>(), Ok(Action::SaveBookmark(0)));
+
+        // every action has a key, and a line in the help
+        for &(action, name, _) in ACTIONS.iter() {
+            assert_eq!(name.parse::<Action>(), Ok(action));
+            assert!(!keys.keys_for(action).is_empty(), "{} has no key", name);
+        }
+        let help = keys.help_lines();
+        assert_eq!(help.len(), ACTIONS.len() + 2);
+        assert!(help.contains(&"Copy: Ctrl+C".to_string()));
+        assert!(help.contains(&"Scroll up: Up, W".to_string()));
+        assert_eq!(
+            help.last().unwrap(),
+            "Save bookmark 0-9: Ctrl+0 Ctrl+1 Ctrl+2 Ctrl+3 Ctrl+4 Ctrl+5 Ctrl+6 Ctrl+7 Ctrl+8 Ctrl+9"
+        );
+    }
+
+    #[test]
+    fn test_KeyBindings_parse_moves_actions() {
+        let keys = KeyBindings::parse("# mine\npause = p\npause = ctrl+shift+Space\nbookmark_3 = F3\n").unwrap();
+        let none = keyboard::KeyMods::NONE;
+        assert_eq!(keys.action_for(key(KeyCode::P), none), Some(Action::Pause));
+        assert_eq!(
+            keys.action_for(key(KeyCode::Space), keyboard::KeyMods::CTRL | keyboard::KeyMods::SHIFT),
+            Some(Action::Pause)
+        );
+        // the old keys go
+        assert_eq!(keys.action_for(key(KeyCode::Space), none), None);
+        assert_eq!(keys.action_for(key(KeyCode::Key3), none), None);
+        assert_eq!(keys.action_for(key(KeyCode::F3), none), Some(Action::Bookmark(3)));
+        // and everything else stays where it was
+        assert_eq!(keys.action_for(key(KeyCode::Key4), none), Some(Action::Bookmark(4)));
+        assert!(keys.help_lines().contains(&"Pause: P, Ctrl+Shift+Space".to_string()));
+
+        for bad in ["pause Space", "pause = Spacebar", "jump = J", "pause = Alt+P", "bookmark_10 = 1"].iter() {
+            match KeyBindings::parse(&format!("pause = P\n{}", bad)) {
+                Err(LifeError::Parse { line, .. }) => assert_eq!(line, 2, "{}", bad),
+                Err(other) => panic!("{} gave {:?}", bad, other),
+                Ok(_) => panic!("{} parsed", bad),
+            }
+        }
+    }
+
+    #[test]
+    fn test_Binding_display_round_trips() {
+        for &text in ["J", "Ctrl+C", "Shift+J", "Ctrl+Shift+0", "MouseMiddle", "PageDown"].iter() {
+            let binding: Binding = text.parse().unwrap();
+            assert_eq!(binding.to_string(), text);
+        }
+    }
+}
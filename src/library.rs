@@ -0,0 +1,261 @@
+// Patterns that come with the app, as RLE files under resources/patterns with
+// one directory per category. They are compiled into the binary so
+// PatternBuilder::place_named works wherever it is run from, and the directory
+// is read again at startup so new or changed files show up without a rebuild
+use super::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+macro_rules! bundled {
+    ($($category:literal / $name:literal),* $(,)?) => {
+        &[$((
+            $category,
+            $name,
+            include_str!(concat!("../resources/patterns/", $category, "/", $name, ".rle")),
+        )),*]
+    };
+}
+
+// (category, name, RLE)
+const BUNDLED: &[(&str, &str, &str)] = bundled![
+    "guns" / "gosper_glider_gun",
+    "guns" / "simkin_glider_gun",
+    "methuselahs" / "acorn",
+    "methuselahs" / "diehard",
+    "methuselahs" / "r_pentomino",
+    "oscillators" / "beacon",
+    "oscillators" / "figure_eight",
+    "oscillators" / "pentadecathlon",
+    "oscillators" / "pulsar",
+    "puffers" / "puffer_train",
+    "spaceships" / "copperhead",
+    "spaceships" / "glider",
+    "spaceships" / "hwss",
+    "spaceships" / "lwss",
+    "spaceships" / "mwss",
+];
+
+pub fn patterns_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("resources").join("patterns")
+}
+
+#[derive(Clone)]
+pub struct LibraryEntry {
+    /// file name without the .rle
+    pub name: String,
+    pub category: String,
+    /// from the #N line, name if there isn't one
+    pub title: String,
+    /// #C lines, joined up
+    pub description: String,
+    pub pattern: BMatrixVector,
+}
+
+impl LibraryEntry {
+    pub fn parse(category: &str, name: &str, text: &str) -> LifeResult<LibraryEntry> {
+        let pattern = rle::parse(text)?;
+        let comment = |tag: &str| -> Vec<&str> {
+            text.lines()
+                .map(str::trim)
+                .filter(|line| line.starts_with(tag))
+                .map(|line| line[tag.len()..].trim())
+                .collect()
+        };
+        Ok(LibraryEntry {
+            name: name.to_string(),
+            category: category.to_string(),
+            title: comment("#N")
+                .first()
+                .map_or_else(|| name.to_string(), |title| title.to_string()),
+            description: comment("#C").join(" "),
+            pattern,
+        })
+    }
+}
+
+/// Every pattern there is to pick from, by category and then name
+pub struct PatternLibrary {
+    entries: Vec<LibraryEntry>,
+}
+
+impl PatternLibrary {
+    pub fn bundled() -> PatternLibrary {
+        let entries = BUNDLED
+            .iter()
+            .map(|&(category, name, text)| {
+                // the tests make sure these all parse
+                LibraryEntry::parse(category, name, text).expect("bundled pattern does not parse")
+            })
+            .collect();
+        let mut library = PatternLibrary { entries };
+        library.sort();
+        library
+    }
+    /// What is bundled, plus or instead of whatever is in patterns_dir now
+    pub fn load() -> PatternLibrary {
+        let mut library = PatternLibrary::bundled();
+        for (path, err) in library.load_dir(&patterns_dir()) {
+            eprintln!("Skipping {}: {}", path.display(), err);
+        }
+        library
+    }
+    /// Adds every dir/category/name.rle, replacing bundled patterns of the
+    /// same name. Hands back the files that could not be read
+    pub fn load_dir(&mut self, dir: &Path) -> Vec<(PathBuf, LifeError)> {
+        let mut failed = Vec::new();
+        // no directory is fine, there is still everything bundled
+        let categories = match fs::read_dir(dir) {
+            Ok(categories) => categories,
+            Err(_) => return failed,
+        };
+        for category in categories.filter_map(Result::ok).map(|entry| entry.path()) {
+            let files = match fs::read_dir(&category) {
+                Ok(files) => files,
+                Err(_) => continue,
+            };
+            let category_name = category.file_name().unwrap().to_string_lossy().into_owned();
+            for path in files.filter_map(Result::ok).map(|entry| entry.path()) {
+                if path.extension() != Some("rle".as_ref()) {
+                    continue;
+                }
+                let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+                let entry = fs::read_to_string(&path)
+                    .map_err(LifeError::from)
+                    .and_then(|text| LibraryEntry::parse(&category_name, &name, &text));
+                match entry {
+                    Ok(entry) => {
+                        self.entries.retain(|existing| existing.name != entry.name);
+                        self.entries.push(entry);
+                    }
+                    Err(err) => failed.push((path, err)),
+                }
+            }
+        }
+        self.sort();
+        failed
+    }
+    pub fn entries(&self) -> &[LibraryEntry] {
+        &self.entries
+    }
+    pub fn get(&self, name: &str) -> Option<&LibraryEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+    fn sort(&mut self) {
+        self.entries
+            .sort_by(|a, b| (&a.category, &a.name).cmp(&(&b.category, &b.name)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::census::{self, ObjectKind};
+
+    fn cells(pattern: &BMatrixVector) -> Vec<(i32, i32)> {
+        let mut cells = Vec::new();
+        for j in 0..pattern.height() {
+            for i in 0..pattern.width() {
+                if pattern.is_alive(i, j) {
+                    cells.push((i, j));
+                }
+            }
+        }
+        cells
+    }
+
+    fn bundled(name: &str) -> BMatrixVector {
+        PatternLibrary::bundled().get(name).unwrap().pattern.clone()
+    }
+
+    // pattern in the middle of a universe with room all round, run for generations
+    fn run(name: &str, room: i32, generations: u32) -> (BMatrixVector, BMatrixVector) {
+        let pattern = bundled(name);
+        let mut vec = BMatrixVector::new(pattern.width() + 2 * room, pattern.height() + 2 * room);
+        vec.paste(&pattern, room, room);
+        let start = vec.clone();
+        let mut next = vec.clone();
+        let mut scratch = vec.clone();
+        create_engine(Backend::Single).step_n(generations, &start, &mut next, &mut scratch);
+        vec.clone_from(&next);
+        (start, vec)
+    }
+
+    #[test]
+    fn test_PatternLibrary_bundled_patterns_all_parse() {
+        let library = PatternLibrary::bundled();
+        assert_eq!(library.entries().len(), BUNDLED.len());
+        let gun = library.get("gosper_glider_gun").unwrap();
+        assert_eq!(gun.category, "guns");
+        assert_eq!(gun.title, "Gosper glider gun");
+        assert_eq!(gun.pattern.population(), 36);
+        // sorted by category, then name
+        assert_eq!(library.entries()[0].name, "gosper_glider_gun");
+        assert_eq!(library.entries().last().unwrap().name, "mwss");
+    }
+
+    #[test]
+    fn test_PatternLibrary_spaceships_and_oscillators_are_what_they_say() {
+        let expected = [
+            ("glider", "xq4_153"),
+            ("lwss", "xq4_6frc"),
+            ("mwss", "xq4_27dee6"),
+            ("hwss", "xq4_27deee6"),
+            ("beacon", "xp2_318c"),
+            ("pentadecathlon", "xp15_4r4z4r4"),
+        ];
+        for &(name, code) in expected.iter() {
+            let pattern = bundled(name);
+            assert_eq!(census::classify(&cells(&pattern)).0, code, "{}", name);
+        }
+        let kind = |name| census::classify(&cells(&bundled(name))).1;
+        assert_eq!(kind("pulsar"), ObjectKind::Oscillator { period: 3 });
+        assert_eq!(kind("figure_eight"), ObjectKind::Oscillator { period: 8 });
+        match kind("copperhead") {
+            ObjectKind::Spaceship { period: 10, dx: 0, dy } => assert_eq!(dy.abs(), 1),
+            other => panic!("copperhead is a {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_PatternLibrary_guns_and_methuselahs() {
+        // a new glider every period, nothing else changes
+        let population = |name, generations| run(name, 40, generations).1.population();
+        assert_eq!(population("gosper_glider_gun", 60), population("gosper_glider_gun", 30) + 5);
+        assert_eq!(population("simkin_glider_gun", 240), population("simkin_glider_gun", 120) + 5);
+        assert!(population("diehard", 129) > 0);
+        assert_eq!(population("diehard", 130), 0);
+    }
+
+    #[test]
+    fn test_PatternLibrary_puffer_leaves_a_trail() {
+        let (start, end) = run("puffer_train", 80, 120);
+        let population = (start.population(), end.population());
+        let (start, end) = (start.bounding_box().unwrap(), end.bounding_box().unwrap());
+        // front goes right at c/2, the smoke stays where it was made
+        assert_eq!(end.x + end.width - (start.x + start.width), 60);
+        assert!(end.x <= start.x);
+        assert!(population.1 > 5 * population.0);
+    }
+
+    #[test]
+    fn test_PatternLibrary_load_dir_adds_and_replaces() {
+        let dir = std::env::temp_dir().join(format!("cgol_library_{}", std::process::id()));
+        fs::create_dir_all(dir.join("still_lifes")).unwrap();
+        fs::write(dir.join("still_lifes").join("block.rle"), "x = 2, y = 2\n2o$2o!").unwrap();
+        // same name as a bundled one
+        fs::write(dir.join("still_lifes").join("glider.rle"), "#N Not a glider\nx = 1, y = 1\no!").unwrap();
+        fs::write(dir.join("still_lifes").join("broken.rle"), "x = 1, y = 1\n2o!").unwrap();
+        fs::write(dir.join("still_lifes").join("notes.txt"), "not a pattern").unwrap();
+
+        let mut library = PatternLibrary::bundled();
+        let failed = library.load_dir(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(failed.len(), 1);
+        assert!(failed[0].0.ends_with("broken.rle"));
+        assert_eq!(library.entries().len(), BUNDLED.len() + 1);
+        assert_eq!(library.get("block").unwrap().pattern.population(), 4);
+        let glider = library.get("glider").unwrap();
+        assert_eq!((glider.category.as_str(), glider.title.as_str()), ("still_lifes", "Not a glider"));
+    }
+}
@@ -7,23 +7,60 @@ use ggez::error::GameError;
 use ggez::event::KeyCode;
 use ggez::graphics::{BlendMode, DrawParam, Image};
 use ggez::input::keyboard;
-use ggez::{conf, event, graphics};
+use ggez::input::mouse::{self, MouseButton};
+use ggez::{conf, event, graphics, timer};
 use ggez::{Context, GameResult};
 
+use std::sync::Arc;
 use std::{thread,time};
-use std::ops::{Deref, DerefMut};
+
+mod error;
+use error::{LifeError, LifeResult};
 
 mod b_matrix;
 use b_matrix::*;
 
 mod fsubview;
-use fsubview::FSubview;
+use fsubview::{FSubview, Layout};
 
 mod user;
-use user::{OffsetState, Point};
+use user::{Camera, CameraTarget, Point, Screen};
 
 mod patterns;
 
+mod census;
+
+mod tracker;
+
+mod hud;
+use hud::Hud;
+
+mod headless;
+
+mod rle;
+
+mod library;
+
+mod browser;
+use browser::LibraryBrowser;
+
+mod clipboard;
+
+mod shading;
+use shading::Shading;
+
+mod theme;
+use theme::Theme;
+
+mod navigation;
+use navigation::{Bookmarks, GoToPrompt};
+
+mod input;
+use input::{Action, Help, Input, KeyBindings};
+
+#[cfg(test)]
+mod fixtures;
+
 #[cfg(test)]
 use mocktopus::macros::*;
 // ************  Frontend Globals  ************
@@ -33,7 +70,6 @@ const WINDOW_HEIGHT: usize = 1080;
 const CELL_SIZE: f32 = 20.0;
 //any smaller and may not print out correctly
 const CELL_GAP: f32 = CELL_SIZE / 6.0;
-const EPSILON: f32 = 1e-2f32;
 
 //const SW_HORIZONTAL_SECTIONS:i32 = ((CELL_SIZE+CELL_GAP+WINDOW_WIDTH as f32)/(CELL_SIZE+CELL_GAP)).ceil() as i32;
 
@@ -59,6 +95,50 @@ const EPSILON: f32 = 1e-2f32;
 //const GRID_SIZE: usize >= std::cmp::max(NUM_BLOCKS_HEIGHT, NUM_BLOCKS_WIDTH);
 const GRID_SIZE: i32 = 10000; // Size is probably bigger than you computer screen. For reference, on my 1920 wide laptop, it will fit about 82 cells across
 
+// How many generations the worker can get ahead of the one on screen.
+// 1 gives one generation per frame, anything bigger lets it go faster than the frame rate
+const RUN_AHEAD: u32 = 1;
+// Longest period the cycle detector catches, e.g. 64. Off(0) by default, since
+// finding the live region means looking through the whole GRID_SIZE universe
+// every generation
+const CYCLE_HISTORY: usize = 0;
+// Follow spaceships and list them in the HUD. Off by default since the full
+// GRID_SIZE soup has far too many bits of debris to classify every generation
+const TRACK_SPACESHIPS: bool = false;
+// Start out following CameraTarget::Spaceship(0)(the first ship found) or
+// CameraTarget::Centroid, like F and C do. Following a ship tracks them all
+// until it stops, so it is as slow as TRACK_SPACESHIPS in the meantime
+const FOLLOW: Option<CameraTarget> = None;
+// How many generations back the , key and undo can reach, e.g. 256. Off(0) by default:
+// every generation gets diffed against the last, and the first keyframe of a
+// GRID_SIZE soup alone is a few hundred MB. Without it undo only reaches edits
+// made in the generation on screen, e.g. while paused
+const GENERATION_HISTORY: usize = 0;
+// for N, which fills the selection with a random soup
+const FILL_DENSITY: f64 = 0.5;
+// Any Generations rule, e.g. B2/S/C3 for Brian's Brain or 345/2/4 for Star Wars
+// (with an H or V on the end for hexagonal or von Neumann neighbors), an isotropic
+// non-totalistic one like B2-a/S12, Larger than Life like R5,C0,M1,S34..58,B34..45,NM,
+// WireWorld, or the path to a Golly .rule file with a @TABLE in it. W30 or W110 run an
+// elementary 1D rule along the bottom row instead, the earlier rows moving up above it
+const RULE: &str = "B3/S23";
+// Layout::Hexagonal draws hexagons instead of squares, for rules with an H on the end
+const LAYOUT: Layout = Layout::Square;
+// Shading::Age colors cells by how long they have been alive(or dead), Shading::Activity
+// by how often they changed lately, which G cycles through too. Neither is cheap on a
+// GRID_SIZE universe: ages are 2 bytes a cell and activity 5, one set for each of the
+// 3 snapshots with the worker sharing the latest, so about 600MB and 1.5GB
+const SHADING: Shading = Shading::States;
+// generations Shading::Activity looks back over, up to 32
+const ACTIVITY_WINDOW: u32 = 32;
+// classic, dark, high_contrast, colorblind, anything else in resources/themes, or
+// the path to a .theme file. T cycles through them. Themes with born or died colors
+// (all but classic) have the worker remember the generation before, 1 bit a cell
+const THEME: &str = "classic";
+// A pattern from the library to start out with at start_point in main instead of
+// the soup, e.g. Some("gosper_glider_gun")
+const PATTERN: Option<&str> = None;
+
 const INVALID_X: i32 = 2 * GRID_SIZE;
 const INVALID_Y: i32 = 2 * GRID_SIZE;
 
@@ -76,19 +156,36 @@ macro_rules! WHITE {
     };
 }
 
-#[macro_export]
-macro_rules! GREY {
-    () => {
-        [0.5, 0.5, 0.5, 1.0].into()
-    };
-}
-
 // ************  MAIN CODE  ************
 
 pub struct Grid {
     b_matrix: BMatrix,
     f_subview: FSubview,
-    f_user_offset: OffsetState,
+    f_user_offset: Camera,
+    hud: Hud,
+    // None while the arrow keys are in charge of the offset
+    follow: Option<CameraTarget>,
+    // spaceships are tracked for the HUD, not just while following one
+    track_spaceships: bool,
+    // cells picked with the mouse, which the editing keys work on
+    selection: Option<BoundingBox>,
+    // where the left button went down, while a selection is being dragged out
+    selecting_from: Option<(i32, i32)>,
+    // last thing copied or cut, for when the OS clipboard has no RLE on it
+    clipboard: Option<BMatrixVector>,
+    // what to go back to when unpausing
+    run_ahead: u32,
+    paused: bool,
+    browser: LibraryBrowser,
+    rule: Arc<dyn Rule>,
+    shading: Shading,
+    themes: Vec<Theme>,
+    // which of themes is in use
+    theme: usize,
+    bookmarks: Bookmarks,
+    go_to: GoToPrompt,
+    keys: KeyBindings,
+    help: Help,
 }
 //#[mockable]
 impl Grid {
@@ -96,35 +193,483 @@ impl Grid {
     fn new(ctx: &mut Context, update_method: Backend) -> GameResult<Grid> {
         let b_matrix = BMatrix::new(update_method);
         let f_subview = FSubview::new(ctx)?;
-        let f_user_offset = OffsetState::default();
+        let f_user_offset = Camera::default();
+        let hud = Hud::new();
+        let browser = LibraryBrowser::new(library::PatternLibrary::load());
+        // a broken bookmarks file shouldn't stop it starting
+        let bookmarks = Bookmarks::load(&navigation::bookmarks_path(ctx)).unwrap_or_else(|err| {
+            eprintln!("Not loading bookmarks: {}", err);
+            Bookmarks::default()
+        });
+        let keys = KeyBindings::load(&input::keys_path(ctx)).unwrap_or_else(|err| {
+            eprintln!("Not loading keys.txt, using the default keys: {}", err);
+            KeyBindings::defaults()
+        });
 
         Ok(Grid {
             b_matrix,
             f_subview,
             f_user_offset,
+            hud,
+            follow: None,
+            track_spaceships: false,
+            selection: None,
+            selecting_from: None,
+            clipboard: None,
+            run_ahead: 1,
+            paused: false,
+            browser,
+            rule: Arc::new(Generations::life()),
+            shading: Shading::States,
+            themes: theme::load_themes(),
+            theme: 0,
+            bookmarks,
+            go_to: GoToPrompt::new(),
+            keys,
+            help: Help::new(),
         })
     }
 
+    // colors come from the rule too, so this needs the context
+    fn init_rule(mut self, ctx: &mut Context, rule: Arc<dyn Rule>) -> GameResult<Self> {
+        self.b_matrix.set_rule(rule.clone());
+        self.rule = rule;
+        self.update_colors(ctx)?;
+        Ok(self)
+    }
+
+    fn init_shading(mut self, ctx: &mut Context, shading: Shading) -> GameResult<Self> {
+        self.shading = shading;
+        self.update_colors(ctx)?;
+        Ok(self)
+    }
+
+    // by name, or the path to a .theme file
+    fn init_theme(mut self, ctx: &mut Context, name_or_path: &str) -> GameResult<Self> {
+        self.theme = theme::find_theme(&mut self.themes, name_or_path)?;
+        self.update_colors(ctx)?;
+        Ok(self)
+    }
+
+    fn theme(&self) -> &Theme {
+        &self.themes[self.theme]
+    }
+
+    // after the rule, shading or theme changes. The worker only keeps ages or
+    // activity while they are being shown
+    fn update_colors(&mut self, ctx: &mut Context) -> GameResult {
+        let palette = self.shading.palette(self.theme(), &self.rule.colors());
+        self.f_subview.set_colors(ctx, &palette)?;
+        self.b_matrix.set_age_tracking(self.shading.needs_ages());
+        self.b_matrix.set_alive_before_tracking(self.shading.needs_alive_before(self.theme()));
+        self.b_matrix.set_activity_tracking(match self.shading {
+            Shading::Activity(window) => window,
+            _ => 0,
+        });
+        Ok(())
+    }
+
+    fn init_layout(mut self, ctx: &mut Context, layout: Layout) -> GameResult<Self> {
+        self.f_subview.set_layout(ctx, layout)?;
+        Ok(self)
+    }
+
     fn init_seed(mut self, init_b_matrix_vector: BMatrixVector) -> Self {
+        self.b_matrix.set_seed(init_b_matrix_vector);
+        self
+    }
+
+    // same as init_seed, but the soups show up in the HUD so the run can be reproduced
+    fn init_seed_with_soups(
+        mut self,
+        init_b_matrix_vector: BMatrixVector,
+        soups: Vec<patterns::SoupInfo>,
+    ) -> Self {
+        self.b_matrix
+            .set_seed_with_soups(init_b_matrix_vector, soups);
+        self
+    }
+
+    fn init_run_ahead(mut self, generations: u32) -> Self {
+        self.b_matrix.set_run_ahead(generations);
+        self.run_ahead = generations;
+        self
+    }
+
+    fn init_generation_history(mut self, generations: usize) -> Self {
+        self.b_matrix.set_generation_history(generations);
+        self
+    }
 
-        let mut new_vec_lock = self.b_matrix.new_vec.grab_lock();
-        let new_vec_raw: &mut BMatrixVector = new_vec_lock.deref_mut();
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        let run_ahead = if self.paused { 0 } else { self.run_ahead };
+        self.b_matrix.set_run_ahead(run_ahead);
+    }
 
-        let mut vec_lock = self.b_matrix.vec.grab_writer_lock();
-        let vec_raw: &mut BMatrixVector = vec_lock.deref_mut();
-        *new_vec_raw = init_b_matrix_vector.clone();
-        *vec_raw = init_b_matrix_vector;
+    fn init_cycle_detection(mut self, history_len: usize) -> Self {
+        self.b_matrix.set_cycle_detection(history_len);
+        self
+    }
 
-        // Have to explicitly drop b/c self can't return until there are no borrows
-        std::mem::drop(new_vec_lock);
-        std::mem::drop(vec_lock);
+    fn init_spaceship_tracking(mut self, on: bool) -> Self {
+        self.b_matrix.set_spaceship_tracking(on);
+        self.track_spaceships = on;
         self
     }
 
+    fn init_follow(mut self, target: Option<CameraTarget>) -> Self {
+        if let Some(target) = target {
+            self.start_following(target);
+        }
+        self
+    }
+
+    fn start_following(&mut self, target: CameraTarget) {
+        // the worker only does the extra work while something needs it
+        match target {
+            CameraTarget::Spaceship(_) => self.b_matrix.set_spaceship_tracking(true),
+            CameraTarget::Centroid => self.b_matrix.set_centroid_tracking(true),
+        }
+        self.follow = Some(target);
+    }
+
+    fn stop_following(&mut self) {
+        match self.follow {
+            Some(CameraTarget::Centroid) => self.b_matrix.set_centroid_tracking(false),
+            Some(CameraTarget::Spaceship(_)) if !self.track_spaceships => {
+                self.b_matrix.set_spaceship_tracking(false)
+            }
+            _ => (),
+        }
+        self.follow = None;
+    }
+
+    // the tracked spaceship after the one being followed, wrapping around by id
+    fn follow_next_spaceship(&mut self) {
+        let current = match self.follow {
+            Some(CameraTarget::Spaceship(id)) => Some(id),
+            _ => None,
+        };
+        let spaceships = &self.b_matrix.latest().spaceships;
+        let next = spaceships
+            .iter()
+            .map(|track| track.id)
+            .filter(|&id| !matches!(current, Some(current) if id <= current))
+            .min()
+            .or_else(|| spaceships.iter().map(|track| track.id).min());
+        // switching ships keeps tracking on, turning it off would start the ids over
+        if current.is_none() {
+            self.stop_following();
+        }
+        // turning tracking on the first time round, nothing to follow yet
+        self.start_following(CameraTarget::Spaceship(next.unwrap_or(0)));
+    }
+
+    fn update_follow(&mut self, ctx: &mut Context, target: CameraTarget) {
+        let snapshot = self.b_matrix.latest();
+        let cell = match target {
+            CameraTarget::Centroid => snapshot.centroid,
+            CameraTarget::Spaceship(id) => {
+                // a ship that has not been confirmed yet is just not there yet,
+                // only give up once the tracker says it is gone for good
+                let gone = snapshot.spaceship_events.iter().any(|event| {
+                    event.id() == id && !matches!(event, tracker::TrackEvent::Emitted { .. })
+                });
+                if gone {
+                    self.stop_following();
+                    return;
+                }
+                snapshot
+                    .spaceships
+                    .iter()
+                    .find(|track| track.id == id)
+                    .map(|track| track.center())
+            }
+        };
+        if let Some((cell_x, cell_y)) = cell {
+            let screen = self.f_subview.screen();
+            let (x, y) = self.f_subview.layout().offset_centered_on(cell_x, cell_y, screen);
+            let seconds = timer::duration_to_f64(timer::delta(ctx)) as f32;
+            self.f_user_offset = self
+                .f_user_offset
+                .approach(x, y, user::follow_fraction(seconds), screen);
+        }
+    }
+
+    // queues make(selection), and moves the selection to wherever the cells end up
+    fn edit_selection(&mut self, make: impl FnOnce(BoundingBox) -> Edit) {
+        if let Some(selection) = self.selection {
+            let edit = make(selection);
+            self.selection = Some(edit.region());
+            self.b_matrix.queue_edit(edit);
+        }
+    }
+
+    // NOTE: copies what is on screen, so edits still in the queue are not in it
+    fn copy_selection(&mut self) {
+        if let Some(selection) = self.selection {
+            let pattern = self.b_matrix.latest().vec.copy_region(selection);
+            // the copy in here still works without one
+            if let Err(err) = clipboard::set_text(&rle::write(&pattern, &*self.rule)) {
+                eprintln!("{}", err);
+            }
+            self.clipboard = Some(pattern);
+        }
+    }
+
+    fn cut_selection(&mut self) {
+        self.copy_selection();
+        self.edit_selection(Edit::Clear);
+    }
+
+    // RLE from the OS clipboard if there is any, otherwise whatever was copied
+    // last. Lands on the selection, or under the mouse if nothing is selected
+    fn paste(&mut self, ctx: &mut Context) {
+        let pattern = clipboard::get_text()
+            .and_then(|text| rle::parse(&text))
+            .ok()
+            .or_else(|| self.clipboard.clone());
+        let mut pattern = match pattern {
+            Some(pattern) => pattern,
+            None => return,
+        };
+        // e.g. Brian's Brain pasted into Life
+        pattern.drop_states_from(self.rule.states());
+        let (x, y) = match self.selection {
+            Some(selection) => (selection.x, selection.y),
+            None => {
+                let mouse = mouse::position(ctx);
+                self.cell_at_screen(mouse.x, mouse.y)
+            }
+        };
+        let edit = Edit::Paste { pattern, x, y };
+        self.selection = Some(edit.region());
+        self.b_matrix.queue_edit(edit);
+    }
+
+    // the pattern picked in the browser, with its top left corner on the cell at x,y
+    fn stamp_at(&mut self, x: f32, y: f32) {
+        if let Some(pattern) = self.browser.pattern() {
+            let (x, y) = self.cell_at_screen(x, y);
+            let edit = Edit::Paste { pattern, x, y };
+            // selected, so it can be rotated or flipped straight away
+            self.selection = Some(edit.region());
+            self.b_matrix.queue_edit(edit);
+        }
+    }
+
+    // the cell in the middle of the window
+    fn center_cell(&self) -> (i32, i32) {
+        let screen = self.f_subview.screen();
+        self.cell_at_screen(screen.width / 2.0, screen.height / 2.0)
+    }
+
+    // puts cell in the middle of the window, or as near as the edges of the grid allow
+    fn go_to_cell(&mut self, (i, j): (i32, i32)) {
+        self.stop_following();
+        let screen = self.f_subview.screen();
+        let (x, y) = self.f_subview.layout().offset_centered_on_cell(i, j, screen);
+        self.f_user_offset = Camera::at(x, y, screen);
+    }
+
+    fn save_bookmark(&mut self, ctx: &Context, slot: usize) {
+        self.bookmarks.set(slot, self.center_cell());
+        if let Err(err) = self.bookmarks.save(&navigation::bookmarks_path(ctx)) {
+            eprintln!("Couldn't save bookmarks: {}", err);
+        }
+    }
+
+    fn go_to_bookmark(&mut self, slot: usize) {
+        if let Some(cell) = self.bookmarks.get(slot) {
+            self.go_to_cell(cell);
+        }
+    }
+
+    fn go_to_nearest_alive(&mut self) {
+        let (i, j) = self.center_cell();
+        if let Some(cell) = self.b_matrix.latest().vec.nearest_alive(i, j) {
+            self.go_to_cell(cell);
+        }
+    }
+
+    // fitting them all in the window needs zooming out, which the viewer can't do,
+    // so the middle of their bounding box is as close as it gets
+    fn go_to_live_cells(&mut self) {
+        if let Some(bounds) = self.b_matrix.latest().vec.bounding_box() {
+            self.go_to_cell((bounds.x + bounds.width / 2, bounds.y + bounds.height / 2));
+        }
+    }
+
+    // while the go to prompt is open, the keyboard is for typing into it
+    fn go_to_key(&mut self, keycode: KeyCode) {
+        match keycode {
+            KeyCode::Return => {
+                if let Some(cell) = self.go_to.target() {
+                    self.go_to_cell(cell);
+                }
+                self.go_to.close();
+            }
+            KeyCode::Escape => self.go_to.close(),
+            KeyCode::Back => self.go_to.backspace(),
+            _ => (),
+        }
+    }
+
+    // -1, 0 or 1 for which of the two is held down
+    fn axis(&self, ctx: &Context, plus: Action, minus: Action) -> f32 {
+        self.keys.is_held(ctx, plus) as i32 as f32 - self.keys.is_held(ctx, minus) as i32 as f32
+    }
+
+    // whatever a key or mouse button was bound to
+    fn act(&mut self, ctx: &mut Context, action: Action) {
+        match action {
+            Action::Quit => event::quit(ctx),
+            Action::StepBack => self.b_matrix.step_back(1),
+            Action::StepForward => {
+                let next = self.b_matrix.latest().generation + 1;
+                self.b_matrix.run_to_generation(next);
+            }
+            Action::Pause => self.toggle_pause(),
+            // the library gets the scroll keys while it is open, otherwise
+            // the camera reads them every frame
+            Action::ScrollUp if self.browser.is_visible() => self.browser.select_previous(),
+            Action::ScrollDown if self.browser.is_visible() => self.browser.select_next(),
+            Action::ScrollLeft | Action::ScrollRight | Action::ScrollUp | Action::ScrollDown => (),
+            Action::PageLeft | Action::PageRight | Action::PageUp | Action::PageDown => {
+                let (across, down) = match action {
+                    Action::PageLeft => (-1.0, 0.0),
+                    Action::PageRight => (1.0, 0.0),
+                    Action::PageUp => (0.0, -1.0),
+                    _ => (0.0, 1.0),
+                };
+                self.stop_following();
+                self.f_user_offset.page(across, down, self.f_subview.screen());
+            }
+            // these need to know where the mouse is, see mouse_button_down_event
+            Action::Select | Action::Pan => (),
+            Action::Deselect => self.selection = None,
+            Action::Copy => self.copy_selection(),
+            Action::Cut => self.cut_selection(),
+            Action::Paste => self.paste(ctx),
+            Action::Undo => self.b_matrix.undo(),
+            Action::Redo => self.b_matrix.redo(),
+            // and the pattern it is about to stamp, rather than the selection
+            Action::Rotate if self.browser.is_visible() => self.browser.rotate(),
+            Action::FlipHorizontal if self.browser.is_visible() => self.browser.flip_horizontal(),
+            Action::FlipVertical if self.browser.is_visible() => self.browser.flip_vertical(),
+            Action::Rotate => self.edit_selection(Edit::Rotate),
+            Action::FlipHorizontal => self.edit_selection(Edit::FlipHorizontal),
+            Action::FlipVertical => self.edit_selection(Edit::FlipVertical),
+            Action::FillRandom => self.edit_selection(|region| Edit::FillRandom {
+                region,
+                seed: rand::random(),
+                density: FILL_DENSITY,
+            }),
+            Action::Clear => self.edit_selection(Edit::Clear),
+            Action::ToggleHud => self.hud.toggle(),
+            Action::ToggleHelp => self.help.toggle(),
+            Action::CycleShading => {
+                self.shading = self.shading.next(ACTIVITY_WINDOW);
+                if let Err(err) = self.update_colors(ctx) {
+                    eprintln!("{}", err);
+                }
+            }
+            Action::CycleTheme => {
+                self.theme = (self.theme + 1) % self.themes.len();
+                if let Err(err) = self.update_colors(ctx) {
+                    eprintln!("{}", err);
+                }
+            }
+            Action::ToggleLibrary => self.browser.toggle(),
+            Action::Stamp if self.browser.is_visible() => {
+                let mouse = mouse::position(ctx);
+                self.stamp_at(mouse.x, mouse.y);
+            }
+            Action::Stamp => (),
+            Action::FollowSpaceship => self.follow_next_spaceship(),
+            Action::FollowCentroid => match self.follow {
+                Some(CameraTarget::Centroid) => self.stop_following(),
+                _ => {
+                    self.stop_following();
+                    self.start_following(CameraTarget::Centroid);
+                }
+            },
+            Action::GoTo => self.go_to.open(),
+            Action::NearestAlive => self.go_to_nearest_alive(),
+            Action::AllLiveCells => self.go_to_live_cells(),
+            Action::Bookmark(slot) => self.go_to_bookmark(slot),
+            Action::SaveBookmark(slot) => self.save_bookmark(ctx, slot),
+        }
+    }
+
+    // cell under the screen position (x,y), whichever way the cells are laid out
+    fn cell_at_screen(&self, x: f32, y: f32) -> (i32, i32) {
+        self.f_subview
+            .layout()
+            .cell_at_screen(self.f_user_offset.get_point(), x, y)
+    }
+
+    fn draw_outline(&self, ctx: &mut Context, region: BoundingBox, color: graphics::Color) -> GameResult {
+        let corners = self
+            .f_subview
+            .layout()
+            .outline_of(self.f_user_offset.get_point(), region);
+        let outline = graphics::Mesh::new_polygon(ctx, graphics::DrawMode::stroke(CELL_GAP), &corners, color)?;
+        graphics::draw(ctx, &outline, DrawParam::default())
+    }
+
+    fn draw_selection(&self, ctx: &mut Context) -> GameResult {
+        if let Some(selection) = self.selection {
+            self.draw_outline(ctx, selection, theme::to_color(self.theme().selection))?;
+        }
+        // where the browser would stamp its pattern
+        if let (true, Some(pattern)) = (self.browser.is_visible(), self.browser.pattern()) {
+            let mouse = mouse::position(ctx);
+            let (x, y) = self.cell_at_screen(mouse.x, mouse.y);
+            let ghost = BoundingBox {
+                x,
+                y,
+                width: pattern.width(),
+                height: pattern.height(),
+            };
+            self.draw_outline(ctx, ghost, theme::to_color(self.theme().outline))?;
+        }
+        Ok(())
+    }
+
+    fn hud_lines(&mut self) -> Vec<String> {
+        let snapshot = self.b_matrix.latest();
+        let mut lines = Hud::lines(snapshot);
+        lines.insert(1, format!("Rule: {}", self.rule));
+        if self.shading != Shading::States {
+            lines.insert(2, format!("Shading: {}", self.shading));
+        }
+        // classic goes without saying
+        if self.theme != 0 {
+            lines.push(format!("Theme: {}", self.themes[self.theme].name));
+        }
+        if let Some(target) = self.follow {
+            lines.push(Hud::follow_line(target, snapshot));
+        }
+        if let Some(selection) = self.selection {
+            lines.push(Hud::selection_line(selection));
+        }
+        let bookmarks = self.bookmarks.used();
+        if !bookmarks.is_empty() {
+            let slots: Vec<String> = bookmarks.iter().map(|slot| slot.to_string()).collect();
+            lines.push(format!("Bookmarks: {}", slots.join(" ")));
+        }
+        if self.paused {
+            lines.push("Paused".to_string());
+        }
+        lines
+    }
+
     // NOTE: Please initialize to a region inside
     fn init_offset(mut self, x: f32, y: f32) -> Self {
         if x > 0.0 && y > 0.0 {
-            self.f_user_offset = OffsetState::Inside(Point::new(x, y));
+            self.f_user_offset = Camera::at(x, y, self.f_subview.screen());
             self
         } else {
             panic!("Please set initial offset to be positive. Default is at (0.0,0.0)");
@@ -133,17 +678,17 @@ impl Grid {
 
     // Invariant Sliding Window Version
     fn sync_update_view(&mut self, ctx: &mut Context) -> GameResult {
-        // 0. Extracting updated b_matrix_vector
-        let vec_lock = self.b_matrix.vec.grab_reader_lock();
-        let vec_raw = vec_lock.deref();
+        // 0. Extracting updated b_matrix_vector -> doesn't wait on the worker
+        let snapshot = self.b_matrix.latest();
         // 1. get bounding boxes
         let offset_point = self.f_user_offset.get_point();
+        let screen = self.f_subview.screen();
         let (left_idx, right_idx) = self
             .f_subview
-            .get_horizontal_window_range(offset_point.x, offset_point.x + WINDOW_WIDTH as f32);
+            .get_horizontal_window_range(offset_point.x, offset_point.x + screen.width);
         let (top_idx, bottom_idx) = self
             .f_subview
-            .get_vertical_window_range(offset_point.y, offset_point.y + WINDOW_HEIGHT as f32);
+            .get_vertical_window_range(offset_point.y, offset_point.y + screen.height);
 
         // 2. now draw from base_index_top -> base_index_bottom, inclusive
         self.f_subview.startView();
@@ -152,21 +697,19 @@ impl Grid {
             for i in left_idx..right_idx + 1 {
                 let relative_i = i - left_idx;
 
-                if vec_raw.at(i, j)? {
-                    //self.f_subview.change_to_white(i,j);
-                    self.f_subview.addWhiteToView(relative_i, relative_j);
-                } else {
-                    //self.f_subview.change_to_black(i,j);
-                    self.f_subview.addBlackToView(relative_i, relative_j);
-                }
+                // hexagons can have the window hang off the right and bottom of
+                // the grid, which is all dead
+                let color = self.shading.color_of(snapshot, i, j, self.rule.states());
+                self.f_subview.addStateToView(relative_i, relative_j, color);
             }
         }
         self.f_subview.endView(ctx);
 
         // 3. finally define new relative offset
         // aka relative to the box at (left_idx,top_idx)
-        let rel_offset_y = fsubview::get_distance_to_top(offset_point.y, top_idx)?;
-        let rel_offset_x = fsubview::get_distance_to_left(offset_point.x, left_idx)?;
+        let (rel_offset_x, rel_offset_y) = self
+            .f_subview
+            .get_relative_offset(offset_point, left_idx, top_idx)?;
         self.f_subview
             .update_relative_offset(rel_offset_x, rel_offset_y);
         Ok(())
@@ -176,28 +719,143 @@ impl Grid {
 trait MatrixView {
     /// i and j are with respect to computer graphics convention
     type Item;
-    fn at(&self, i: i32, j: i32) -> GameResult<Self::Item>;
-    fn at_mut(&mut self, i: i32, j: i32) -> GameResult<&mut Self::Item>;
+    fn at(&self, i: i32, j: i32) -> LifeResult<Self::Item>;
+    fn at_mut(&mut self, i: i32, j: i32) -> LifeResult<&mut Self::Item>;
+}
+
+// Viewer boundary: the backend only knows about LifeError
+impl From<LifeError> for GameError {
+    fn from(err: LifeError) -> GameError {
+        match err {
+            LifeError::Io(err) => GameError::from(err),
+            LifeError::InvalidConfig(_) => GameError::ConfigError(err.to_string()),
+            LifeError::Parse { .. } => GameError::ResourceLoadError(err.to_string()),
+            LifeError::OutOfBounds { .. }
+            | LifeError::InvalidOffset { .. }
+            | LifeError::NotInHistory { .. } => GameError::EventLoopError(err.to_string()),
+        }
+    }
 }
 
 impl event::EventHandler for Grid {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
 
         self.b_matrix.sync_main_update_backend();
+        // nothing the worker gets wrong is worth stopping for
+        for err in self.b_matrix.take_errors() {
+            eprintln!("{}", err);
+        }
 
-        self.f_user_offset.update(ctx);
+        // the arrow keys go through the library while the browser is open
+        let arrows_free = !self.browser.is_visible() && !self.go_to.is_visible();
+        let push = (
+            self.axis(ctx, Action::ScrollRight, Action::ScrollLeft),
+            self.axis(ctx, Action::ScrollDown, Action::ScrollUp),
+        );
+        // taking the camera back is enough to stop following
+        if self.follow.is_some() && arrows_free && push != (0.0, 0.0) {
+            self.stop_following();
+        }
+        match self.follow {
+            Some(target) => self.update_follow(ctx, target),
+            None if arrows_free => self.f_user_offset.update(ctx, push, self.f_subview.screen()),
+            None => (),
+        }
         // use updated b_matrix and offset to update view
         self.sync_update_view(ctx)?;
         Ok(())
     }
 
+    fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        let keymods = keyboard::active_mods(ctx);
+        match self.keys.action_for(Input::Mouse(button), keymods) {
+            Some(Action::Select) if self.browser.is_visible() => self.stamp_at(x, y),
+            Some(Action::Select) => {
+                let cell = self.cell_at_screen(x, y);
+                self.selecting_from = Some(cell);
+                self.selection = Some(BoundingBox::spanning(cell, cell));
+            }
+            // grabbing the grid takes the camera back too
+            Some(Action::Pan) => {
+                self.stop_following();
+                self.f_user_offset.start_drag();
+            }
+            Some(action) => self.act(ctx, action),
+            None => (),
+        }
+    }
+
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32, dx: f32, dy: f32) {
+        self.f_user_offset.drag(dx, dy, self.f_subview.screen());
+        if let Some(from) = self.selecting_from {
+            let cell = self.cell_at_screen(x, y);
+            self.selection = Some(BoundingBox::spanning(from, cell));
+        }
+    }
+
+    // whatever was held when it went down might not be any more
+    fn mouse_button_up_event(&mut self, _ctx: &mut Context, button: MouseButton, _x: f32, _y: f32) {
+        if self.keys.is_bound(Input::Mouse(button), Action::Select) {
+            self.selecting_from = None;
+        }
+        if self.keys.is_bound(Input::Mouse(button), Action::Pan) {
+            self.f_user_offset.end_drag();
+        }
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyCode,
+        keymods: keyboard::KeyMods,
+        repeat: bool,
+    ) {
+        if self.go_to.is_visible() {
+            self.go_to_key(keycode);
+            return;
+        }
+        match self.keys.action_for(Input::Key(keycode), keymods) {
+            Some(action) if !repeat || action.repeats() => self.act(ctx, action),
+            _ => (),
+        }
+    }
+
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char) {
+        if self.go_to.is_visible() {
+            self.go_to.push(character);
+        }
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        // otherwise ggez stretches the old coordinates over the new window
+        let screen = Screen::new(width, height);
+        if let Err(err) = graphics::set_screen_coordinates(ctx, graphics::Rect::new(0.0, 0.0, width, height)) {
+            eprintln!("{}", err);
+        }
+        if let Err(err) = self.f_subview.resize(ctx, screen) {
+            eprintln!("{}", err);
+        }
+        // a bigger window can't scroll as far
+        self.f_user_offset.clamp_to(screen);
+    }
+
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
         //we should sleep as otherwise we spend too much time redrawing
         let time = time::Duration::from_millis(10);
         thread::sleep(time);
 
-        graphics::clear(ctx, GREY!());
+        graphics::clear(ctx, theme::to_color(self.theme().background));
         self.f_subview.drawView(ctx)?;
+        self.draw_selection(ctx)?;
+        let hud_lines = self.hud_lines();
+        self.hud.draw(ctx, &hud_lines)?;
+        self.browser.draw(ctx)?;
+        self.go_to.draw(ctx)?;
+        // only worth putting together while it is up
+        if self.help.is_visible() {
+            let help_lines = self.keys.help_lines();
+            self.help.draw(ctx, &help_lines)?;
+        }
         graphics::present(ctx)?;
 
         Ok(())
@@ -205,19 +863,28 @@ impl event::EventHandler for Grid {
 }
 
 pub fn main() -> GameResult {
+    if let Some(args) = headless::HeadlessArgs::from_args(std::env::args().skip(1))? {
+        headless::run(&args, &mut std::io::stdout())?;
+        return Ok(());
+    }
     // ************  GRID  ************
     // NOTE: all patterns start drawing from the top leftmost corner of the
     // "smallest bounding rectangle" of the pattern
     let start_point = (0, 150);
-    let mut init_b_matrix_vector = patterns::PatternBuilder::new()
+    let builder = patterns::PatternBuilder::new();
         //.make_square(0,0)
         //.make_blinker(5,5)
         //.make_t(12,12)
         //.make_r_pentomino(30,30)
         //.make_glider(60,60)
         //.make_random(start_point,400,500)
-        .make_random((0, 0), GRID_SIZE, GRID_SIZE)
-        .build();
+        //.make_random_seeded(start_point, 400, 500, 1234, 0.35)
+        //.make_symmetric_soup(start_point, 64, 64, 1234, 0.5, patterns::Symmetry::D8)
+    let (init_b_matrix_vector, soups) = match PATTERN {
+        Some(name) => builder.place_named(name, start_point.0, start_point.1),
+        None => builder.make_random((0, 0), GRID_SIZE, GRID_SIZE),
+    }
+    .build_with_soups();
     // ************  GGEZ  ************
     let cb = ggez::ContextBuilder::new("super_simple", "ggez").window_mode(
         conf::WindowMode::default()
@@ -235,9 +902,21 @@ pub fn main() -> GameResult {
     //let update_method = Backend::MultiThreaded(8);
     //let update_method = Backend::Single;
     let update_method = Backend::Rayon;
+    let rule = parse_rule(RULE)?;
+    // 1D rules grow along the bottom row, so start out looking at it
+    let origin_y = if rule.dimensions() == 1 { Screen::default().max_offset_y() } else { origin_point };
     let ref mut state = Grid::new(ctx, update_method)?
-        .init_seed(init_b_matrix_vector)
-        .init_offset(origin_point, origin_point);
+        .init_layout(ctx, LAYOUT)?
+        .init_rule(ctx, rule)?
+        .init_shading(ctx, SHADING)?
+        .init_theme(ctx, THEME)?
+        .init_seed_with_soups(init_b_matrix_vector, soups)
+        .init_run_ahead(RUN_AHEAD)
+        .init_cycle_detection(CYCLE_HISTORY)
+        .init_generation_history(GENERATION_HISTORY)
+        .init_spaceship_tracking(TRACK_SPACESHIPS)
+        .init_follow(FOLLOW)
+        .init_offset(origin_point, origin_y);
     event::run(ctx, event_loop, state)
 }
 #[cfg(test)]
@@ -293,7 +972,7 @@ mod tests {
                 //make_blinker(i,j,&mut init_b_matrix_vector);
                 //make_square(i,j,&mut init_b_matrix_vector);
                 if i > GRID_SIZE / 2 {
-                    *init_b_matrix_vector.at_mut(i, j).unwrap() = true;
+                    *init_b_matrix_vector.at_mut(i, j).unwrap() = ALIVE;
                 }
             }
         }
@@ -319,7 +998,7 @@ mod tests {
                 //make_blinker(i,j,&mut init_b_matrix_vector);
                 //make_square(i,j,&mut init_b_matrix_vector);
                 if i > GRID_SIZE / 2 {
-                    *init_b_matrix_vector.at_mut(i, j).unwrap() = true;
+                    *init_b_matrix_vector.at_mut(i, j).unwrap() = ALIVE;
                 }
             }
         }
@@ -329,7 +1008,7 @@ mod tests {
         let update_method = Backend::Skip;
         let mut grid = Grid::new(&mut globals.ctx, update_method)
             .unwrap()
-            .init_offset(user::get_max_offset_x(), 0.1)
+            .init_offset(Screen::default().max_offset_x(), 0.1)
             .init_seed(init_b_matrix_vector);
         event::run(&mut globals.ctx, &mut globals.event_loop, &mut grid);
     }
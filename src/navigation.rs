@@ -0,0 +1,227 @@
+// Getting back to somewhere on the grid without holding the arrow keys down
+// for a minute: numbered bookmarks that last between runs, and a prompt for
+// typing in the cell to go to
+use super::*;
+use ggez::filesystem;
+use ggez::graphics::{DrawMode, Rect, Text};
+use nalgebra::geometry::Point2;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One for each number key, to begin with
+pub const BOOKMARK_SLOTS: usize = 10;
+const PROMPT_MARGIN: f32 = 10.0;
+const PROMPT_PADDING: f32 = 6.0;
+
+/// ggez's config directory for this game, e.g. ~/.config/super_simple on Linux
+pub fn bookmarks_path(ctx: &Context) -> PathBuf {
+    filesystem::user_config_dir(ctx).join("bookmarks.txt")
+}
+
+/// The cell in the middle of the window for each slot. Cells rather than the
+/// offset, so they still land in the middle after the window changes size
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Bookmarks {
+    slots: [Option<(i32, i32)>; BOOKMARK_SLOTS],
+}
+
+impl Bookmarks {
+    pub fn get(&self, slot: usize) -> Option<(i32, i32)> {
+        self.slots.get(slot).copied().flatten()
+    }
+    pub fn set(&mut self, slot: usize, cell: (i32, i32)) {
+        if let Some(saved) = self.slots.get_mut(slot) {
+            *saved = Some(cell);
+        }
+    }
+    /// Slots with something in them
+    pub fn used(&self) -> Vec<usize> {
+        (0..BOOKMARK_SLOTS).filter(|&slot| self.slots[slot].is_some()).collect()
+    }
+
+    /// A `slot x y` line per bookmark
+    pub fn parse(text: &str) -> LifeResult<Bookmarks> {
+        let mut bookmarks = Bookmarks::default();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parse_error = || LifeError::Parse {
+                line: idx + 1,
+                message: format!("expected slot x y, got {:?}", line),
+            };
+            let numbers: Vec<i32> = line
+                .split_whitespace()
+                .map(|number| number.parse().ok())
+                .collect::<Option<_>>()
+                .ok_or_else(parse_error)?;
+            match numbers[..] {
+                [slot, x, y] if (0..BOOKMARK_SLOTS as i32).contains(&slot) => bookmarks.set(slot as usize, (x, y)),
+                _ => return Err(parse_error()),
+            }
+        }
+        Ok(bookmarks)
+    }
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("# slot x y, for the cell in the middle of the window\n");
+        for slot in self.used() {
+            let (x, y) = self.slots[slot].unwrap();
+            text.push_str(&format!("{} {} {}\n", slot, x, y));
+        }
+        text
+    }
+
+    /// No file yet is no bookmarks yet
+    pub fn load(path: &Path) -> LifeResult<Bookmarks> {
+        match fs::read_to_string(path) {
+            Ok(text) => Bookmarks::parse(&text),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Bookmarks::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+    pub fn save(&self, path: &Path) -> LifeResult<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_text())?;
+        Ok(())
+    }
+}
+
+// Box at the bottom of the window for typing in a cell to go to. While it is
+// open the keyboard is all its
+pub struct GoToPrompt {
+    text: String,
+    visible: bool,
+}
+
+impl GoToPrompt {
+    pub fn new() -> Self {
+        GoToPrompt {
+            text: String::new(),
+            visible: false,
+        }
+    }
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+    pub fn open(&mut self) {
+        self.text.clear();
+        self.visible = true;
+    }
+    pub fn close(&mut self) {
+        self.visible = false;
+    }
+    /// Typed characters, anything that can't be part of a cell gets ignored
+    pub fn push(&mut self, character: char) {
+        if character.is_ascii_digit() || character == ',' || character == ' ' || character == '-' {
+            self.text.push(character);
+        }
+    }
+    pub fn backspace(&mut self) {
+        self.text.pop();
+    }
+    /// The cell typed in so far, as x,y or x y
+    pub fn target(&self) -> Option<(i32, i32)> {
+        let numbers: Vec<i32> = self
+            .text
+            .split(|character: char| character == ',' || character.is_whitespace())
+            .filter(|number| !number.is_empty())
+            .map(|number| number.parse().ok())
+            .collect::<Option<_>>()?;
+        match numbers[..] {
+            [x, y] => Some((x, y)),
+            _ => None,
+        }
+    }
+    pub fn draw(&self, ctx: &mut Context) -> GameResult {
+        if !self.visible {
+            return Ok(());
+        }
+        let (_, screen_height) = graphics::drawable_size(ctx);
+        let text = Text::new(format!("Go to cell (x, y): {}_", self.text));
+        let (width, height) = text.dimensions(ctx);
+        let top = screen_height - PROMPT_MARGIN - height as f32;
+        let background = graphics::Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(
+                PROMPT_MARGIN - PROMPT_PADDING,
+                top - PROMPT_PADDING,
+                width as f32 + 2.0 * PROMPT_PADDING,
+                height as f32 + 2.0 * PROMPT_PADDING,
+            ),
+            BLACK!(),
+        )?;
+        graphics::draw(ctx, &background, DrawParam::default())?;
+        // glyphs need alpha blending, everything else is drawn with Replace
+        graphics::set_blend_mode(ctx, BlendMode::Alpha)?;
+        graphics::draw(
+            ctx,
+            &text,
+            DrawParam::default()
+                .dest(Point2::new(PROMPT_MARGIN, top))
+                .color(WHITE!()),
+        )?;
+        graphics::set_blend_mode(ctx, BlendMode::Replace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_Bookmarks_parse_and_to_text() {
+        let mut bookmarks = Bookmarks::default();
+        bookmarks.set(1, (5000, 5000));
+        bookmarks.set(0, (12, -3));
+        // there is no slot 10
+        bookmarks.set(BOOKMARK_SLOTS, (1, 1));
+        assert_eq!(bookmarks.used(), vec![0, 1]);
+        let text = bookmarks.to_text();
+        assert_eq!(Bookmarks::parse(&text).unwrap(), bookmarks);
+        assert_eq!(Bookmarks::parse("\n# nothing\n").unwrap(), Bookmarks::default());
+
+        for bad in ["1 2", "1 2 3 4", "10 1 1", "one 2 3"].iter() {
+            match Bookmarks::parse(&format!("0 1 1\n{}", bad)) {
+                Err(LifeError::Parse { line, .. }) => assert_eq!(line, 2, "{}", bad),
+                other => panic!("{} gave {:?}", bad, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_Bookmarks_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("gol_bookmarks_{}", std::process::id()));
+        let path = dir.join("config").join("bookmarks.txt");
+        assert_eq!(Bookmarks::load(&path).unwrap(), Bookmarks::default());
+        let mut bookmarks = Bookmarks::default();
+        bookmarks.set(7, (70, 700));
+        bookmarks.save(&path).unwrap();
+        let loaded = Bookmarks::load(&path).unwrap();
+        assert_eq!(loaded.get(7), Some((70, 700)));
+        assert_eq!(loaded.get(3), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_GoToPrompt_target() {
+        let mut prompt = GoToPrompt::new();
+        prompt.open();
+        assert_eq!(prompt.target(), None);
+        for character in "12, 3x4".chars() {
+            prompt.push(character);
+        }
+        // the x never made it in
+        assert_eq!(prompt.target(), Some((12, 34)));
+        prompt.backspace();
+        assert_eq!(prompt.target(), Some((12, 3)));
+        prompt.open();
+        for character in "7 8 9".chars() {
+            prompt.push(character);
+        }
+        assert_eq!(prompt.target(), None);
+    }
+}
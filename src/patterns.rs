@@ -1,11 +1,248 @@
 use super::*;
+use crate::library::PatternLibrary;
 use std::ops::{Deref, DerefMut};
 // no need since we "inherit" parent's uses
 //use super::b_matrix_vector::BMatrixVector;
 use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::fmt;
+use std::str::FromStr;
+
+/// Symmetry group a soup is made to have, named the way apgsearch names them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Symmetry {
+    /// no symmetry at all
+    C1,
+    /// unchanged by a half turn
+    C2,
+    /// unchanged by a quarter turn, needs a square soup
+    C4,
+    /// mirrored across both the horizontal and vertical middle lines
+    D4,
+    /// all 8 rotations and reflections of the square, needs a square soup
+    D8,
+}
+
+impl Symmetry {
+    fn needs_square(self) -> bool {
+        match self {
+            Symmetry::C4 | Symmetry::D8 => true,
+            Symmetry::C1 | Symmetry::C2 | Symmetry::D4 => false,
+        }
+    }
+    // Every cell the symmetry maps (i,j) onto within a width by height box
+    fn orbit(self, i: i32, j: i32, width: i32, height: i32) -> Vec<(i32, i32)> {
+        let (max_i, max_j) = (width - 1, height - 1);
+        match self {
+            Symmetry::C1 => vec![(i, j)],
+            Symmetry::C2 => vec![(i, j), (max_i - i, max_j - j)],
+            Symmetry::C4 => vec![(i, j), (max_j - j, i), (max_i - i, max_j - j), (j, max_i - i)],
+            Symmetry::D4 => vec![(i, j), (max_i - i, j), (i, max_j - j), (max_i - i, max_j - j)],
+            Symmetry::D8 => vec![
+                (i, j),
+                (max_j - j, i),
+                (max_i - i, max_j - j),
+                (j, max_i - i),
+                (max_i - i, j),
+                (i, max_j - j),
+                (j, i),
+                (max_j - j, max_i - i),
+            ],
+        }
+    }
+    // First cell of the orbit in row major order, so it always gets filled in before the rest
+    fn representative(self, i: i32, j: i32, width: i32, height: i32) -> (i32, i32) {
+        // skip the allocation, C1 soups tend to be the size of the whole grid
+        if self == Symmetry::C1 {
+            return (i, j);
+        }
+        self.orbit(i, j, width, height)
+            .into_iter()
+            .min_by_key(|&(i, j)| (j, i))
+            .unwrap()
+    }
+}
+
+impl FromStr for Symmetry {
+    type Err = LifeError;
+    fn from_str(s: &str) -> LifeResult<Self> {
+        match s {
+            "C1" => Ok(Symmetry::C1),
+            "C2" => Ok(Symmetry::C2),
+            "C4" => Ok(Symmetry::C4),
+            "D4" => Ok(Symmetry::D4),
+            "D8" => Ok(Symmetry::D8),
+            _ => Err(LifeError::InvalidConfig(format!(
+                "unknown symmetry {}, expected one of C1, C2, C4, D4, D8",
+                s
+            ))),
+        }
+    }
+}
+
+/// Everything needed to make the exact same soup again
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SoupInfo {
+    pub seed: u64,
+    pub density: f64,
+    pub symmetry: Symmetry,
+    pub start_point: (i32, i32),
+    pub width: i32,
+    pub height: i32,
+}
+
+impl fmt::Display for SoupInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Soup: seed {}, density {}, {:?}, {}x{} at ({},{})",
+            self.seed,
+            self.density,
+            self.symmetry,
+            self.width,
+            self.height,
+            self.start_point.0,
+            self.start_point.1
+        )
+    }
+}
+
+/// Line a reflection mirrors the pattern in, through the pattern's (0,0)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mirror {
+    /// left and right swap
+    Vertical,
+    /// top and bottom swap
+    Horizontal,
+    /// x and y swap, from top left to bottom right
+    Diagonal,
+    /// from bottom left to top right
+    AntiDiagonal,
+}
+
+/// Where each cell of a pattern goes when it is stamped: one of the 8
+/// rotations and reflections of the square, then a shift. Same as the
+/// transforms in Golly's scripts, so (x,y) goes to (axx*x + axy*y + dx, ayx*x + ayy*y + dy)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Transform {
+    axx: i32,
+    axy: i32,
+    ayx: i32,
+    ayy: i32,
+    dx: i32,
+    dy: i32,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        axx: 1,
+        axy: 0,
+        ayx: 0,
+        ayy: 1,
+        dx: 0,
+        dy: 0,
+    };
+    pub fn translate(dx: i32, dy: i32) -> Self {
+        Transform {
+            dx,
+            dy,
+            ..Transform::IDENTITY
+        }
+    }
+    /// Quarter turns about (0,0), negative ones go anticlockwise
+    pub fn rotate_clockwise(quarter_turns: i32) -> Self {
+        // y points down the screen, so clockwise takes (1,0) to (0,1)
+        let quarter = Transform {
+            axx: 0,
+            axy: -1,
+            ayx: 1,
+            ayy: 0,
+            ..Transform::IDENTITY
+        };
+        (0..quarter_turns.rem_euclid(4)).fold(Transform::IDENTITY, |turned, _| turned.then(quarter))
+    }
+    pub fn reflect(mirror: Mirror) -> Self {
+        let (axx, axy, ayx, ayy) = match mirror {
+            Mirror::Vertical => (-1, 0, 0, 1),
+            Mirror::Horizontal => (1, 0, 0, -1),
+            Mirror::Diagonal => (0, 1, 1, 0),
+            Mirror::AntiDiagonal => (0, -1, -1, 0),
+        };
+        Transform {
+            axx,
+            axy,
+            ayx,
+            ayy,
+            ..Transform::IDENTITY
+        }
+    }
+    /// Reflection, then distance cells along the mirror. A glider two
+    /// generations on is its own glide reflection
+    pub fn glide_reflect(mirror: Mirror, distance: i32) -> Self {
+        let (dx, dy) = match mirror {
+            Mirror::Vertical => (0, distance),
+            Mirror::Horizontal => (distance, 0),
+            Mirror::Diagonal => (distance, distance),
+            Mirror::AntiDiagonal => (distance, -distance),
+        };
+        Transform::reflect(mirror).then(Transform::translate(dx, dy))
+    }
+    /// self first, then next
+    pub fn then(self, next: Transform) -> Self {
+        Transform {
+            axx: next.axx * self.axx + next.axy * self.ayx,
+            axy: next.axx * self.axy + next.axy * self.ayy,
+            ayx: next.ayx * self.axx + next.ayy * self.ayx,
+            ayy: next.ayx * self.axy + next.ayy * self.ayy,
+            dx: next.axx * self.dx + next.axy * self.dy + next.dx,
+            dy: next.ayx * self.dx + next.ayy * self.dy + next.dy,
+        }
+    }
+    pub fn apply(self, x: i32, y: i32) -> (i32, i32) {
+        (
+            self.axx * x + self.axy * y + self.dx,
+            self.ayx * x + self.ayy * y + self.dy,
+        )
+    }
+}
+
+/// count_x by count_y copies of pattern, each dx across and dy down from the
+/// last, as one pattern. Copies that overlap are or'ed together
+pub fn tile(
+    pattern: &BMatrixVector,
+    dx: i32,
+    dy: i32,
+    count_x: i32,
+    count_y: i32,
+) -> BMatrixVector {
+    if count_x < 1 || count_y < 1 {
+        panic!("Need at least one copy each way to tile, got {}x{}", count_x, count_y);
+    }
+    // negative steps lay the copies out leftwards/upwards from the first one
+    let (span_x, span_y) = (dx * (count_x - 1), dy * (count_y - 1));
+    let (left, top) = (span_x.min(0), span_y.min(0));
+    let mut tiled = BMatrixVector::new(
+        pattern.width() + span_x.abs(),
+        pattern.height() + span_y.abs(),
+    );
+    for copy_y in 0..count_y {
+        for copy_x in 0..count_x {
+            for j in 0..pattern.height() {
+                for i in 0..pattern.width() {
+                    if pattern.is_alive(i, j) {
+                        let (x, y) = (copy_x * dx - left + i, copy_y * dy - top + j);
+                        *tiled.at_mut(x, y).unwrap() = ALIVE;
+                    }
+                }
+            }
+        }
+    }
+    tiled
+}
 
 pub struct PatternBuilder {
     vec: BMatrixVector,
+    soups: Vec<SoupInfo>,
 }
 
 impl Deref for PatternBuilder {
@@ -21,83 +258,464 @@ impl DerefMut for PatternBuilder {
     }
 }
 
+fn named(name: &str) -> BMatrixVector {
+    let library = PatternLibrary::bundled();
+    match library.get(name) {
+        Some(entry) => entry.pattern.clone(),
+        None => panic!(
+            "No pattern called {} in the library. Try one of: {}",
+            name,
+            library.entries().iter().map(|entry| entry.name.as_str()).collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
 impl PatternBuilder {
     pub fn new() -> Self {
         let vec = BMatrixVector::default();
-        PatternBuilder { vec }
+        PatternBuilder {
+            vec,
+            soups: Vec::new(),
+        }
+    }
+    // for when the pattern should live in a smaller universe than the viewer's
+    pub fn with_size(width: i32, height: i32) -> Self {
+        let vec = BMatrixVector::new(width, height);
+        PatternBuilder {
+            vec,
+            soups: Vec::new(),
+        }
     }
     pub fn build(self) -> BMatrixVector {
         self.vec
     }
+    // the soups are what you need to reproduce the random parts of the pattern
+    pub fn build_with_soups(self) -> (BMatrixVector, Vec<SoupInfo>) {
+        (self.vec, self.soups)
+    }
     pub fn make_square(mut self, init_x: i32, init_y: i32) -> Self {
-        *self.at_mut(init_x, init_y).unwrap() = true;
-        *self.at_mut(init_x + 1, init_y).unwrap() = true;
-        *self.at_mut(init_x + 1, init_y + 1).unwrap() = true;
-        *self.at_mut(init_x, init_y + 1).unwrap() = true;
+        *self.at_mut(init_x, init_y).unwrap() = ALIVE;
+        *self.at_mut(init_x + 1, init_y).unwrap() = ALIVE;
+        *self.at_mut(init_x + 1, init_y + 1).unwrap() = ALIVE;
+        *self.at_mut(init_x, init_y + 1).unwrap() = ALIVE;
 
         self
     }
     pub fn make_blinker(mut self, init_x: i32, init_y: i32) -> Self {
-        *self.at_mut(init_x, init_y).unwrap() = true;
-        *self.at_mut(init_x, init_y + 1).unwrap() = true;
-        *self.at_mut(init_x, init_y + 2).unwrap() = true;
+        *self.at_mut(init_x, init_y).unwrap() = ALIVE;
+        *self.at_mut(init_x, init_y + 1).unwrap() = ALIVE;
+        *self.at_mut(init_x, init_y + 2).unwrap() = ALIVE;
 
         self
     }
     pub fn make_t(mut self, init_x: i32, init_y: i32) -> Self {
         // First Row
-        *self.at_mut(init_x, init_y).unwrap() = false;
-        *self.at_mut(init_x + 1, init_y).unwrap() = true;
-        *self.at_mut(init_x + 2, init_y).unwrap() = false;
+        *self.at_mut(init_x, init_y).unwrap() = DEAD;
+        *self.at_mut(init_x + 1, init_y).unwrap() = ALIVE;
+        *self.at_mut(init_x + 2, init_y).unwrap() = DEAD;
         //Second Row
-        *self.at_mut(init_x, init_y + 1).unwrap() = true;
-        *self.at_mut(init_x + 1, init_y + 1).unwrap() = true;
-        *self.at_mut(init_x + 2, init_y + 1).unwrap() = true;
+        *self.at_mut(init_x, init_y + 1).unwrap() = ALIVE;
+        *self.at_mut(init_x + 1, init_y + 1).unwrap() = ALIVE;
+        *self.at_mut(init_x + 2, init_y + 1).unwrap() = ALIVE;
 
         self
     }
     pub fn make_r_pentomino(mut self, init_x: i32, init_y: i32) -> Self {
         // First column
-        *self.at_mut(init_x, init_y).unwrap() = false;
-        *self.at_mut(init_x, init_y + 1).unwrap() = true;
-        *self.at_mut(init_x, init_y + 2).unwrap() = false;
+        *self.at_mut(init_x, init_y).unwrap() = DEAD;
+        *self.at_mut(init_x, init_y + 1).unwrap() = ALIVE;
+        *self.at_mut(init_x, init_y + 2).unwrap() = DEAD;
         // Second column
-        *self.at_mut(init_x + 1, init_y).unwrap() = true;
-        *self.at_mut(init_x + 1, init_y + 1).unwrap() = true;
-        *self.at_mut(init_x + 1, init_y + 2).unwrap() = true;
+        *self.at_mut(init_x + 1, init_y).unwrap() = ALIVE;
+        *self.at_mut(init_x + 1, init_y + 1).unwrap() = ALIVE;
+        *self.at_mut(init_x + 1, init_y + 2).unwrap() = ALIVE;
         //Third Column
-        *self.at_mut(init_x + 2, init_y).unwrap() = true;
-        *self.at_mut(init_x + 2, init_y + 1).unwrap() = false;
-        *self.at_mut(init_x + 2, init_y + 2).unwrap() = false;
+        *self.at_mut(init_x + 2, init_y).unwrap() = ALIVE;
+        *self.at_mut(init_x + 2, init_y + 1).unwrap() = DEAD;
+        *self.at_mut(init_x + 2, init_y + 2).unwrap() = DEAD;
 
         self
     }
     pub fn make_glider(mut self, init_x: i32, init_y: i32) -> Self {
         // First Row
-        *self.at_mut(init_x, init_y).unwrap() = true;
-        *self.at_mut(init_x + 1, init_y).unwrap() = true;
-        *self.at_mut(init_x + 2, init_y).unwrap() = true;
+        *self.at_mut(init_x, init_y).unwrap() = ALIVE;
+        *self.at_mut(init_x + 1, init_y).unwrap() = ALIVE;
+        *self.at_mut(init_x + 2, init_y).unwrap() = ALIVE;
         // Second Row
-        *self.at_mut(init_x, init_y + 1).unwrap() = true;
-        *self.at_mut(init_x + 1, init_y + 1).unwrap() = false;
-        *self.at_mut(init_x + 2, init_y + 1).unwrap() = false;
+        *self.at_mut(init_x, init_y + 1).unwrap() = ALIVE;
+        *self.at_mut(init_x + 1, init_y + 1).unwrap() = DEAD;
+        *self.at_mut(init_x + 2, init_y + 1).unwrap() = DEAD;
         //Third Row
-        *self.at_mut(init_x, init_y + 2).unwrap() = false;
-        *self.at_mut(init_x + 1, init_y + 2).unwrap() = true;
-        *self.at_mut(init_x + 2, init_y + 2).unwrap() = false;
+        *self.at_mut(init_x, init_y + 2).unwrap() = DEAD;
+        *self.at_mut(init_x + 1, init_y + 2).unwrap() = ALIVE;
+        *self.at_mut(init_x + 2, init_y + 2).unwrap() = DEAD;
         self
     }
 
-    pub fn make_random(mut self, start_point: (i32, i32), width: i32, height: i32) -> Self {
-        let mut rng = rand::thread_rng();
+    /// Stamps a pattern from the library by its file name, e.g. "gosper_glider_gun",
+    /// with its top left corner at (init_x,init_y)
+    pub fn place_named(mut self, name: &str, init_x: i32, init_y: i32) -> Self {
+        let pattern = named(name);
+        // same as the make_ functions, which panic on the first cell that is off the edge
+        let (right, bottom) = (init_x + pattern.width() - 1, init_y + pattern.height() - 1);
+        if !self.in_bounds(init_x, init_y) || !self.in_bounds(right, bottom) {
+            panic!(
+                "{} is {}x{}, which does not fit at ({},{})",
+                name,
+                pattern.width(),
+                pattern.height(),
+                init_x,
+                init_y
+            );
+        }
+        self.paste(&pattern, init_x, init_y);
+        self
+    }
+    /// Live cells of pattern, each (i,j) going to (init_x,init_y) + transform(i,j).
+    /// Only ever turns cells on, so stamps can overlap
+    pub fn stamp(
+        mut self,
+        pattern: &BMatrixVector,
+        init_x: i32,
+        init_y: i32,
+        transform: Transform,
+    ) -> Self {
+        for j in 0..pattern.height() {
+            for i in 0..pattern.width() {
+                if pattern.is_alive(i, j) {
+                    let (x, y) = transform.apply(i, j);
+                    match self.at_mut(init_x + x, init_y + y) {
+                        Ok(cell) => *cell = ALIVE,
+                        Err(err) => panic!("Stamped pattern goes off the edge: {}", err),
+                    }
+                }
+            }
+        }
+        self
+    }
+    /// stamp with a pattern from the library, e.g. a glider turned to head the other way
+    pub fn stamp_named(self, name: &str, init_x: i32, init_y: i32, transform: Transform) -> Self {
+        self.stamp(&named(name), init_x, init_y, transform)
+    }
+    // Still different every run, but the seed gets recorded in the soups so
+    // the run can be reproduced with make_random_seeded
+    pub fn make_random(self, start_point: (i32, i32), width: i32, height: i32) -> Self {
+        let seed = rand::random();
+        self.make_random_seeded(start_point, width, height, seed, 0.5)
+    }
+    pub fn make_random_seeded(
+        self,
+        start_point: (i32, i32),
+        width: i32,
+        height: i32,
+        seed: u64,
+        density: f64,
+    ) -> Self {
+        self.make_symmetric_soup(start_point, width, height, seed, density, Symmetry::C1)
+    }
+    // NOTE: only ever turns cells on, like the other make_ functions, so
+    // whatever is already in the region stays
+    pub fn make_symmetric_soup(
+        mut self,
+        start_point: (i32, i32),
+        width: i32,
+        height: i32,
+        seed: u64,
+        density: f64,
+        symmetry: Symmetry,
+    ) -> Self {
+        if !(0.0..=1.0).contains(&density) {
+            panic!("Soup density has to be between 0 and 1, got {}", density);
+        }
+        if symmetry.needs_square() && width != height {
+            panic!(
+                "{:?} soups have to be square, got {}x{}",
+                symmetry, width, height
+            );
+        }
+        let mut rng = StdRng::seed_from_u64(seed);
+        // what each representative drew, since the cell itself may have been
+        // alive already. C1 soups never look back, so they skip it
+        let mut drawn = if symmetry == Symmetry::C1 {
+            Vec::new()
+        } else {
+            vec![false; (width * height) as usize]
+        };
         for j in 0..height {
             for i in 0..width {
-                if rand::random() {
-                    *self.at_mut(start_point.0 + i, start_point.1 + j).unwrap() = true;
+                let (rep_i, rep_j) = symmetry.representative(i, j, width, height);
+                let rep_idx = (rep_j * width + rep_i) as usize;
+                // rng only gets drawn from for representatives, in row major order,
+                // so a C1 soup is just one draw per cell
+                let alive = if (rep_i, rep_j) == (i, j) {
+                    let alive = rng.gen_bool(density);
+                    if let Some(cell) = drawn.get_mut(rep_idx) {
+                        *cell = alive;
+                    }
+                    alive
+                } else {
+                    drawn[rep_idx]
+                };
+                if alive {
+                    *self.at_mut(start_point.0 + i, start_point.1 + j).unwrap() = ALIVE;
                 }
             }
         }
+        self.soups.push(SoupInfo {
+            seed,
+            density,
+            symmetry,
+            start_point,
+            width,
+            height,
+        });
 
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYMMETRIES: [Symmetry; 5] = [
+        Symmetry::C1,
+        Symmetry::C2,
+        Symmetry::C4,
+        Symmetry::D4,
+        Symmetry::D8,
+    ];
+
+    fn soup(seed: u64, density: f64, symmetry: Symmetry) -> BMatrixVector {
+        PatternBuilder::with_size(24, 24)
+            .make_symmetric_soup((2, 3), 16, 16, seed, density, symmetry)
+            .build()
+    }
+
+    fn live_cells(vec: &BMatrixVector, start_point: (i32, i32), size: i32) -> Vec<(i32, i32)> {
+        let mut cells = Vec::new();
+        for j in 0..size {
+            for i in 0..size {
+                if vec.is_alive(start_point.0 + i, start_point.1 + j) {
+                    cells.push((i, j));
+                }
+            }
+        }
+        cells
+    }
+
+    #[test]
+    fn test_make_random_seeded_same_seed_same_soup() {
+        for &symmetry in SYMMETRIES.iter() {
+            assert!(soup(42, 0.5, symmetry) == soup(42, 0.5, symmetry));
+        }
+    }
+
+    #[test]
+    fn test_make_random_seeded_different_seed_different_soup() {
+        assert!(soup(1, 0.5, Symmetry::C1) != soup(2, 0.5, Symmetry::C1));
+    }
+
+    #[test]
+    fn test_make_random_seeded_density_extremes() {
+        let empty = soup(3, 0.0, Symmetry::C1);
+        assert!(empty.iter().all(|&cell| cell == DEAD));
+        let full = soup(3, 1.0, Symmetry::C1);
+        assert_eq!(full.iter().filter(|&&cell| cell == ALIVE).count(), 16 * 16);
+    }
+
+    #[test]
+    fn test_make_random_seeded_stays_inside_region() {
+        let vec = soup(5, 1.0, Symmetry::D8);
+        for j in 0..24 {
+            for i in 0..24 {
+                let inside = i >= 2 && i < 18 && j >= 3 && j < 19;
+                assert_eq!(vec.is_alive(i, j), inside, "cell ({},{})", i, j);
+            }
+        }
+    }
+
+    #[test]
+    fn test_make_random_seeded_keeps_what_was_there() {
+        let vec = PatternBuilder::with_size(24, 24)
+            .make_square(2, 3)
+            .make_random_seeded((2, 3), 16, 16, 7, 0.0)
+            .build();
+        assert!(vec.is_alive(2, 3) && vec.is_alive(3, 4));
+        // symmetric ones copy what was drawn, not what was there
+        let vec = PatternBuilder::with_size(24, 24)
+            .make_square(2, 3)
+            .make_symmetric_soup((2, 3), 16, 16, 7, 0.0, Symmetry::D8)
+            .build();
+        assert!(!vec.is_alive(17, 18));
+        assert_eq!(vec.iter().filter(|&&cell| cell == ALIVE).count(), 4);
+    }
+
+    #[test]
+    fn test_make_symmetric_soup_is_symmetric() {
+        for &symmetry in SYMMETRIES.iter() {
+            for seed in 0..5 {
+                let vec = soup(seed, 0.5, symmetry);
+                let cells = live_cells(&vec, (2, 3), 16);
+                for &(i, j) in cells.iter() {
+                    for image in symmetry.orbit(i, j, 16, 16) {
+                        assert!(
+                            cells.contains(&image),
+                            "{:?} soup {} has ({},{}) but not {:?}",
+                            symmetry,
+                            seed,
+                            i,
+                            j,
+                            image
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_make_symmetric_soup_rectangular_d4() {
+        let vec = PatternBuilder::with_size(10, 7)
+            .make_symmetric_soup((0, 0), 10, 7, 11, 0.5, Symmetry::D4)
+            .build();
+        for j in 0..7 {
+            for i in 0..10 {
+                assert_eq!(vec.at(i, j).unwrap(), vec.at(9 - i, j).unwrap());
+                assert_eq!(vec.at(i, j).unwrap(), vec.at(i, 6 - j).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_make_symmetric_soup_c4_needs_square() {
+        PatternBuilder::with_size(20, 20).make_symmetric_soup((0, 0), 10, 8, 0, 0.5, Symmetry::C4);
+    }
+
+    #[test]
+    fn test_make_random_soup_info_reproduces_soup() {
+        let (vec, soups) = PatternBuilder::with_size(30, 30)
+            .make_random((4, 4), 20, 20)
+            .build_with_soups();
+        assert_eq!(soups.len(), 1);
+        let info = soups[0];
+        assert_eq!(info.density, 0.5);
+        assert_eq!(info.symmetry, Symmetry::C1);
+        let reproduced = PatternBuilder::with_size(30, 30)
+            .make_symmetric_soup(
+                info.start_point,
+                info.width,
+                info.height,
+                info.seed,
+                info.density,
+                info.symmetry,
+            )
+            .build();
+        assert!(vec == reproduced);
+    }
+
+    #[test]
+    fn test_PatternBuilder_place_named() {
+        let vec = PatternBuilder::with_size(50, 20)
+            .place_named("gosper_glider_gun", 10, 5)
+            .build();
+        assert_eq!(
+            vec.bounding_box(),
+            Some(BoundingBox { x: 10, y: 5, width: 36, height: 9 })
+        );
+        let glider = PatternBuilder::with_size(3, 3).place_named("glider", 0, 0).build();
+        assert!(glider == crate::rle::parse("x = 3, y = 3\nbo$2bo$3o!").unwrap());
+    }
+
+    #[should_panic(expected = "No pattern called breeder")]
+    #[test]
+    fn test_PatternBuilder_place_named_unknown() {
+        PatternBuilder::with_size(10, 10).place_named("breeder", 0, 0);
+    }
+
+    const MIRRORS: [Mirror; 4] = [
+        Mirror::Vertical,
+        Mirror::Horizontal,
+        Mirror::Diagonal,
+        Mirror::AntiDiagonal,
+    ];
+
+    #[test]
+    fn test_Transform_compose() {
+        assert_eq!(Transform::rotate_clockwise(1).apply(1, 0), (0, 1));
+        assert_eq!(Transform::rotate_clockwise(4), Transform::IDENTITY);
+        assert_eq!(Transform::rotate_clockwise(-1), Transform::rotate_clockwise(3));
+        assert_eq!(
+            Transform::reflect(Mirror::Vertical).then(Transform::reflect(Mirror::Horizontal)),
+            Transform::rotate_clockwise(2)
+        );
+        for &mirror in MIRRORS.iter() {
+            assert_eq!(Transform::reflect(mirror).then(Transform::reflect(mirror)), Transform::IDENTITY);
+            let glide = Transform::glide_reflect(mirror, 3);
+            // twice over is a plain shift, twice as far along the mirror
+            let (dx, dy) = glide.then(glide).apply(0, 0);
+            assert_eq!(glide.then(glide), Transform::translate(dx, dy));
+            assert_eq!((dx, dy), (2 * glide.apply(0, 0).0, 2 * glide.apply(0, 0).1));
+        }
+        // shifts get turned along with everything else
+        assert_eq!(
+            Transform::translate(2, 0).then(Transform::rotate_clockwise(1)).apply(0, 0),
+            (0, 2)
+        );
+        let glide = Transform::glide_reflect(Mirror::Horizontal, 4);
+        assert_eq!(glide.then(glide), Transform::translate(8, 0));
+    }
+
+    #[test]
+    fn test_PatternBuilder_stamp_gliders_head_every_way() {
+        let glider = PatternBuilder::with_size(3, 3).make_glider(0, 0).build();
+        let mut orientations: Vec<Transform> = (0..4).map(Transform::rotate_clockwise).collect();
+        orientations.extend(MIRRORS.iter().map(|&mirror| Transform::reflect(mirror)));
+        for transform in orientations {
+            let start = PatternBuilder::with_size(20, 20)
+                .stamp(&glider, 10, 10, transform)
+                .build();
+            let (mut next, mut scratch) = (start.clone(), start.clone());
+            create_engine(Backend::Single).step_n(4, &start, &mut next, &mut scratch);
+            let (before, after) = (start.bounding_box().unwrap(), next.bounding_box().unwrap());
+            // make_glider heads up and to the left
+            let (origin_x, origin_y) = transform.apply(0, 0);
+            let heading = transform.apply(-1, -1);
+            assert_eq!(
+                (after.x - before.x, after.y - before.y),
+                (heading.0 - origin_x, heading.1 - origin_y),
+                "{:?}",
+                transform
+            );
+            assert_eq!(next.population(), 5);
+        }
+    }
+
+    #[test]
+    fn test_tile() {
+        let block = PatternBuilder::with_size(2, 2).make_square(0, 0).build();
+        let array = tile(&block, 5, 4, 3, 2);
+        assert_eq!((array.width(), array.height(), array.population()), (12, 6, 24));
+        assert!(array.is_alive(10, 4) && !array.is_alive(2, 0));
+        // laid out the other way, the copies are all the same so nothing changes
+        assert!(tile(&block, -5, -4, 3, 2) == array);
+        // overlapping copies
+        assert_eq!(tile(&block, 1, 0, 2, 1).population(), 6);
+
+        // a row of gliders, all turned to head down and to the right
+        let glider = PatternBuilder::with_size(3, 3).make_glider(0, 0).build();
+        let fleet = PatternBuilder::with_size(40, 10)
+            .stamp(&tile(&glider, 10, 0, 3, 1), 25, 5, Transform::rotate_clockwise(2))
+            .build();
+        assert_eq!(fleet.population(), 15);
+        assert_eq!(fleet.bounding_box(), Some(BoundingBox { x: 3, y: 3, width: 23, height: 3 }));
+    }
+
+    #[should_panic(expected = "goes off the edge")]
+    #[test]
+    fn test_PatternBuilder_stamp_off_the_edge() {
+        PatternBuilder::with_size(10, 10).stamp_named("glider", 1, 1, Transform::rotate_clockwise(1));
+    }
+
+}
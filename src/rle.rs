@@ -0,0 +1,299 @@
+// Run Length Encoded patterns, the plain text format Golly, LifeWiki and
+// Catagolue all use (see https://conwaylife.com/wiki/Run_Length_Encoded).
+// Two state rules use b and o for cells, the rest . for dead and A to X,
+// then pA to yO, for states 1 to 255
+use super::*;
+
+// Golly and LifeWiki keep lines under 70 characters
+const MAX_LINE_LENGTH: usize = 70;
+
+fn parse_error(line: usize, message: String) -> LifeError {
+    LifeError::Parse { line, message }
+}
+
+// states written as one letter, past that they get a prefix
+const LETTERS: u32 = 24;
+
+// width, height and the rule if there is one
+type Header = (i32, i32, Option<Arc<dyn Rule>>);
+
+// "x = 3, y = 3, rule = B3/S23", rule is optional
+fn parse_header(header: &str, line: usize) -> LifeResult<Header> {
+    let (mut width, mut height, mut rule) = (None, None, None);
+    // the rule comes last and can have commas of its own, like R5,C0,M1,S34..58,B34..45,NM
+    let (sizes, rule_field) = match header.find("rule") {
+        Some(at) => (header[..at].trim_end().trim_end_matches(','), Some(&header[at..])),
+        None => (header, None),
+    };
+    let mut fields: Vec<&str> = if sizes.trim().is_empty() { Vec::new() } else { sizes.split(',').collect() };
+    fields.extend(rule_field);
+    for field in fields {
+        let mut parts = field.splitn(2, '=');
+        let key = parts.next().unwrap_or("").trim();
+        let value = parts
+            .next()
+            .ok_or_else(|| parse_error(line, format!("expected key = value, got '{}'", field.trim())))?
+            .trim();
+        let parse_size = |value: &str| {
+            value
+                .parse::<i32>()
+                .ok()
+                .filter(|&size| size >= 0)
+                .ok_or_else(|| parse_error(line, format!("{} is not a valid size", value)))
+        };
+        match key {
+            "x" => width = Some(parse_size(value)?),
+            "y" => height = Some(parse_size(value)?),
+            "rule" => {
+                rule = Some(
+                    parse_rule(value)
+                        .map_err(|_| parse_error(line, format!("rule {} is not supported", value)))?,
+                )
+            }
+            _ => return Err(parse_error(line, format!("unknown header field '{}'", key))),
+        }
+    }
+    match (width, height) {
+        (Some(width), Some(height)) => Ok((width, height, rule)),
+        _ => Err(parse_error(line, "header needs both x and y".to_string())),
+    }
+}
+
+/// Pattern as its own x by y matrix. Comment lines(#) are skipped
+pub fn parse(text: &str) -> LifeResult<BMatrixVector> {
+    parse_with_rule(text).map(|(pattern, _)| pattern)
+}
+
+/// parse, along with the rule from the header if it has one
+pub fn parse_with_rule(text: &str) -> LifeResult<(BMatrixVector, Option<Arc<dyn Rule>>)> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+    let (header_line, header) = lines
+        .next()
+        .ok_or_else(|| parse_error(1, "no header line".to_string()))?;
+    let (width, height, rule) = parse_header(header, header_line)?;
+    let states = rule.as_ref().map_or(2, |rule| rule.states());
+
+    let mut pattern = BMatrixVector::new(width, height);
+    let (mut i, mut j) = (0, 0);
+    let mut run: Option<i32> = None;
+    // p to y in front of a state letter
+    let mut prefix: Option<u32> = None;
+    for (line, text) in lines {
+        for c in text.chars() {
+            let state = match (prefix, c) {
+                (None, 'b') | (None, '.') => Some(DEAD as u32),
+                (None, 'o') => Some(ALIVE as u32),
+                (_, 'A'..='X') => Some(prefix.take().unwrap_or(0) * LETTERS + c as u32 - 'A' as u32 + 1),
+                (None, 'p'..='y') => {
+                    prefix = Some(c as u32 - 'p' as u32 + 1);
+                    continue;
+                }
+                (Some(_), c) => return Err(parse_error(line, format!("unexpected '{}' after a state prefix", c))),
+                _ => None,
+            };
+            if let Some(state) = state {
+                let count = run.take().unwrap_or(1);
+                if i + count > width || j >= height {
+                    return Err(parse_error(
+                        line,
+                        format!("pattern does not fit in x = {}, y = {}", width, height),
+                    ));
+                }
+                if state as usize >= states {
+                    return Err(parse_error(
+                        line,
+                        format!("state {} is more than the rule has", state),
+                    ));
+                }
+                let start = (j * width + i) as usize;
+                for cell in pattern[start..start + count as usize].iter_mut() {
+                    *cell = state as CellState;
+                }
+                i += count;
+                continue;
+            }
+            match c {
+                '0'..='9' => {
+                    let digit = c.to_digit(10).unwrap() as i32;
+                    run = run
+                        .unwrap_or(0)
+                        .checked_mul(10)
+                        .and_then(|run| run.checked_add(digit));
+                    if run.is_none() {
+                        return Err(parse_error(line, "run count is too large".to_string()));
+                    }
+                }
+                '$' => {
+                    j += run.take().unwrap_or(1);
+                    i = 0;
+                }
+                '!' => return Ok((pattern, rule)),
+                c if c.is_whitespace() => (),
+                c => return Err(parse_error(line, format!("unexpected '{}'", c))),
+            }
+        }
+    }
+    // plenty of patterns pasted from forums lose their last line, so no ! is fine
+    Ok((pattern, rule))
+}
+
+// "3o", "o", "" for no cells
+fn push_run(runs: &mut Vec<String>, count: i32, tag: &str) {
+    match count {
+        0 => (),
+        1 => runs.push(tag.to_string()),
+        count => runs.push(format!("{}{}", count, tag)),
+    }
+}
+
+fn state_tag(state: CellState, states: usize) -> String {
+    let state = state as u32;
+    match (states, state) {
+        (2, 0) => "b".to_string(),
+        (2, _) => "o".to_string(),
+        (_, 0) => ".".to_string(),
+        _ => {
+            let (prefix, letter) = ((state - 1) / LETTERS, (state - 1) % LETTERS);
+            let letter = std::char::from_u32('A' as u32 + letter).unwrap();
+            match prefix {
+                0 => letter.to_string(),
+                prefix => format!("{}{}", std::char::from_u32('p' as u32 + prefix - 1).unwrap(), letter),
+            }
+        }
+    }
+}
+
+/// Inverse of parse, with rule going in the header. Dead cells at the end of
+/// a row and empty rows at the bottom are left out, like Golly does
+pub fn write(pattern: &BMatrixVector, rule: &dyn Rule) -> String {
+    let mut runs = Vec::new();
+    // every row before this one still needs ending, even the empty ones
+    let mut rows_ended = 0;
+    for j in 0..pattern.height() {
+        let row = pattern.row(j);
+        let last_alive = match row.iter().rposition(|&cell| cell != DEAD) {
+            Some(last_alive) => last_alive,
+            None => continue,
+        };
+        push_run(&mut runs, j - rows_ended, "$");
+        rows_ended = j;
+        let mut start = 0;
+        while start <= last_alive {
+            let state = row[start];
+            let length = row[start..].iter().take_while(|&&cell| cell == state).count();
+            push_run(&mut runs, length as i32, &state_tag(state, rule.states()));
+            start += length;
+        }
+    }
+    runs.push("!".to_string());
+
+    let mut rle = format!(
+        "x = {}, y = {}, rule = {}\n",
+        pattern.width(),
+        pattern.height(),
+        rule
+    );
+    let mut line_length = 0;
+    for run in runs {
+        if line_length + run.len() > MAX_LINE_LENGTH {
+            rle.push('\n');
+            line_length = 0;
+        }
+        line_length += run.len();
+        rle.push_str(&run);
+    }
+    rle.push('\n');
+    rle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::PatternBuilder;
+
+    #[test]
+    fn test_rle_parse_glider() {
+        let glider = parse("#N Glider\n#C comments are skipped\nx = 3, y = 3, rule = B3/S23\n3o$o$bo!").unwrap();
+        assert!(glider == PatternBuilder::with_size(3, 3).make_glider(0, 0).build());
+        // no rule, and rows split across lines
+        let glider = parse("x = 3, y = 3\n3o$o$\nbo!").unwrap();
+        assert!(glider == PatternBuilder::with_size(3, 3).make_glider(0, 0).build());
+    }
+
+    #[test]
+    fn test_rle_round_trip() {
+        let (pattern, _) = PatternBuilder::with_size(100, 40)
+            .make_random_seeded((3, 2), 90, 30, 31337, 0.4)
+            .build_with_soups();
+        let rle = write(&pattern, &Generations::life());
+        assert!(rle.lines().all(|line| line.len() <= MAX_LINE_LENGTH));
+        assert!(parse(&rle).unwrap() == pattern);
+
+        // empty rows at the top and in the middle
+        let mut sparse = BMatrixVector::new(5, 7);
+        *sparse.at_mut(4, 1).unwrap() = ALIVE;
+        sparse.paste(&PatternBuilder::with_size(1, 3).make_blinker(0, 0).build(), 0, 4);
+        let rle = write(&sparse, &Generations::life());
+        assert_eq!(rle, "x = 5, y = 7, rule = B3/S23\n$4bo3$o$o$o!\n");
+        assert!(parse(&rle).unwrap() == sparse);
+    }
+
+    #[test]
+    fn test_rle_parse_errors_have_line_numbers() {
+        let message = |text| match parse(text) {
+            Err(LifeError::Parse { line, message }) => (line, message),
+            _ => panic!("expected a parse error"),
+        };
+        assert_eq!(
+            message("#C\nx = 3, y = 3, rule = B39/S23\n3o!"),
+            (2, "rule B39/S23 is not supported".to_string())
+        );
+        assert_eq!(
+            message("x = 3, y = 3\n3o$\n4o!"),
+            (3, "pattern does not fit in x = 3, y = 3".to_string())
+        );
+        assert_eq!(message("x = 3, y = 3\n3o$2z!"), (2, "unexpected 'z'".to_string()));
+        assert_eq!(message("y = 3\n3o!").1, "header needs both x and y");
+        assert_eq!(message("x = 3, y = 1\n2AB!"), (2, "state 2 is more than the rule has".to_string()));
+        assert_eq!(message("x = 3, y = 1, rule = B2/S/C3\npo!").1, "unexpected 'o' after a state prefix");
+    }
+
+    #[test]
+    fn test_rle_multi_state() {
+        // Brian's Brain, with a dying cell in the middle
+        let (pattern, rule) = parse_with_rule("x = 4, y = 2, rule = B2/S/C3\nABA$.2A!").unwrap();
+        let rule = rule.unwrap();
+        assert_eq!((rule.states(), rule.to_string()), (3, "B2/S/C3".to_string()));
+        assert_eq!(pattern.row(0), &[ALIVE, 2, ALIVE, DEAD][..]);
+        assert_eq!(pattern.row(1), &[DEAD, ALIVE, ALIVE, DEAD][..]);
+        assert_eq!(write(&pattern, &*rule), "x = 4, y = 2, rule = B2/S/C3\nABA$.2A!\n");
+
+        // states past X get a prefix
+        let many = Generations::new(&[2], &[], 256);
+        let mut vec = BMatrixVector::new(4, 1);
+        vec.copy_from_slice(&[24, 25, 49, 255]);
+        let rle = write(&vec, &many);
+        assert_eq!(rle, "x = 4, y = 1, rule = B2/S/C256\nXpAqAyO!\n");
+        assert!(parse(&rle).unwrap() == vec);
+        // rule tables work the same way
+        let (pattern, rule) = parse_with_rule("x = 3, y = 1, rule = WireWorld\nCAB!").unwrap();
+        assert_eq!(rule.unwrap().to_string(), "WireWorld");
+        assert_eq!(pattern.row(0), &[3, 1, 2][..]);
+        // a rule in the header is not needed to read it, but plain Life only goes up to A
+        assert!(parse("x = 1, y = 1, rule = B3/S23\nA!").unwrap().is_alive(0, 0));
+    }
+
+    #[test]
+    fn test_rle_rule_with_commas() {
+        let rle = "x = 2, y = 1, rule = R5,C0,M1,S34..58,B34..45,NM\n2o!\n";
+        let (pattern, rule) = parse_with_rule(rle).unwrap();
+        let rule = rule.unwrap();
+        assert_eq!(rule.to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+        assert_eq!(pattern.population(), 2);
+        assert_eq!(write(&pattern, &*rule), rle);
+    }
+}
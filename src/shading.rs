@@ -0,0 +1,224 @@
+// What color each cell gets drawn in. FSubview draws one color per index, so
+// for ages and activity the index is a step along a gradient rather than a state
+use super::*;
+use std::fmt;
+
+// steps along each gradient. Ages go up in powers of 2, so the last step is
+// anything 2^(AGE_STEPS - 1) generations or older
+const AGE_STEPS: usize = 12;
+// white when just born, going red as it settles down into ash
+const BORN_TO_ASH: [(u8, u8, u8); 4] = [(255, 255, 255), (255, 220, 0), (255, 80, 0), (120, 0, 40)];
+// just died, fading out to dead
+const TRAIL: [(u8, u8, u8); 2] = [(80, 150, 255), (0, 0, 40)];
+// changed once, on up to changed every generation
+const HEAT: [(u8, u8, u8); 4] = [(60, 0, 0), (220, 30, 0), (255, 200, 0), (255, 255, 255)];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shading {
+    /// In the rule's colors, dead and alive from the theme, and cells born
+    /// or died this generation picked out if the theme says so
+    States,
+    /// Live cells by how long they have been alive, dead ones by how long ago
+    /// they were last alive
+    Age,
+    /// How many of the last this many generations each cell changed in
+    Activity(u32),
+}
+
+impl Shading {
+    /// The one after this, for cycling through them with a key
+    pub fn next(self, activity_window: u32) -> Shading {
+        match self {
+            Shading::States => Shading::Age,
+            Shading::Age => Shading::Activity(activity_window),
+            Shading::Activity(_) => Shading::States,
+        }
+    }
+
+    /// Colors for FSubview::set_colors, the theme's dead first
+    pub fn palette(self, theme: &Theme, rule_colors: &[(u8, u8, u8)]) -> Vec<(u8, u8, u8)> {
+        match self {
+            Shading::States => theme.palette(rule_colors),
+            Shading::Age => {
+                let mut colors = vec![theme.dead];
+                colors.extend((0..AGE_STEPS).map(|step| gradient(&TRAIL, step, AGE_STEPS)));
+                colors.extend((0..AGE_STEPS).map(|step| gradient(&BORN_TO_ASH, step, AGE_STEPS)));
+                colors
+            }
+            Shading::Activity(window) => {
+                let window = window.min(MAX_ACTIVITY_WINDOW) as usize;
+                let mut colors = vec![theme.dead];
+                colors.extend((0..window).map(|step| gradient(&HEAT, step, window)));
+                colors
+            }
+        }
+    }
+
+    /// Which of the palette's colors (i, j) gets, under a rule with that many
+    /// states. Until the worker catches up with tracking being turned on,
+    /// everything alive is a newborn
+    pub fn color_of(self, snapshot: &Snapshot, i: i32, j: i32, states: usize) -> CellState {
+        match self {
+            Shading::States => {
+                let state = snapshot.vec.state(i, j);
+                let before = match snapshot.alive_before.as_ref() {
+                    Some(before) => before,
+                    None => return state,
+                };
+                // the theme's born and died come straight after the states, if
+                // there is room for them
+                if states + 2 > MAX_STATES {
+                    return state;
+                }
+                match state {
+                    ALIVE if !before.was_alive(i, j) => states as CellState,
+                    DEAD if before.was_alive(i, j) => (states + 1) as CellState,
+                    _ => state,
+                }
+            }
+            Shading::Age => {
+                let alive = snapshot.vec.state(i, j) != DEAD;
+                let ages = match snapshot.ages.as_ref() {
+                    Some(ages) => ages,
+                    None => return if alive { 1 + AGE_STEPS as CellState } else { 0 },
+                };
+                if let Some(age) = ages.since_birth(i, j) {
+                    (1 + AGE_STEPS + age_step(age)) as CellState
+                } else {
+                    match ages.since_alive(i, j).map(age_step) {
+                        // the trail ends a step early, at black
+                        Some(step) if step < AGE_STEPS - 1 => (1 + step) as CellState,
+                        _ => 0,
+                    }
+                }
+            }
+            Shading::Activity(window) => {
+                let changes = snapshot.activity.as_ref().map_or(0, |activity| activity.changes(i, j));
+                changes.min(window.min(MAX_ACTIVITY_WINDOW)) as CellState
+            }
+        }
+    }
+
+    /// Whether the worker has to keep track of ages to draw it
+    pub fn needs_ages(self) -> bool {
+        self == Shading::Age
+    }
+
+    /// Whether the worker has to remember the generation before each
+    /// snapshot, for the theme's born and died colors
+    pub fn needs_alive_before(self, theme: &Theme) -> bool {
+        self == Shading::States && theme.marks_changes()
+    }
+}
+
+impl fmt::Display for Shading {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Shading::States => write!(f, "states"),
+            Shading::Age => write!(f, "age"),
+            Shading::Activity(window) => write!(f, "activity over {} generations", window),
+        }
+    }
+}
+
+// 0 for 0, then one step for every doubling
+fn age_step(age: u32) -> usize {
+    ((32 - (age + 1).leading_zeros() - 1) as usize).min(AGE_STEPS - 1)
+}
+
+// step of steps evenly along the stops, first to last
+fn gradient(stops: &[(u8, u8, u8)], step: usize, steps: usize) -> (u8, u8, u8) {
+    let along = if steps <= 1 { 0.0 } else { step as f32 / (steps - 1) as f32 } * (stops.len() - 1) as f32;
+    let from = (along.floor() as usize).min(stops.len() - 2);
+    let fade = along - from as f32;
+    let (a, b) = (stops[from], stops[from + 1]);
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * fade).round() as u8;
+    (mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::PatternBuilder;
+
+    #[test]
+    fn test_gradient_hits_its_stops() {
+        assert_eq!(gradient(&HEAT, 0, 7), HEAT[0]);
+        assert_eq!(gradient(&HEAT, 2, 7), HEAT[1]);
+        assert_eq!(gradient(&HEAT, 6, 7), HEAT[3]);
+        assert_eq!(gradient(&TRAIL, 0, 1), TRAIL[0]);
+    }
+
+    #[test]
+    fn test_age_step_doubles() {
+        let steps: Vec<usize> = [0, 1, 2, 3, 6, 7, 100_000].iter().map(|&age| age_step(age)).collect();
+        assert_eq!(steps, vec![0, 1, 1, 2, 2, 3, AGE_STEPS - 1]);
+    }
+
+    #[test]
+    fn test_Shading_palette_covers_every_color() {
+        let (theme, life) = (Theme::classic(), default_colors(2));
+        assert_eq!(Shading::States.palette(&theme, &life), theme.palette(&life));
+        assert_eq!(Shading::Age.palette(&theme, &life).len(), 1 + 2 * AGE_STEPS);
+        assert_eq!(Shading::Activity(16).palette(&theme, &life).len(), 17);
+        assert_eq!(Shading::Activity(1000).palette(&theme, &life).len(), 1 + MAX_ACTIVITY_WINDOW as usize);
+        assert_eq!(Shading::States.next(8).next(8).next(8), Shading::States);
+    }
+
+    #[test]
+    fn test_Shading_color_of_ages_and_activity() {
+        let vec = PatternBuilder::with_size(8, 8).make_square(1, 1).build();
+        let mut ages = CellAges::new();
+        ages.reset(&vec);
+        let mut activity = Activity::new(4);
+        activity.reset(&BMatrixVector::new(8, 8));
+        for _ in 0..3 {
+            ages.observe(&vec);
+            activity.observe(&vec);
+        }
+        let snapshot = Snapshot {
+            vec: vec.clone(),
+            ages: Some(Arc::new(ages)),
+            activity: Some(Arc::new(activity)),
+            ..Snapshot::empty()
+        };
+        assert_eq!(Shading::States.color_of(&snapshot, 1, 1, 2), ALIVE);
+        // 3 generations old
+        assert_eq!(Shading::Age.color_of(&snapshot, 1, 1, 2) as usize, 1 + AGE_STEPS + 2);
+        assert_eq!(Shading::Age.color_of(&snapshot, 5, 5, 2), 0);
+        // came alive once in the window
+        assert_eq!(Shading::Activity(4).color_of(&snapshot, 1, 1, 2), 1);
+        assert_eq!(Shading::Activity(4).color_of(&snapshot, 5, 5, 2), 0);
+
+        // nothing from the worker yet
+        let untracked = Snapshot { vec, ..Snapshot::empty() };
+        assert_eq!(Shading::Age.color_of(&untracked, 1, 1, 2) as usize, 1 + AGE_STEPS);
+        assert_eq!(Shading::Activity(4).color_of(&untracked, 1, 1, 2), 0);
+    }
+
+    #[test]
+    fn test_Shading_states_mark_births_and_deaths() {
+        // vertical blinker, just turned horizontal
+        let before = PatternBuilder::with_size(8, 8).make_blinker(3, 2).build();
+        let mut after = before.clone();
+        create_engine(Backend::Single).next_b_matrix(&before, &mut after);
+        let mut alive_before = AliveBefore::default();
+        alive_before.record(&before);
+        let snapshot = Snapshot {
+            vec: after,
+            alive_before: Some(alive_before),
+            ..Snapshot::empty()
+        };
+        // born, survived, died and never alive, under a 2 state rule
+        assert_eq!(Shading::States.color_of(&snapshot, 2, 3, 2), 2);
+        assert_eq!(Shading::States.color_of(&snapshot, 3, 3, 2), ALIVE);
+        assert_eq!(Shading::States.color_of(&snapshot, 3, 2, 2), 3);
+        assert_eq!(Shading::States.color_of(&snapshot, 0, 0, 2), DEAD);
+        assert!(!Shading::States.needs_ages());
+        assert!(Shading::Age.needs_ages());
+        let marked = Theme { born: Some((0, 255, 0)), ..Theme::classic() };
+        assert!(Shading::States.needs_alive_before(&marked));
+        assert!(!Shading::States.needs_alive_before(&Theme::classic()));
+        assert!(!Shading::Age.needs_alive_before(&marked));
+    }
+}
@@ -0,0 +1,243 @@
+// Which colors everything gets drawn in. Themes are .theme files under
+// resources/themes, bundled like the pattern library, and any dropped in there
+// show up the next time it starts. Each line is `key = color`, the color either
+// #rrggbb or r g b, and anything left out is whatever classic has
+use super::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+macro_rules! bundled {
+    ($($name:literal),* $(,)?) => {
+        &[$(($name, include_str!(concat!("../resources/themes/", $name, ".theme")))),*]
+    };
+}
+
+// (name, theme file), classic first since the rest fill in from it
+const BUNDLED: &[(&str, &str)] = bundled!["classic", "dark", "high_contrast", "colorblind"];
+
+pub fn themes_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("resources").join("themes")
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    /// file name without the .theme
+    pub name: String,
+    /// behind the cells, so it is also the gap between them
+    pub background: (u8, u8, u8),
+    pub dead: (u8, u8, u8),
+    pub alive: (u8, u8, u8),
+    /// cells born or died this generation. None draws them as alive or dead,
+    /// which saves the worker remembering the generation before, 1 bit a cell
+    /// in every snapshot
+    pub born: Option<(u8, u8, u8)>,
+    pub died: Option<(u8, u8, u8)>,
+    pub selection: (u8, u8, u8),
+    /// around where the browser would stamp its pattern
+    pub outline: (u8, u8, u8),
+}
+
+impl Theme {
+    pub fn classic() -> Theme {
+        // the tests make sure these all parse
+        Theme::parse_over(&CLASSIC_FALLBACK, BUNDLED[0].0, BUNDLED[0].1).expect("bundled theme does not parse")
+    }
+
+    /// A theme file, with anything it leaves out taken from classic
+    pub fn parse(name: &str, text: &str) -> LifeResult<Theme> {
+        Theme::parse_over(&Theme::classic(), name, text)
+    }
+
+    fn parse_over(base: &Theme, name: &str, text: &str) -> LifeResult<Theme> {
+        let mut theme = Theme {
+            name: name.to_string(),
+            ..base.clone()
+        };
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parse_error = |message: String| LifeError::Parse { line: idx + 1, message };
+            let (key, value) = match line.find('=') {
+                Some(at) => (line[..at].trim(), line[at + 1..].trim()),
+                None => return Err(parse_error(format!("expected key = color, got {:?}", line))),
+            };
+            let color = parse_color(value).ok_or_else(|| parse_error(format!("{:?} is not a color", value)))?;
+            match key {
+                "background" => theme.background = color,
+                "dead" => theme.dead = color,
+                "alive" => theme.alive = color,
+                "born" => theme.born = Some(color),
+                "died" => theme.died = Some(color),
+                "selection" => theme.selection = color,
+                "outline" => theme.outline = color,
+                _ => return Err(parse_error(format!("unknown color {:?}", key))),
+            }
+        }
+        Ok(theme)
+    }
+
+    /// A .theme file anywhere
+    pub fn load(path: &Path) -> LifeResult<Theme> {
+        let name = path.file_stem().map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+        Theme::parse(&name, &fs::read_to_string(path)?)
+    }
+
+    /// Whether drawing it needs the worker to remember the generation before
+    pub fn marks_changes(&self) -> bool {
+        self.born.is_some() || self.died.is_some()
+    }
+
+    /// The rule's colors with dead and alive swapped for the theme's, then the
+    /// born and died colors, so a rule with n states takes n + 2 colors
+    pub fn palette(&self, rule_colors: &[(u8, u8, u8)]) -> Vec<(u8, u8, u8)> {
+        let mut colors = rule_colors.to_vec();
+        colors[0] = self.dead;
+        if colors.len() > 1 {
+            colors[1] = self.alive;
+        }
+        colors.push(self.born.unwrap_or(self.alive));
+        colors.push(self.died.unwrap_or(self.dead));
+        colors
+    }
+}
+
+// only there for classic to fill in from, every color is in its file
+const CLASSIC_FALLBACK: Theme = Theme {
+    name: String::new(),
+    background: (128, 128, 128),
+    dead: (0, 0, 0),
+    alive: (255, 255, 255),
+    born: None,
+    died: None,
+    selection: (255, 217, 0),
+    outline: (255, 255, 255),
+};
+
+/// #rrggbb or r g b
+fn parse_color(text: &str) -> Option<(u8, u8, u8)> {
+    if let Some(hex) = text.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |at: usize| u8::from_str_radix(&hex[at..at + 2], 16).ok();
+        return Some((channel(0)?, channel(2)?, channel(4)?));
+    }
+    let channels: Vec<u8> = text
+        .split_whitespace()
+        .map(|channel| channel.parse().ok())
+        .collect::<Option<_>>()?;
+    match channels[..] {
+        [r, g, b] => Some((r, g, b)),
+        _ => None,
+    }
+}
+
+/// Every bundled theme, with any in themes_dir added on or replacing them by
+/// name. Files that don't parse get skipped
+pub fn load_themes() -> Vec<Theme> {
+    let mut themes: Vec<Theme> = BUNDLED
+        .iter()
+        .map(|&(name, text)| Theme::parse(name, text).expect("bundled theme does not parse"))
+        .collect();
+    let files = match fs::read_dir(themes_dir()) {
+        Ok(files) => files,
+        Err(_) => return themes,
+    };
+    let mut paths: Vec<PathBuf> = files.filter_map(Result::ok).map(|entry| entry.path()).collect();
+    paths.sort();
+    for path in paths.into_iter().filter(|path| path.extension() == Some("theme".as_ref())) {
+        match Theme::load(&path) {
+            Ok(theme) => match themes.iter_mut().find(|existing| existing.name == theme.name) {
+                Some(existing) => *existing = theme,
+                None => themes.push(theme),
+            },
+            Err(err) => eprintln!("Skipping {}: {}", path.display(), err),
+        }
+    }
+    themes
+}
+
+/// Where name_or_path is in themes, loading it first if it is the path to a
+/// .theme file
+pub fn find_theme(themes: &mut Vec<Theme>, name_or_path: &str) -> LifeResult<usize> {
+    if name_or_path.ends_with(".theme") {
+        themes.push(Theme::load(Path::new(name_or_path))?);
+        return Ok(themes.len() - 1);
+    }
+    themes
+        .iter()
+        .position(|theme| theme.name == name_or_path)
+        .ok_or_else(|| LifeError::InvalidConfig(format!("there is no theme called {:?}", name_or_path)))
+}
+
+/// For ggez, which wants them as floats
+pub fn to_color((r, g, b): (u8, u8, u8)) -> graphics::Color {
+    graphics::Color::from_rgb(r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_Theme_bundled_themes_all_parse() {
+        for &(name, text) in BUNDLED.iter() {
+            let theme = Theme::parse(name, text).unwrap();
+            assert_eq!(theme.name, name);
+        }
+        // classic is the black and white from before there were themes
+        let classic = Theme::classic();
+        assert_eq!(classic, Theme { name: "classic".to_string(), ..CLASSIC_FALLBACK });
+        assert!(!classic.marks_changes());
+        let names: Vec<String> = load_themes().into_iter().map(|theme| theme.name).collect();
+        assert_eq!(&names[..4], &["classic", "dark", "high_contrast", "colorblind"]);
+    }
+
+    #[test]
+    fn test_Theme_parse() {
+        let theme = Theme::parse(
+            "mine",
+            "# comments and blank lines are fine\n\nalive = #FF8000\n  born=0 128 255\n",
+        )
+        .unwrap();
+        assert_eq!(theme.alive, (255, 128, 0));
+        assert_eq!(theme.born, Some((0, 128, 255)));
+        assert_eq!(theme.died, None);
+        // the rest is classic
+        assert_eq!(theme.background, Theme::classic().background);
+        assert!(theme.marks_changes());
+
+        for bad in ["alive #ffffff", "alive = #fffff", "alive = 1 2", "alive = 1 2 300", "glow = #ffffff"].iter() {
+            match Theme::parse("bad", &format!("# bad\n{}", bad)) {
+                Err(LifeError::Parse { line, .. }) => assert_eq!(line, 2, "{}", bad),
+                other => panic!("{} gave {:?}", bad, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_Theme_palette() {
+        let mut theme = Theme::classic();
+        theme.dead = (1, 2, 3);
+        // Brian's Brain keeps its dying color
+        let rule_colors = default_colors(3);
+        assert_eq!(
+            theme.palette(&rule_colors),
+            vec![(1, 2, 3), (255, 255, 255), rule_colors[2], (255, 255, 255), (1, 2, 3)]
+        );
+        theme.born = Some((0, 255, 0));
+        assert_eq!(theme.palette(&default_colors(2))[2], (0, 255, 0));
+    }
+
+    #[test]
+    fn test_find_theme() {
+        let mut themes = load_themes();
+        assert_eq!(find_theme(&mut themes, "dark").unwrap(), 1);
+        assert!(find_theme(&mut themes, "neon").is_err());
+        let path = themes_dir().join("dark.theme");
+        let idx = find_theme(&mut themes, path.to_str().unwrap()).unwrap();
+        assert_eq!(themes[idx], themes[1]);
+    }
+}
@@ -0,0 +1,453 @@
+// Follows spaceships from generation to generation, so they can be found and
+// followed without scrolling around looking for them
+use super::*;
+use crate::census::{bounding_box_of, classify_up_to, connected_components, ObjectKind};
+use std::collections::HashMap;
+use std::fmt;
+
+// Anything bigger or slower is debris, and classifying it every generation would be too slow
+const MAX_SHIP_POPULATION: usize = 64;
+const MAX_SHIP_SIZE: i32 = 24;
+const MAX_SHIP_PERIOD: u32 = 16;
+// a ship's bounding box wobbles a little between phases on top of actually moving
+const MATCH_SLACK: i32 = 2;
+// only the most recent events are kept around
+const MAX_EVENTS: usize = 32;
+// a soup throws up more small shapes than is worth remembering, so past this
+// the cache starts over
+const MAX_CACHED_SHAPES: usize = 4096;
+
+// width, height and which cells of the bounding box are alive, row by row
+type Shape = (i32, i32, Vec<bool>);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Velocity {
+    pub period: u32,
+    // per period
+    pub dx: i32,
+    pub dy: i32,
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// e.g. "c/4 diagonal, up-left" for the glider make_glider draws. Oblique
+// ones don't reduce, so a knightship is "(2,1)c/6 oblique" like on LifeWiki
+impl fmt::Display for Velocity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (across, along) = (self.dx.abs().min(self.dy.abs()), self.dx.abs().max(self.dy.abs()));
+        if across != 0 && across != along {
+            write!(f, "({},{})c/{} oblique", along, across, self.period)?;
+        } else {
+            let divisor = gcd(along as u32, self.period);
+            let (distance, period) = (along as u32 / divisor, self.period / divisor);
+            if distance != 1 {
+                write!(f, "{}", distance)?;
+            }
+            let heading = if across == 0 { "orthogonal" } else { "diagonal" };
+            write!(f, "c/{} {}", period, heading)?;
+        }
+        let vertical = match self.dy.signum() {
+            -1 => "up",
+            1 => "down",
+            _ => "",
+        };
+        let horizontal = match self.dx.signum() {
+            -1 => "left",
+            1 => "right",
+            _ => "",
+        };
+        match (vertical.is_empty(), horizontal.is_empty()) {
+            (false, false) => write!(f, ", {}-{}", vertical, horizontal),
+            (false, true) => write!(f, ", {}", vertical),
+            (true, false) => write!(f, ", {}", horizontal),
+            (true, true) => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Track {
+    pub id: u64,
+    pub code: String,
+    pub velocity: Velocity,
+    pub bounding_box: BoundingBox,
+    pub first_seen: u64,
+    pub last_seen: u64,
+    first_position: (i32, i32),
+    // a ship still being built can look finished for a generation or two, so
+    // it only counts once it has kept going for a whole period
+    confirmed: bool,
+}
+
+impl Track {
+    pub fn center(&self) -> (f32, f32) {
+        (
+            self.bounding_box.x as f32 + self.bounding_box.width as f32 / 2.0,
+            self.bounding_box.y as f32 + self.bounding_box.height as f32 / 2.0,
+        )
+    }
+}
+
+impl fmt::Display for Track {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = crate::census::name(&self.code).unwrap_or(&self.code);
+        write!(
+            f,
+            "#{} {} {} at ({},{})",
+            self.id, name, self.velocity, self.bounding_box.x, self.bounding_box.y
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TrackEvent {
+    /// a spaceship separated from whatever made it, or was there from the start.
+    /// Only reported once it has flown for a period, but generation and position
+    /// are from when it was first seen
+    Emitted { id: u64, code: String, generation: u64, position: (i32, i32) },
+    /// ran into something and stopped being a spaceship. Generation is the last
+    /// one it was still flying in
+    Collided { id: u64, code: String, generation: u64, position: (i32, i32) },
+    /// flew into the edge of the universe
+    LeftUniverse { id: u64, code: String, generation: u64, position: (i32, i32) },
+}
+
+impl TrackEvent {
+    pub fn id(&self) -> u64 {
+        match self {
+            TrackEvent::Emitted { id, .. }
+            | TrackEvent::Collided { id, .. }
+            | TrackEvent::LeftUniverse { id, .. } => *id,
+        }
+    }
+}
+
+impl fmt::Display for TrackEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (what, id, code, generation, position) = match self {
+            TrackEvent::Emitted { id, code, generation, position } => ("emitted", id, code, generation, position),
+            TrackEvent::Collided { id, code, generation, position } => ("collided", id, code, generation, position),
+            TrackEvent::LeftUniverse { id, code, generation, position } => ("left the universe", id, code, generation, position),
+        };
+        let name = crate::census::name(code).unwrap_or(code);
+        write!(
+            f,
+            "Generation {}: #{} {} {} at ({},{})",
+            generation, id, name, what, position.0, position.1
+        )
+    }
+}
+
+// a spaceship this generation, not matched up with a track yet
+struct Sighting {
+    code: String,
+    velocity: Velocity,
+    bounding_box: BoundingBox,
+}
+
+pub struct SpaceshipTracker {
+    tracks: Vec<Track>,
+    events: Vec<TrackEvent>,
+    next_id: u64,
+    // the same few phases of the same few ships come up over and over
+    shapes: HashMap<Shape, Option<(String, Velocity)>>,
+}
+
+impl SpaceshipTracker {
+    pub fn new() -> Self {
+        SpaceshipTracker {
+            tracks: Vec::new(),
+            events: Vec::new(),
+            next_id: 0,
+            shapes: HashMap::new(),
+        }
+    }
+    pub fn tracks(&self) -> impl Iterator<Item = &Track> {
+        self.tracks.iter().filter(|track| track.confirmed)
+    }
+    /// Most recent MAX_EVENTS events, oldest first
+    pub fn events(&self) -> &[TrackEvent] {
+        &self.events
+    }
+    // for a new seed
+    pub fn reset(&mut self) {
+        self.tracks.clear();
+        self.events.clear();
+        self.shapes.clear();
+    }
+
+    fn classify_shape(&mut self, cells: &[(i32, i32)], bounding_box: BoundingBox) -> Option<(String, Velocity)> {
+        let mut shape = vec![false; (bounding_box.width * bounding_box.height) as usize];
+        for &(i, j) in cells.iter() {
+            shape[((j - bounding_box.y) * bounding_box.width + i - bounding_box.x) as usize] = true;
+        }
+        if self.shapes.len() >= MAX_CACHED_SHAPES {
+            self.shapes.clear();
+        }
+        self.shapes
+            .entry((bounding_box.width, bounding_box.height, shape))
+            .or_insert_with(|| match classify_up_to(cells, MAX_SHIP_PERIOD) {
+                (code, ObjectKind::Spaceship { period, dx, dy }) => Some((code, Velocity { period, dx, dy })),
+                _ => None,
+            })
+            .clone()
+    }
+
+    fn sightings(&mut self, vec: &BMatrixVector) -> Vec<Sighting> {
+        let mut sightings = Vec::new();
+        // within 2 so anything a ship is touching, or about to, counts as part of it
+        for cells in connected_components(vec, 2) {
+            if cells.len() > MAX_SHIP_POPULATION {
+                continue;
+            }
+            let bounding_box = bounding_box_of(&cells);
+            if bounding_box.width > MAX_SHIP_SIZE || bounding_box.height > MAX_SHIP_SIZE {
+                continue;
+            }
+            if let Some((code, velocity)) = self.classify_shape(&cells, bounding_box) {
+                sightings.push(Sighting {
+                    code,
+                    velocity,
+                    bounding_box,
+                });
+            }
+        }
+        sightings
+    }
+
+    fn record_events(&mut self, new_events: &[TrackEvent]) {
+        self.events.extend_from_slice(new_events);
+        let excess = self.events.len().saturating_sub(MAX_EVENTS);
+        self.events.drain(..excess);
+    }
+
+    /// Matches up the spaceships in this generation with the ones seen before,
+    /// returning anything that happened in between. Works best when called every
+    /// generation, since ships only get matched if they didn't get too far
+    pub fn observe(&mut self, generation: u64, vec: &BMatrixVector) -> Vec<TrackEvent> {
+        let mut sightings: Vec<Option<Sighting>> = self.sightings(vec).into_iter().map(Some).collect();
+        let mut new_events = Vec::new();
+        let mut lost = Vec::new();
+        for mut track in std::mem::take(&mut self.tracks) {
+            let elapsed = generation.saturating_sub(track.last_seen);
+            let reach = elapsed.min(i32::MAX as u64) as i32 + MATCH_SLACK;
+            let closest = sightings
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, sighting)| sighting.as_ref().map(|sighting| (idx, sighting)))
+                .filter(|(_, sighting)| sighting.code == track.code)
+                .map(|(idx, sighting)| {
+                    let distance = (sighting.bounding_box.x - track.bounding_box.x)
+                        .abs()
+                        .max((sighting.bounding_box.y - track.bounding_box.y).abs());
+                    (idx, distance)
+                })
+                .filter(|&(_, distance)| distance <= reach)
+                .min_by_key(|&(_, distance)| distance);
+            match closest {
+                Some((idx, _)) => {
+                    let sighting = sightings[idx].take().unwrap();
+                    track.bounding_box = sighting.bounding_box;
+                    track.last_seen = generation;
+                    if !track.confirmed && generation - track.first_seen >= track.velocity.period as u64 {
+                        track.confirmed = true;
+                        new_events.push(TrackEvent::Emitted {
+                            id: track.id,
+                            code: track.code.clone(),
+                            generation: track.first_seen,
+                            position: track.first_position,
+                        });
+                    }
+                    self.tracks.push(track);
+                }
+                // never was a ship then
+                None if !track.confirmed => (),
+                None => {
+                    let at_edge = track.bounding_box.x <= reach
+                        || track.bounding_box.y <= reach
+                        || track.bounding_box.x + track.bounding_box.width >= vec.width() - reach
+                        || track.bounding_box.y + track.bounding_box.height >= vec.height() - reach;
+                    // Ships can brush past something without hitting it, getting lumped
+                    // in with it for a few generations. Give it a period to come back out
+                    if at_edge || elapsed > track.velocity.period as u64 {
+                        lost.push((track, at_edge));
+                    } else {
+                        self.tracks.push(track);
+                    }
+                }
+            }
+        }
+
+        for (track, at_edge) in lost {
+            let position = (track.bounding_box.x, track.bounding_box.y);
+            let (id, code, generation) = (track.id, track.code, track.last_seen);
+            new_events.push(if at_edge {
+                TrackEvent::LeftUniverse { id, code, generation, position }
+            } else {
+                TrackEvent::Collided { id, code, generation, position }
+            });
+        }
+
+        for sighting in sightings.into_iter().flatten() {
+            let id = self.next_id;
+            self.next_id += 1;
+            let position = (sighting.bounding_box.x, sighting.bounding_box.y);
+            self.tracks.push(Track {
+                id,
+                code: sighting.code,
+                velocity: sighting.velocity,
+                bounding_box: sighting.bounding_box,
+                first_seen: generation,
+                last_seen: generation,
+                first_position: position,
+                confirmed: false,
+            });
+        }
+        self.record_events(&new_events);
+        new_events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use std::mem;
+
+    const GOSPER_GUN: [&str; 9] = [
+        "........................O...........",
+        "......................O.O...........",
+        "............OO......OO............OO",
+        "...........O...O....OO............OO",
+        "OO........O.....O...OO..............",
+        "OO........O...O.OO....O.O...........",
+        "..........O.....O.......O...........",
+        "...........O...O....................",
+        "............OO......................",
+    ];
+
+    // observes every generation from 0 to generations
+    fn run(vec: BMatrixVector, generations: u64) -> (SpaceshipTracker, Vec<TrackEvent>) {
+        let mut tracker = SpaceshipTracker::new();
+        let mut engine = create_engine(Backend::Single);
+        let mut events = tracker.observe(0, &vec);
+        let mut old_vec = vec.clone();
+        let mut new_vec = vec;
+        for generation in 1..=generations {
+            engine.next_b_matrix(&old_vec, &mut new_vec);
+            mem::swap(&mut old_vec, &mut new_vec);
+            events.extend(tracker.observe(generation, &old_vec));
+        }
+        (tracker, events)
+    }
+
+    #[test]
+    fn test_Velocity_display() {
+        let glider = Velocity {
+            period: 4,
+            dx: -1,
+            dy: -1,
+        };
+        assert_eq!(glider.to_string(), "c/4 diagonal, up-left");
+        let lwss = Velocity {
+            period: 4,
+            dx: 2,
+            dy: 0,
+        };
+        assert_eq!(lwss.to_string(), "c/2 orthogonal, right");
+        let knightship = Velocity {
+            period: 6,
+            dx: 1,
+            dy: 2,
+        };
+        assert_eq!(knightship.to_string(), "(2,1)c/6 oblique, down-right");
+        let copperhead = Velocity {
+            period: 10,
+            dx: 0,
+            dy: -1,
+        };
+        assert_eq!(copperhead.to_string(), "c/10 orthogonal, up");
+    }
+
+    #[test]
+    fn test_SpaceshipTracker_follows_make_glider() {
+        let vec = crate::patterns::PatternBuilder::with_size(64, 64)
+            .make_glider(50, 50)
+            .build();
+        let (tracker, events) = run(vec, 40);
+        assert_eq!(events.len(), 1);
+        let track = tracker.tracks().next().unwrap();
+        assert_eq!(track.code, "xq4_153");
+        assert_eq!(track.velocity.to_string(), "c/4 diagonal, up-left");
+        let moved = (track.bounding_box.x - track.first_position.0, track.bounding_box.y - track.first_position.1);
+        assert_eq!(moved, (-10, -10));
+        assert_eq!(track.first_seen, 0);
+        assert_eq!(track.last_seen, 40);
+    }
+
+    #[test]
+    fn test_SpaceshipTracker_glider_leaves_universe() {
+        let vec = crate::patterns::PatternBuilder::with_size(32, 32)
+            .make_glider(10, 10)
+            .build();
+        let (tracker, events) = run(vec, 60);
+        assert_eq!(tracker.tracks().count(), 0);
+        match events.last() {
+            Some(TrackEvent::LeftUniverse { id: 0, .. }) => (),
+            other => panic!("expected glider to leave the universe, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_SpaceshipTracker_glider_hits_block() {
+        let mut vec = BMatrixVector::new(64, 64);
+        place(&mut vec, 40, 40, &["OOO", "O..", ".O."]);
+        // right in the glider's path
+        place(&mut vec, 30, 30, &["OO", "OO"]);
+        let (tracker, events) = run(vec, 60);
+        assert_eq!(tracker.tracks().count(), 0);
+        assert!(
+            events
+                .iter()
+                .any(|event| matches!(event, TrackEvent::Collided { id: 0, .. })),
+            "{:?}",
+            events
+        );
+    }
+
+    #[test]
+    fn test_SpaceshipTracker_gun_emits_gliders() {
+        let mut vec = BMatrixVector::new(120, 100);
+        place(&mut vec, 2, 2, &GOSPER_GUN);
+        let (tracker, events) = run(vec, 150);
+        let emitted: Vec<&TrackEvent> = events
+            .iter()
+            .filter(|event| matches!(event, TrackEvent::Emitted { .. }))
+            .collect();
+        // one every 30 generations, and gliders that are still being built don't count
+        assert_eq!(emitted.len(), 5, "{:?}", events);
+        for track in tracker.tracks() {
+            assert_eq!(track.velocity.to_string(), "c/4 diagonal, down-right");
+        }
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, TrackEvent::Collided { .. })));
+    }
+
+    #[test]
+    fn test_SpaceshipTracker_lwss_velocity() {
+        let mut vec = BMatrixVector::new(80, 20);
+        place(&mut vec, 60, 8, &[".O..O", "O....", "O...O", "OOOO."]);
+        let (tracker, _) = run(vec, 40);
+        let track = tracker.tracks().next().unwrap();
+        assert_eq!(track.velocity.to_string(), "c/2 orthogonal, left");
+        let moved = (track.bounding_box.x - track.first_position.0, track.bounding_box.y - track.first_position.1);
+        assert_eq!(moved, (-20, 0));
+    }
+}
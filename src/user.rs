@@ -19,287 +19,397 @@ impl Point {
     }
 }
 
-pub fn get_max_offset_x() -> f32 {
-    (GRID_SIZE - 1) as f32 * (CELL_SIZE + CELL_GAP) + CELL_SIZE - WINDOW_WIDTH as f32
+/// Size of the window in pixels. Starts out at WINDOW_WIDTH by WINDOW_HEIGHT,
+/// and changes whenever the window gets resized
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Screen {
+    pub width: f32,
+    pub height: f32,
 }
 
-pub fn get_max_offset_y() -> f32 {
-    (GRID_SIZE - 1) as f32 * (CELL_SIZE + CELL_GAP) + CELL_SIZE - WINDOW_HEIGHT as f32
+impl Screen {
+    pub fn new(width: f32, height: f32) -> Screen {
+        Screen { width, height }
+    }
+    // a window bigger than the grid can't scroll at all
+    pub fn max_offset_x(self) -> f32 {
+        ((GRID_SIZE - 1) as f32 * (CELL_SIZE + CELL_GAP) + CELL_SIZE - self.width).max(0.0)
+    }
+    pub fn max_offset_y(self) -> f32 {
+        ((GRID_SIZE - 1) as f32 * (CELL_SIZE + CELL_GAP) + CELL_SIZE - self.height).max(0.0)
+    }
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Screen::new(WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32)
+    }
+}
+
+// how quickly the camera catches up with whatever it is following, per second
+const FOLLOW_RATE: f32 = 5.0;
+
+/// What follow mode keeps in the middle of the screen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraTarget {
+    /// by its SpaceshipTracker id
+    Spaceship(u64),
+    /// average position of every live cell
+    Centroid,
+}
+
+// Offset that puts cell coordinates (not pixels) in the middle of the window
+pub fn offset_centered_on(cell_x: f32, cell_y: f32, screen: Screen) -> (f32, f32) {
+    (
+        cell_x * (CELL_SIZE + CELL_GAP) - screen.width / 2.0,
+        cell_y * (CELL_SIZE + CELL_GAP) - screen.height / 2.0,
+    )
+}
+
+/// Cell under the screen position (x,y), e.g. the mouse
+pub fn cell_at_screen(offset: Point, x: f32, y: f32) -> (i32, i32) {
+    (
+        ((offset.x + x) / (CELL_SIZE + CELL_GAP)).floor() as i32,
+        ((offset.y + y) / (CELL_SIZE + CELL_GAP)).floor() as i32,
+    )
+}
+
+/// Where region is on screen as (x, y, width, height), gaps around it included
+pub fn screen_rect_of(offset: Point, region: BoundingBox) -> (f32, f32, f32, f32) {
+    (
+        region.x as f32 * (CELL_SIZE + CELL_GAP) - CELL_GAP / 2.0 - offset.x,
+        region.y as f32 * (CELL_SIZE + CELL_GAP) - CELL_GAP / 2.0 - offset.y,
+        region.width as f32 * (CELL_SIZE + CELL_GAP),
+        region.height as f32 * (CELL_SIZE + CELL_GAP),
+    )
 }
 
-// NOTE: states do not represent when the offset reaches the corners
-// but rather when it reaches the max offset in the x and y direction
-#[derive(Clone, Copy)]
-pub enum OffsetState {
-    Inside(Point),
-    TopEdge(Point),
-    RightEdge(Point),
-    BottomEdge(Point),
-    LeftEdge(Point),
-    TopLeftCorner(Point),
-    TopRightCorner(Point),
-    BottomRightCorner(Point),
-    BottomLeftCorner(Point),
+// Fraction of the way to the target to move this frame. Exponential so it
+// comes out the same no matter the frame rate
+pub fn follow_fraction(seconds: f32) -> f32 {
+    1.0 - (-FOLLOW_RATE * seconds).exp()
+}
+
+// pixels a second the keys scroll at flat out, which is the 2 cells a frame
+// they used to move at 60 FPS
+const MAX_SPEED: f32 = 2.0 * CELL_SIZE * 60.0;
+// how long holding a key takes to get up to MAX_SPEED
+const RAMP_SECONDS: f32 = 0.2;
+// how quickly it slows down after letting go, per second
+const FRICTION: f32 = 6.0;
+// anything slower than this in pixels a second has stopped
+const STOP_SPEED: f32 = 1.0;
+// a fling with the mouse can go this much faster than the keys
+const MAX_FLING_SPEED: f32 = 4.0 * MAX_SPEED;
+// a frame longer than this (the window being dragged around, say) only
+// moves the camera as far as one this long would
+const MAX_FRAME_SECONDS: f32 = 0.1;
+// how much of the old window a page jump leaves on screen
+const PAGE_OVERLAP: f32 = 0.1;
+
+/// Where the window is on the grid, as the offset of its top left corner in
+/// pixels, and how fast that is moving. Always kept between 0 and the
+/// Screen's max offsets, and running into an edge stops it along that axis
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+    point: Point,
+    // pixels a second
+    velocity: (f32, f32),
+    // Some while the mouse is panning, with how far it has moved this frame
+    dragged: Option<(f32, f32)>,
 }
-impl Default for OffsetState {
+
+impl Default for Camera {
     fn default() -> Self {
-        OffsetState::TopLeftCorner(Point::new(0.0, 0.0))
+        Camera {
+            point: Point::new(0.0, 0.0),
+            velocity: (0.0, 0.0),
+            dragged: None,
+        }
     }
 }
 
-impl OffsetState {
+impl Camera {
+    /// Standing still at (x,y), clamped to the grid
+    pub fn at(x: f32, y: f32, screen: Screen) -> Camera {
+        let mut camera = Camera::default();
+        camera.move_by(x, y, screen);
+        camera.velocity = (0.0, 0.0);
+        camera
+    }
     pub fn get_point(&self) -> Point {
-        use OffsetState::*;
-        match self {
-            &Inside(ref point) => point.clone(),
-            &TopEdge(ref point) => point.clone(),
-            &RightEdge(ref point) => point.clone(),
-            &BottomEdge(ref point) => point.clone(),
-            &LeftEdge(ref point) => point.clone(),
-            &TopLeftCorner(ref point) => point.clone(),
-            &TopRightCorner(ref point) => point.clone(),
-            &BottomRightCorner(ref point) => point.clone(),
-            &BottomLeftCorner(ref point) => point.clone(),
-        }
+        self.point
     }
-    pub fn update(&mut self, ctx: &mut Context) {
-        if keyboard::is_key_pressed(ctx, KeyCode::Right) {
-            *self = transition_offset_state_right(*self);
-        }
-        if keyboard::is_key_pressed(ctx, KeyCode::Left) {
-            *self = transition_offset_state_left(*self);
-        }
-        if keyboard::is_key_pressed(ctx, KeyCode::Up) {
-            *self = transition_offset_state_up(*self);
-        }
-        if keyboard::is_key_pressed(ctx, KeyCode::Down) {
-            *self = transition_offset_state_down(*self);
+    /// Puts it back in bounds, after the window gets resized
+    pub fn clamp_to(&mut self, screen: Screen) {
+        self.move_by(0.0, 0.0, screen);
+    }
+    /// Moves `fraction` of the way towards offset (x,y), 1 jumps straight
+    /// there. Whatever it is following does the moving, so it stops coasting
+    pub fn approach(&self, x: f32, y: f32, fraction: f32, screen: Screen) -> Camera {
+        let point = self.point;
+        Camera::at(
+            point.x + (x - point.x) * fraction,
+            point.y + (y - point.y) * fraction,
+            screen,
+        )
+    }
+    /// Jumps a window's worth (less PAGE_OVERLAP) that many windows across
+    /// and down
+    pub fn page(&mut self, across: f32, down: f32, screen: Screen) {
+        let scale = 1.0 - PAGE_OVERLAP;
+        self.velocity = (0.0, 0.0);
+        self.move_by(across * screen.width * scale, down * screen.height * scale, screen);
+    }
+
+    /// The mouse has grabbed the grid
+    pub fn start_drag(&mut self) {
+        self.velocity = (0.0, 0.0);
+        self.dragged = Some((0.0, 0.0));
+    }
+    /// The mouse moved (dx,dy) while holding on, and the grid goes with it
+    pub fn drag(&mut self, dx: f32, dy: f32, screen: Screen) {
+        if let Some((x, y)) = self.dragged {
+            self.dragged = Some((x - dx, y - dy));
+            self.move_by(-dx, -dy, screen);
         }
+    }
+    /// Lets go, and it carries on at however fast it was being dragged
+    pub fn end_drag(&mut self) {
+        self.dragged = None;
+    }
 
-        //println!("Point: {:?}",self.get_point());
+    /// Moves on however long the last frame took, see step
+    pub fn update(&mut self, ctx: &Context, push: (f32, f32), screen: Screen) {
+        let seconds = timer::duration_to_f64(timer::delta(ctx)) as f32;
+        self.step(push, seconds, screen);
     }
-}
 
-fn transition_offset_state_right(state: OffsetState) -> OffsetState {
-    use OffsetState::*;
-    match state {
-        Inside(point) => {
-            let new_x = point.x + 2.0 * CELL_SIZE;
-            if get_max_offset_x() <= new_x {
-                let right_edge_point = Point::new(get_max_offset_x(), point.y);
-                RightEdge(right_edge_point)
-            } else {
-                let new_point = Point::new(new_x, point.y);
-                Inside(new_point)
-            }
+    /// Moves on `seconds`, with push saying which way the keys are held on
+    /// each axis, -1, 0 or 1. Held keys speed it up to MAX_SPEED, and once
+    /// let go of it slows down, both by time rather than frames
+    pub fn step(&mut self, push: (f32, f32), seconds: f32, screen: Screen) {
+        let seconds = seconds.min(MAX_FRAME_SECONDS);
+        if seconds <= 0.0 {
+            return;
         }
-        TopEdge(point) => {
-            let new_x = point.x + 2.0 * CELL_SIZE;
-            if get_max_offset_x() <= new_x {
-                let right_edge_point = Point::new(get_max_offset_x(), point.y);
-                TopRightCorner(right_edge_point)
-            } else {
-                let new_point = Point::new(new_x, point.y);
-                TopEdge(new_point)
-            }
+        if let Some((x, y)) = self.dragged {
+            // the mouse already moved it, this is just for when it lets go
+            let clamp = |speed: f32| speed.clamp(-MAX_FLING_SPEED, MAX_FLING_SPEED);
+            self.velocity = (clamp(x / seconds), clamp(y / seconds));
+            self.dragged = Some((0.0, 0.0));
+            return;
         }
-        BottomEdge(point) => {
-            let new_x = point.x + 2.0 * CELL_SIZE;
-            if get_max_offset_x() <= new_x {
-                let right_edge_point = Point::new(get_max_offset_x(), point.y);
-                BottomRightCorner(right_edge_point)
-            } else {
-                let new_point = Point::new(new_x, point.y);
-                BottomEdge(new_point)
+        let accelerate = |speed: f32, push: f32| {
+            if push == 0.0 {
+                let speed = speed * (-FRICTION * seconds).exp();
+                return if speed.abs() < STOP_SPEED { 0.0 } else { speed };
             }
-        }
-        LeftEdge(point) => {
-            // Assume window width is large than twice the cell size
-            let new_x = point.x + 2.0 * CELL_SIZE;
-            let new_point = Point::new(new_x, point.y);
-            Inside(new_point)
-        }
-        RightEdge(point) => RightEdge(point),
-        TopLeftCorner(point) => {
-            // Assume window width is large than twice the cell size
-            let new_x = point.x + 2.0 * CELL_SIZE;
-            let new_point = Point::new(new_x, point.y);
-            TopEdge(new_point)
-        }
-        TopRightCorner(point) => TopRightCorner(point),
-        BottomRightCorner(point) => BottomRightCorner(point),
-        BottomLeftCorner(point) => {
-            let new_x = point.x + 2.0 * CELL_SIZE;
-            let new_point = Point::new(new_x, point.y);
-            BottomEdge(new_point)
-        }
+            // turning round brakes as well as pushing
+            let speed = if speed * push < 0.0 { speed * (-FRICTION * seconds).exp() } else { speed };
+            (speed + push * MAX_SPEED / RAMP_SECONDS * seconds).clamp(-MAX_SPEED, MAX_SPEED)
+        };
+        let (vx, vy) = self.velocity;
+        self.velocity = (accelerate(vx, push.0), accelerate(vy, push.1));
+        self.move_by(self.velocity.0 * seconds, self.velocity.1 * seconds, screen);
     }
-}
-fn transition_offset_state_left(state: OffsetState) -> OffsetState {
-    use OffsetState::*;
-    match state {
-        Inside(point) => {
-            let new_x = point.x - 2.0 * CELL_SIZE;
-            if new_x < EPSILON {
-                let left_edge_point = Point::new(0.0, point.y);
-                LeftEdge(left_edge_point)
-            } else {
-                let new_point = Point::new(new_x, point.y);
-                Inside(new_point)
-            }
-        }
-        TopEdge(point) => {
-            let new_x = point.x - 2.0 * CELL_SIZE;
-            if new_x < EPSILON {
-                let left_edge_point = Point::new(0.0, point.y);
-                TopLeftCorner(left_edge_point)
-            } else {
-                let new_point = Point::new(new_x, point.y);
-                TopEdge(new_point)
-            }
-        }
-        BottomEdge(point) => {
-            let new_x = point.x - 2.0 * CELL_SIZE;
-            if new_x < EPSILON {
-                let left_edge_point = Point::new(0.0, point.y);
-                BottomLeftCorner(left_edge_point)
-            } else {
-                let new_point = Point::new(new_x, point.y);
-                BottomEdge(new_point)
+
+    // anything that runs into an edge stops along that axis
+    fn move_by(&mut self, dx: f32, dy: f32, screen: Screen) {
+        let clamp = |to: f32, max: f32, speed: &mut f32| {
+            if to <= 0.0 || to >= max {
+                *speed = 0.0;
             }
-        }
-        LeftEdge(point) => LeftEdge(point),
-        RightEdge(point) => {
-            // Assume window width is large than twice the cell size
-            let new_x = point.x - 2.0 * CELL_SIZE;
-            let new_point = Point::new(new_x, point.y);
-            Inside(new_point)
-        }
-        TopLeftCorner(point) => TopLeftCorner(point),
-        TopRightCorner(point) => {
-            // Assume window width is large than twice the cell size
-            let new_x = point.x - 2.0 * CELL_SIZE;
-            let new_point = Point::new(new_x, point.y);
-            TopEdge(new_point)
-        }
-        BottomRightCorner(point) => {
-            let new_x = point.x - 2.0 * CELL_SIZE;
-            let new_point = Point::new(new_x, point.y);
-            BottomEdge(new_point)
-        }
-        BottomLeftCorner(point) => BottomLeftCorner(point),
+            to.min(max).max(0.0)
+        };
+        let x = clamp(self.point.x + dx, screen.max_offset_x(), &mut self.velocity.0);
+        let y = clamp(self.point.y + dy, screen.max_offset_y(), &mut self.velocity.1);
+        self.point = Point::new(x, y);
     }
 }
-fn transition_offset_state_up(state: OffsetState) -> OffsetState {
-    use OffsetState::*;
-    match state {
-        Inside(point) => {
-            let new_y = point.y - 2.0 * CELL_SIZE;
-            if new_y < EPSILON {
-                let top_edge_point = Point::new(point.x, 0.0);
-                TopEdge(top_edge_point)
-            } else {
-                let new_point = Point::new(point.x, new_y);
-                Inside(new_point)
-            }
-        }
-        TopEdge(point) => TopEdge(point),
-        BottomEdge(point) => {
-            let new_y = point.y - 2.0 * CELL_SIZE;
-            let new_point = Point::new(point.x, new_y);
-            Inside(new_point)
-        }
-        LeftEdge(point) => {
-            let new_y = point.y - 2.0 * CELL_SIZE;
-            if new_y < EPSILON {
-                let top_edge_point = Point::new(point.x, 0.0);
-                TopLeftCorner(top_edge_point)
-            } else {
-                let new_point = Point::new(point.x, new_y);
-                LeftEdge(new_point)
-            }
-        }
-        RightEdge(point) => {
-            let new_y = point.y - 2.0 * CELL_SIZE;
-            if new_y < EPSILON {
-                let top_edge_point = Point::new(point.x, 0.0);
-                TopRightCorner(top_edge_point)
-            } else {
-                let new_point = Point::new(point.x, new_y);
-                RightEdge(new_point)
-            }
-        }
-        TopLeftCorner(point) => TopLeftCorner(point),
-        TopRightCorner(point) => TopRightCorner(point),
-        BottomRightCorner(point) => {
-            let new_y = point.y - 2.0 * CELL_SIZE;
-            let new_point = Point::new(point.x, new_y);
-            RightEdge(new_point)
-        }
-        BottomLeftCorner(point) => {
-            let new_y = point.y - 2.0 * CELL_SIZE;
-            let new_point = Point::new(point.x, new_y);
-            LeftEdge(new_point)
-        }
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::*;
+    use super::{MAX_FRAME_SECONDS, MAX_SPEED, PAGE_OVERLAP};
+
+    #[test]
+    fn test_Camera_at_clamps_to_the_grid() {
+        let screen = Screen::default();
+        let (max_x, max_y) = (screen.max_offset_x(), screen.max_offset_y());
+        let point_of = |x, y| {
+            let point = Camera::at(x, y, screen).get_point();
+            (point.x, point.y)
+        };
+        assert_eq!(point_of(100.0, 200.0), (100.0, 200.0));
+        assert_eq!(point_of(-50.0, -50.0), (0.0, 0.0));
+        assert_eq!(point_of(max_x + 500.0, 100.0), (max_x, 100.0));
+        assert_eq!(point_of(100.0, max_y + 500.0), (100.0, max_y));
     }
-}
-fn transition_offset_state_down(state: OffsetState) -> OffsetState {
-    use OffsetState::*;
-    match state {
-        Inside(point) => {
-            let new_y = point.y + 2.0 * CELL_SIZE;
-            if get_max_offset_y() < new_y {
-                let bottom_edge_point = Point::new(point.x, get_max_offset_y());
-                BottomEdge(bottom_edge_point)
-            } else {
-                let new_point = Point::new(point.x, new_y);
-                Inside(new_point)
-            }
-        }
-        TopEdge(point) => {
-            let new_y = point.y + 2.0 * CELL_SIZE;
-            let new_point = Point::new(point.x, new_y);
-            Inside(new_point)
-        }
-        BottomEdge(point) => BottomEdge(point),
-        LeftEdge(point) => {
-            let new_y = point.y + 2.0 * CELL_SIZE;
-            if get_max_offset_y() < new_y {
-                let bottom_edge_point = Point::new(point.x, get_max_offset_y());
-                BottomLeftCorner(bottom_edge_point)
-            } else {
-                let new_point = Point::new(point.x, new_y);
-                LeftEdge(new_point)
-            }
+
+    #[test]
+    fn test_Camera_approach() {
+        let screen = Screen::default();
+        let camera = Camera::at(100.0, 200.0, screen);
+        let halfway = camera.approach(300.0, 400.0, 0.5, screen).get_point();
+        assert_approx_eq!(halfway.x, 200.0);
+        assert_approx_eq!(halfway.y, 300.0);
+        let there = camera.approach(300.0, 400.0, 1.0, screen).get_point();
+        assert_approx_eq!(there.x, 300.0);
+        assert_approx_eq!(there.y, 400.0);
+        // following something into the corner stops at the corner
+        let corner = camera.approach(-1000.0, -1000.0, 1.0, screen).get_point();
+        assert_eq!((corner.x, corner.y), (0.0, 0.0));
+    }
+
+    // how far holding push for that long gets from the middle of the grid,
+    // at frames per second
+    fn distance_held(push: (f32, f32), seconds: f32, frames_per_second: u32) -> (Camera, f32) {
+        let screen = Screen::default();
+        let (x, y) = (screen.max_offset_x() / 2.0, screen.max_offset_y() / 2.0);
+        let mut camera = Camera::at(x, y, screen);
+        for _ in 0..(seconds * frames_per_second as f32).round() as u32 {
+            camera.step(push, 1.0 / frames_per_second as f32, screen);
         }
-        RightEdge(point) => {
-            let new_y = point.y + 2.0 * CELL_SIZE;
-            if get_max_offset_y() < new_y {
-                let bottom_edge_point = Point::new(point.x, get_max_offset_y());
-                BottomRightCorner(bottom_edge_point)
-            } else {
-                let new_point = Point::new(point.x, new_y);
-                RightEdge(new_point)
-            }
+        let point = camera.get_point();
+        (camera, (point.x - x).hypot(point.y - y))
+    }
+
+    #[test]
+    fn test_Camera_speed_does_not_depend_on_the_frame_rate() {
+        let (camera, at_60) = distance_held((1.0, 0.0), 1.0, 60);
+        let (_, at_30) = distance_held((1.0, 0.0), 1.0, 30);
+        let (_, at_144) = distance_held((1.0, 0.0), 1.0, 144);
+        assert!((at_30 - at_60).abs() < 0.02 * at_60, "{} at 30 FPS, {} at 60", at_30, at_60);
+        assert!((at_144 - at_60).abs() < 0.02 * at_60, "{} at 144 FPS, {} at 60", at_144, at_60);
+        // up to full speed by then, and only going right
+        assert_eq!(camera.velocity, (MAX_SPEED, 0.0));
+        // it takes a moment to get going
+        let (_, first_frame) = distance_held((0.0, 1.0), 1.0 / 60.0, 60);
+        assert!(first_frame < MAX_SPEED / 60.0);
+    }
+
+    #[test]
+    fn test_Camera_coasts_to_a_stop() {
+        let screen = Screen::default();
+        let (mut camera, _) = distance_held((-1.0, -1.0), 0.5, 60);
+        let let_go_at = camera.get_point();
+        for _ in 0..30 {
+            camera.step((0.0, 0.0), 1.0 / 60.0, screen);
         }
-        TopLeftCorner(point) => {
-            let new_y = point.y + 2.0 * CELL_SIZE;
-            let new_point = Point::new(point.x, new_y);
-            LeftEdge(new_point)
+        // still going the same way half a second after letting go
+        let (vx, vy) = camera.velocity;
+        assert!(vx < 0.0 && vy < 0.0 && vx.abs() < MAX_SPEED);
+        assert!(camera.get_point().x < let_go_at.x);
+        for _ in 0..600 {
+            camera.step((0.0, 0.0), 1.0 / 60.0, screen);
         }
-        TopRightCorner(point) => {
-            let new_y = point.y + 2.0 * CELL_SIZE;
-            let new_point = Point::new(point.x, new_y);
-            RightEdge(new_point)
+        assert_eq!(camera.velocity, (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_Camera_stops_at_the_edges() {
+        let screen = Screen::default();
+        let mut camera = Camera::at(10.0, 300.0, screen);
+        for _ in 0..30 {
+            camera.step((-1.0, 1.0), 1.0 / 60.0, screen);
         }
-        BottomRightCorner(point) => BottomRightCorner(point),
-        BottomLeftCorner(point) => BottomLeftCorner(point),
+        // ran into the left edge, but still going down
+        assert_eq!(camera.get_point().x, 0.0);
+        assert_eq!(camera.velocity.0, 0.0);
+        assert!(camera.velocity.1 > 0.0);
+        // and doesn't stick to it going the other way
+        camera.step((1.0, 0.0), 1.0 / 60.0, screen);
+        assert!(camera.get_point().x > 0.0);
+        // a huge frame only counts as MAX_FRAME_SECONDS
+        let before = camera.get_point();
+        camera.step((0.0, 0.0), 10.0, screen);
+        assert!(camera.get_point().y - before.y <= MAX_SPEED * MAX_FRAME_SECONDS);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::tests::*;
+    #[test]
+    fn test_Camera_drag_and_fling() {
+        let screen = Screen::default();
+        let mut camera = Camera::at(500.0, 500.0, screen);
+        camera.start_drag();
+        // the grid follows the mouse, so the offset goes the other way
+        camera.drag(30.0, -10.0, screen);
+        let point = camera.get_point();
+        assert_eq!((point.x, point.y), (470.0, 510.0));
+        camera.step((0.0, 0.0), 0.1, screen);
+        assert_approx_eq!(camera.velocity.0, -300.0);
+        assert_approx_eq!(camera.velocity.1, 100.0);
+        // dragging doesn't scroll on its own
+        assert_eq!(camera.get_point().x, 470.0);
+        camera.end_drag();
+        camera.step((0.0, 0.0), 0.1, screen);
+        assert!(camera.get_point().x < 470.0);
+        // held still before letting go, so it stays put
+        camera.start_drag();
+        camera.step((0.0, 0.0), 0.1, screen);
+        camera.end_drag();
+        let held = camera.get_point();
+        camera.step((0.0, 0.0), 0.1, screen);
+        assert_eq!(camera.get_point().x, held.x);
+        assert!(camera.dragged.is_none());
+    }
+
+    #[test]
+    fn test_Camera_page() {
+        let screen = Screen::default();
+        let mut camera = Camera::at(1000.0, 1000.0, screen);
+        camera.page(1.0, 0.0, screen);
+        assert_approx_eq!(camera.get_point().x, 1000.0 + screen.width * (1.0 - PAGE_OVERLAP), 1e-2f32);
+        camera.page(0.0, -100.0, screen);
+        assert_eq!(camera.get_point().y, 0.0);
+    }
+
+    #[test]
+    fn test_Screen_resizing_moves_the_offset_bounds() {
+        let screen = Screen::default();
+        let wider = Screen::new(screen.width + 200.0, screen.height);
+        assert_approx_eq!(wider.max_offset_x(), screen.max_offset_x() - 200.0);
+        assert_approx_eq!(wider.max_offset_y(), screen.max_offset_y());
+        // widening the window pulls an offset at the right edge back in
+        let mut camera = Camera::at(screen.max_offset_x(), 100.0, screen);
+        camera.clamp_to(wider);
+        assert_approx_eq!(camera.get_point().x, wider.max_offset_x());
+        // and shrinking it back leaves it there, it is in bounds either way
+        camera.clamp_to(screen);
+        assert_approx_eq!(camera.get_point().x, wider.max_offset_x());
+        // bigger than the whole grid, so it can't go anywhere
+        let huge = Screen::new(1.0e6, 1.0e6);
+        assert_eq!((huge.max_offset_x(), huge.max_offset_y()), (0.0, 0.0));
+        let mut camera = Camera::at(50.0, 50.0, huge);
+        camera.step((1.0, 1.0), 0.1, huge);
+        let point = camera.get_point();
+        assert_eq!((point.x, point.y), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_cell_at_screen_round_trips_through_screen_rect_of() {
+        let offset = Point::new(3.5 * (CELL_SIZE + CELL_GAP), 0.0);
+        // half way into the cell at the left edge of the window
+        assert_eq!(user::cell_at_screen(offset, 0.0, CELL_SIZE / 2.0), (3, 0));
+        assert_eq!(user::cell_at_screen(offset, CELL_SIZE + CELL_GAP, 0.0), (4, 0));
+        let region = BoundingBox::spanning((4, 2), (6, 5));
+        let (x, y, width, height) = user::screen_rect_of(offset, region);
+        assert_eq!(user::cell_at_screen(offset, x + CELL_GAP, y + CELL_GAP), (4, 2));
+        assert_eq!(
+            user::cell_at_screen(offset, x + width - CELL_GAP, y + height - CELL_GAP),
+            (6, 5)
+        );
+    }
+
+    #[test]
+    fn test_follow_fraction() {
+        assert_eq!(user::follow_fraction(0.0), 0.0);
+        // two half frames get as far as one whole one
+        let half = user::follow_fraction(0.008);
+        let whole = user::follow_fraction(0.016);
+        assert_approx_eq!(1.0 - (1.0 - half) * (1.0 - half), whole);
+        assert!(user::follow_fraction(10.0) > 0.99);
+    }
     #[test]
     #[ignore]
     fn test_transition_bottom_right_corner() {
@@ -322,8 +432,8 @@ mod tests {
             .unwrap()
             .init_seed(init_b_matrix_vector)
             .init_offset(
-                user::get_max_offset_x() - 5.0,
-                user::get_max_offset_y() - 5.0,
+                Screen::default().max_offset_x() - 5.0,
+                Screen::default().max_offset_y() - 5.0,
             );
         event::run(ctx, event_loop, state);
     }